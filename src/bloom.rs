@@ -0,0 +1,269 @@
+use crate::texture::Texture;
+use crate::stats;
+
+/// 泛光mipmap链的最大级数
+const MAX_MIP_LEVELS: u32 = 6;
+
+/// 泛光相关uniform变量，与bloom.wgsl中的`BloomUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BloomUniform {
+  threshold: f32,
+  knee: f32,
+  intensity: f32,
+  filter_radius: f32,
+}
+
+/// 基于mipmap链的泛光
+///
+/// 场景中亮度超过阈值的部分先降采样到半分辨率的第0级，再逐级降采样，
+/// 然后从最小一级开始逐级升采样并叠加回上一级，最后加到场景颜色上；
+pub struct Bloom {
+  uniform: BloomUniform,
+  buffer: stats::Buffer,
+  layout: wgpu::BindGroupLayout,
+  composite_layout: wgpu::BindGroupLayout,
+  prefilter_pipeline: wgpu::RenderPipeline,
+  downsample_pipeline: wgpu::RenderPipeline,
+  upsample_pipeline: wgpu::RenderPipeline,
+  composite_pipeline: wgpu::RenderPipeline,
+  resources: BloomResources,
+}
+
+/// 与窗口尺寸相关的资源
+struct BloomResources {
+  /// 泛光mipmap链，只通过各级视图访问，持有以保证其存活
+  _texture: Texture,
+  mip_views: Vec<wgpu::TextureView>,
+  /// 以第i级为输入的bind group
+  mip_groups: Vec<wgpu::BindGroup>,
+  /// 以后处理链的第i张纹理为输入
+  prefilter_groups: Vec<wgpu::BindGroup>,
+  composite_groups: Vec<wgpu::BindGroup>,
+}
+
+impl Bloom {
+  /// `inputs`为后处理链中交替读写的纹理
+  pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, inputs: &[Texture], threshold: f32, intensity: f32) -> Self {
+    let uniform = BloomUniform {
+      threshold,
+      knee: 0.5,
+      intensity,
+      filter_radius: 0.005,
+    };
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Bloom buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let mut entries = vec![
+      wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+          multisampled: false,
+          view_dimension: wgpu::TextureViewDimension::D2,
+          sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None
+      },
+      wgpu::BindGroupLayoutEntry {
+        binding: 1,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None
+      },
+      wgpu::BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+          ty: wgpu::BufferBindingType::Uniform,
+          has_dynamic_offset: false,
+          min_binding_size: None
+        },
+        count: None
+      }
+    ];
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("bloom bind group layout"),
+      entries: &entries,
+    });
+    entries.push(wgpu::BindGroupLayoutEntry {
+      binding: 3,
+      visibility: wgpu::ShaderStages::FRAGMENT,
+      ty: wgpu::BindingType::Texture {
+        multisampled: false,
+        view_dimension: wgpu::TextureViewDimension::D2,
+        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+      },
+      count: None
+    });
+    let composite_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("bloom composite bind group layout"),
+      entries: &entries,
+    });
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+      label: Some("Bloom Shader"),
+      source: wgpu::ShaderSource::Wgsl(include_str!("bloom.wgsl").into())
+    });
+    let additive = wgpu::BlendState {
+      color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+      },
+      alpha: wgpu::BlendComponent::REPLACE,
+    };
+    let prefilter_pipeline = create_pipeline(device, &layout, &shader, "fs_prefilter", None);
+    let downsample_pipeline = create_pipeline(device, &layout, &shader, "fs_downsample", None);
+    let upsample_pipeline = create_pipeline(device, &layout, &shader, "fs_upsample", Some(additive));
+    let composite_pipeline = create_pipeline(device, &composite_layout, &shader, "fs_composite", None);
+    let resources = BloomResources::new(device, config, inputs, &layout, &composite_layout, &buffer);
+    Self {
+      uniform,
+      buffer,
+      layout,
+      composite_layout,
+      prefilter_pipeline,
+      downsample_pipeline,
+      upsample_pipeline,
+      composite_pipeline,
+      resources,
+    }
+  }
+
+  /// 窗口尺寸变化后重建mipmap链，`inputs`为重建后的后处理纹理
+  pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, inputs: &[Texture]) {
+    self.resources = BloomResources::new(device, config, inputs, &self.layout, &self.composite_layout, &self.buffer);
+  }
+
+  /// 修改亮度阈值和泛光强度
+  pub fn set_params(&mut self, threshold: f32, intensity: f32, queue: &wgpu::Queue) {
+    self.uniform.threshold = threshold.max(0.0);
+    self.uniform.intensity = intensity.max(0.0);
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+
+  /// 以后处理链的第`input`张纹理为输入，叠加泛光后写入`output`
+  pub fn run(&self, encoder: &mut wgpu::CommandEncoder, input: usize, output: &wgpu::TextureView) {
+    let resources = &self.resources;
+    draw(encoder, &self.prefilter_pipeline, &resources.prefilter_groups[input], &resources.mip_views[0], true);
+    for mip in 1..resources.mip_views.len() {
+      draw(encoder, &self.downsample_pipeline, &resources.mip_groups[mip - 1], &resources.mip_views[mip], true);
+    }
+    for mip in (1..resources.mip_views.len()).rev() {
+      draw(encoder, &self.upsample_pipeline, &resources.mip_groups[mip], &resources.mip_views[mip - 1], false);
+    }
+    draw(encoder, &self.composite_pipeline, &resources.composite_groups[input], output, true);
+  }
+}
+
+impl BloomResources {
+  fn new(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    inputs: &[Texture],
+    layout: &wgpu::BindGroupLayout,
+    composite_layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+  ) -> Self {
+    let width = (config.width / 2).max(1);
+    let height = (config.height / 2).max(1);
+    // 最小一级的边长不小于4个像素
+    let mip_level_count = (32 - width.min(height).max(4).leading_zeros() - 2).clamp(1, MAX_MIP_LEVELS);
+    let texture = Texture::create_render_target(device, width, height, mip_level_count, Texture::HDR_FORMAT, "bloom_texture");
+    let mip_views = (0..mip_level_count).map(|mip| texture.mip_view(mip)).collect::<Vec<_>>();
+    let create_group = |view: &wgpu::TextureView, bloom_view: Option<&wgpu::TextureView>| {
+      let mut entries = vec![
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: wgpu::BindingResource::TextureView(view)
+        },
+        wgpu::BindGroupEntry {
+          binding: 1,
+          resource: wgpu::BindingResource::Sampler(&texture.sampler)
+        },
+        wgpu::BindGroupEntry {
+          binding: 2,
+          resource: buffer.as_entire_binding()
+        }
+      ];
+      if let Some(bloom_view) = bloom_view {
+        entries.push(wgpu::BindGroupEntry {
+          binding: 3,
+          resource: wgpu::BindingResource::TextureView(bloom_view)
+        });
+      }
+      device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("bloom bind group"),
+        layout: if bloom_view.is_some() { composite_layout } else { layout },
+        entries: &entries,
+      })
+    };
+    let mip_groups = mip_views.iter().map(|view| create_group(view, None)).collect();
+    let prefilter_groups = inputs.iter().map(|input| create_group(&input.view, None)).collect();
+    let composite_groups = inputs.iter().map(|input| create_group(&input.view, Some(&mip_views[0]))).collect();
+    Self {
+      _texture: texture,
+      mip_views,
+      mip_groups,
+      prefilter_groups,
+      composite_groups,
+    }
+  }
+}
+
+fn create_pipeline(
+  device: &wgpu::Device,
+  layout: &wgpu::BindGroupLayout,
+  shader: &wgpu::ShaderModule,
+  entry_point: &str,
+  blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+  let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    label: Some("Bloom Pipeline Layout"),
+    bind_group_layouts: &[layout],
+    push_constant_ranges: &[]
+  });
+  device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    label: Some("Bloom Pipeline"),
+    layout: Some(&pipeline_layout),
+    vertex: wgpu::VertexState {
+      module: shader,
+      entry_point: "vs_main",
+      buffers: &[]
+    },
+    fragment: Some(wgpu::FragmentState {
+      module: shader,
+      entry_point,
+      targets: &[wgpu::ColorTargetState {
+        format: Texture::HDR_FORMAT,
+        blend,
+        write_mask: wgpu::ColorWrites::ALL,
+      }],
+    }),
+    primitive: wgpu::PrimitiveState::default(),
+    depth_stencil: None,
+    multisample: wgpu::MultisampleState::default(),
+    multiview: None
+  })
+}
+
+/// 绘制一个全屏三角形；`clear`为false时保留目标原有内容（用于加法混合）
+fn draw(encoder: &mut wgpu::CommandEncoder, pipeline: &wgpu::RenderPipeline, group: &wgpu::BindGroup, output: &wgpu::TextureView, clear: bool) {
+  let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+    label: Some("Bloom Pass"),
+    color_attachments: &[wgpu::RenderPassColorAttachment {
+      view: output,
+      resolve_target: None,
+      ops: wgpu::Operations {
+        load: if clear { wgpu::LoadOp::Clear(wgpu::Color::BLACK) } else { wgpu::LoadOp::Load },
+        store: true,
+      }
+    }],
+    depth_stencil_attachment: None
+  });
+  render_pass.set_pipeline(pipeline);
+  render_pass.set_bind_group(0, group, &[]);
+  render_pass.draw(0..3, 0..1);
+}
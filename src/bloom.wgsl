@@ -0,0 +1,98 @@
+// 泛光：亮度提取 -> 逐级降采样 -> 逐级升采样叠加 -> 与场景相加
+
+struct VertexOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+  [[location(0)]] uv: vec2<f32>;
+};
+
+struct BloomUniform {
+  threshold: f32;
+  /// 阈值附近的软过渡宽度
+  knee: f32;
+  intensity: f32;
+  /// 升采样滤波半径（uv单位）
+  filter_radius: f32;
+};
+
+[[group(0), binding(0)]]
+var source_t: texture_2d<f32>;
+[[group(0), binding(1)]]
+var source_s: sampler;
+[[group(0), binding(2)]]
+var<uniform> bloom: BloomUniform;
+/// 仅在合成pass中使用
+[[group(0), binding(3)]]
+var bloom_t: texture_2d<f32>;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] in_vertex_index: u32) -> VertexOutput {
+  var out: VertexOutput;
+  let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+  let y = f32(i32(in_vertex_index >> 1u) * 4 - 1);
+  out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+  out.uv = vec2<f32>(x * 0.5 + 0.5, 0.5 - y * 0.5);
+  return out;
+}
+
+fn sample_offset(uv: vec2<f32>, texel: vec2<f32>, x: f32, y: f32) -> vec3<f32> {
+  return textureSampleLevel(source_t, source_s, uv + texel * vec2<f32>(x, y), 0.0).rgb;
+}
+
+/// 13次采样的降采样滤波（Jimenez 2014），可避免闪烁
+fn downsample13(uv: vec2<f32>) -> vec3<f32> {
+  let texel = 1.0 / vec2<f32>(textureDimensions(source_t, 0));
+  let a = sample_offset(uv, texel, -2.0, -2.0);
+  let b = sample_offset(uv, texel, 0.0, -2.0);
+  let c = sample_offset(uv, texel, 2.0, -2.0);
+  let d = sample_offset(uv, texel, -2.0, 0.0);
+  let e = sample_offset(uv, texel, 0.0, 0.0);
+  let f = sample_offset(uv, texel, 2.0, 0.0);
+  let g = sample_offset(uv, texel, -2.0, 2.0);
+  let h = sample_offset(uv, texel, 0.0, 2.0);
+  let i = sample_offset(uv, texel, 2.0, 2.0);
+  let j = sample_offset(uv, texel, -1.0, -1.0);
+  let k = sample_offset(uv, texel, 1.0, -1.0);
+  let l = sample_offset(uv, texel, -1.0, 1.0);
+  let m = sample_offset(uv, texel, 1.0, 1.0);
+  return e * 0.125 + (a + c + g + i) * 0.03125 + (b + d + f + h) * 0.0625 + (j + k + l + m) * 0.125;
+}
+
+/// 亮度提取并降采样到泛光mipmap的第0级
+[[stage(fragment)]]
+fn fs_prefilter(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let color = min(downsample13(in.uv), vec3<f32>(65000.0));
+  let brightness = max(color.r, max(color.g, color.b));
+  var soft = clamp(brightness - bloom.threshold + bloom.knee, 0.0, 2.0 * bloom.knee);
+  soft = soft * soft / (4.0 * bloom.knee + 0.0001);
+  let contribution = max(soft, brightness - bloom.threshold) / max(brightness, 0.0001);
+  return vec4<f32>(color * contribution, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_downsample(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  return vec4<f32>(downsample13(in.uv), 1.0);
+}
+
+/// 3x3帐篷滤波升采样，结果以加法混合叠加到上一级
+[[stage(fragment)]]
+fn fs_upsample(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let r = vec2<f32>(bloom.filter_radius);
+  let a = sample_offset(in.uv, r, -1.0, 1.0);
+  let b = sample_offset(in.uv, r, 0.0, 1.0);
+  let c = sample_offset(in.uv, r, 1.0, 1.0);
+  let d = sample_offset(in.uv, r, -1.0, 0.0);
+  let e = sample_offset(in.uv, r, 0.0, 0.0);
+  let f = sample_offset(in.uv, r, 1.0, 0.0);
+  let g = sample_offset(in.uv, r, -1.0, -1.0);
+  let h = sample_offset(in.uv, r, 0.0, -1.0);
+  let i = sample_offset(in.uv, r, 1.0, -1.0);
+  return vec4<f32>((e * 4.0 + (b + d + f + h) * 2.0 + (a + c + g + i)) / 16.0, 1.0);
+}
+
+/// 将泛光叠加到场景上
+[[stage(fragment)]]
+fn fs_composite(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let color = textureSampleLevel(source_t, source_s, in.uv, 0.0);
+  let glow = textureSampleLevel(bloom_t, source_s, in.uv, 0.0).rgb;
+  return vec4<f32>(color.rgb + glow * bloom.intensity, color.a);
+}
@@ -1,10 +1,9 @@
 use cgmath::SquareMatrix;
-use wgpu::util::DeviceExt;
-use winit::{
-  event::*
-};
+use crate::shape::BoundingSphere;
+use crate::stats;
 
 /// 相机
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Camera {
   pub eye: cgmath::Point3<f32>,
   pub lookat: cgmath::Point3<f32>,
@@ -20,21 +19,18 @@ pub struct Camera {
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct CameraUniform {
   /// 视图投影矩阵
-  view_projection: [[f32; 4]; 4] // 4x4矩阵
+  view_projection: [[f32; 4]; 4], // 4x4矩阵
+  /// 相机位置（世界坐标，w分量无意义），用于计算视线方向
+  eye_position: [f32; 4],
 }
 
 pub struct CameraInfo {
   pub uniform: CameraUniform,
-  pub buffer: wgpu::Buffer,
+  pub buffer: stats::Buffer,
   pub group: wgpu::BindGroup,
   pub layout: wgpu::BindGroupLayout
 }
 
-pub struct CameraController<'a> {
-  camera: &'a mut Camera,
-  info: &'a mut CameraInfo,
-}
-
 /// 用于将openGL NDC（标准化设备坐标）中的z从[-1, 1]映射到[0, 1]（Vulkan和Metal）
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
@@ -45,11 +41,17 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
 );
 
 impl Camera {
-  fn get_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+  pub fn get_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
     let view = cgmath::Matrix4::look_at_rh(self.eye, self.lookat, self.up); // 视图变换矩阵
+
+    self.get_projection_matrix() * view
+  }
+
+  /// 投影矩阵（已映射到wgpu的NDC）
+  pub fn get_projection_matrix(&self) -> cgmath::Matrix4<f32> {
     let projection = cgmath::perspective(cgmath::Deg(self.fov), self.aspect, self.near, self.far); // 透视投影矩阵
 
-    OPENGL_TO_WGPU_MATRIX * projection * view
+    OPENGL_TO_WGPU_MATRIX * projection
   }
 
   /// 将相机位置围绕`lookat`旋转
@@ -65,17 +67,38 @@ impl Camera {
     let delta = move_dir * delta_dist;
     self.eye += delta;
   }
+
+  /// 保持视线方向不变，移动相机使包围球恰好充满视野
+  pub fn frame(&mut self, target: &BoundingSphere) {
+    let half_fov_y = cgmath::Deg(self.fov / 2.0);
+    let half_fov_x = cgmath::Rad((cgmath::Angle::tan(half_fov_y) * self.aspect).atan());
+    let half_fov = cgmath::Rad::from(half_fov_y).0.min(half_fov_x.0); // 取水平、竖直视角中较小者
+    let distance = target.radius.max(0.01) / half_fov.sin();
+    let direction = cgmath::InnerSpace::normalize(self.eye - self.lookat);
+    self.lookat = target.center;
+    self.eye = target.center + direction * distance;
+  }
+
+  /// 根据场景包围球设置近/远平面，使整个场景都在深度范围内
+  pub fn fit_depth(&mut self, scene: &BoundingSphere) {
+    let distance = cgmath::MetricSpace::distance(self.eye, scene.center);
+    self.far = (distance + scene.radius) * 1.01;
+    // 近平面不宜过小，否则深度精度不足
+    self.near = (distance - scene.radius).max(self.far / 1000.0).max(0.01);
+  }
 }
 
 impl CameraUniform {
   fn new() -> Self {
     Self {
-      view_projection: cgmath::Matrix4::identity().into()
+      view_projection: cgmath::Matrix4::identity().into(),
+      eye_position: [0.0; 4],
     }
   }
 
   fn update_matrix(&mut self, camera: &Camera) {
     self.view_projection = camera.get_view_projection_matrix().into();
+    self.eye_position = camera.eye.to_homogeneous().into();
   }
 }
 
@@ -83,7 +106,7 @@ impl CameraInfo {
   fn get_info(camera: &Camera, device: &wgpu::Device) -> Self {
     let mut uniform = CameraUniform::new();
     uniform.update_matrix(camera);
-    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
       label: Some("Camera buffer"),
       contents: bytemuck::cast_slice(&[uniform]),
       usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
@@ -93,14 +116,14 @@ impl CameraInfo {
       entries: &[
         wgpu::BindGroupLayoutEntry {
           binding: 0,
-          visibility: wgpu::ShaderStages::VERTEX,
+          visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
           ty: wgpu::BindingType::Buffer {
             ty: wgpu::BufferBindingType::Uniform,
             has_dynamic_offset: false,
             min_binding_size: None
           },
           count: None
-        } // 绑定到group的索引0位置，且只在顶点着色器可见
+        } // 绑定到group的索引0位置，顶点和片元着色器可见
       ],
     });
     let group = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -133,47 +156,3 @@ impl CameraInfo {
     CameraInfo::get_info(camera, device)
   }
 }
-
-impl<'a> CameraController<'a> {
-  pub fn new(camera: &'a mut Camera, info: &'a mut CameraInfo) -> Self {
-    Self {
-      camera,
-      info,
-    }
-  }
-
-  pub fn watch_event(&self, event: &WindowEvent) -> bool {
-    match event {
-      WindowEvent::KeyboardInput {
-        input: KeyboardInput {
-          state: ElementState::Pressed,
-          virtual_keycode: Some(VirtualKeyCode::Left), // 左方向
-          ..
-        },
-        ..
-      } => {
-        true
-      },
-      WindowEvent::KeyboardInput {
-        input: KeyboardInput {
-          state: ElementState::Pressed,
-          virtual_keycode: Some(VirtualKeyCode::Right), // 右方向
-          ..
-        },
-        ..
-      } => {
-        true
-      },
-      WindowEvent::MouseWheel { // 鼠标滚动
-        delta,
-        phase,
-        ..
-      } => {
-        println!("{:#?}", delta);
-        println!("{:#?}", phase);
-        true
-      },
-      _ => false
-    }
-  }
-}
@@ -0,0 +1,394 @@
+use std::future::Future;
+use std::pin::Pin;
+use cgmath::prelude::*;
+use crate::shape::{
+  BoundingSphere,
+  InstanceData
+};
+use crate::stats;
+
+/// 视锥体的6个平面，法线指向视锥体内部，已归一化
+#[derive(Debug, Copy, Clone)]
+pub struct Frustum {
+  pub planes: [cgmath::Vector4<f32>; 6],
+}
+
+impl Frustum {
+  /// 由视图投影矩阵提取平面（Gribb-Hartmann方法，NDC的z范围为[0, 1]）
+  pub fn from_matrix(view_projection: &cgmath::Matrix4<f32>) -> Self {
+    let m = view_projection;
+    let row = |i: usize| cgmath::Vector4::new(m.x[i], m.y[i], m.z[i], m.w[i]);
+    let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+    let planes = [
+      r3 + r0, // 左
+      r3 - r0, // 右
+      r3 + r1, // 下
+      r3 - r1, // 上
+      r2, // 近
+      r3 - r2, // 远
+    ].map(|plane| plane / plane.truncate().magnitude());
+    Self { planes }
+  }
+
+  /// 包围球是否与视锥体相交（保守判断，可能把视锥体角落外的少量物体判为可见）
+  pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+    let center = sphere.center.to_vec().extend(1.0);
+    self.planes.iter().all(|plane| plane.dot(center) >= -sphere.radius)
+  }
+}
+
+/// 一帧的剔除结果
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CullStats {
+  pub visible: u32,
+  pub culled: u32,
+  /// 剔除和上传实例数据所用的CPU时间
+  pub time: std::time::Duration,
+}
+
+/// 剔除方式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CullMode {
+  Off,
+  Cpu,
+  /// 计算着色器剔除，间接绘制
+  Gpu,
+}
+
+impl CullMode {
+  pub const ALL: [CullMode; 3] = [CullMode::Off, CullMode::Cpu, CullMode::Gpu];
+
+  /// 命令行中使用的名称
+  pub fn name(&self) -> &'static str {
+    match self {
+      CullMode::Off => "off",
+      CullMode::Cpu => "cpu",
+      CullMode::Gpu => "gpu",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.iter().copied().find(|mode| mode.name() == name)
+  }
+
+  pub fn next(&self) -> Self {
+    let index = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+    Self::ALL[(index + 1) % Self::ALL.len()]
+  }
+}
+
+/// 剔除计算着色器的uniform变量，与culling.wgsl中的`CullUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullUniform {
+  planes: [[f32; 4]; 6],
+  instance_count: u32,
+  padding: [u32; 3],
+}
+
+/// draw_indexed_indirect的参数
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DrawIndexedArgs {
+  index_count: u32,
+  instance_count: u32,
+  first_index: u32,
+  base_vertex: i32,
+  first_instance: u32,
+}
+
+/// 计算着色器每个工作组的线程数，与culling.wgsl中的workgroup_size一致
+const WORKGROUP_SIZE: u32 = 64;
+/// 实例数读回缓冲的数量，结果在几帧之后才读取，不等待GPU
+const READBACK_FRAMES: usize = 3;
+/// `DrawIndexedArgs::instance_count`在参数缓冲中的偏移
+const INSTANCE_COUNT_OFFSET: wgpu::BufferAddress = 4;
+const INSTANCE_COUNT_SIZE: wgpu::BufferAddress = 4;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+/// 一帧可见实例数的读回缓冲
+struct Readback {
+  buffer: stats::Buffer,
+  future: Option<MapFuture>,
+  /// 已录制复制、等待读回
+  pending: bool,
+}
+
+/// GPU视锥体剔除：计算pass将可见实例写入`visible_buffer`并填写`args_buffer`中的实例数
+pub struct GpuCulling {
+  uniform: CullUniform,
+  buffer: stats::Buffer,
+  group: wgpu::BindGroup,
+  pipeline: wgpu::ComputePipeline,
+  /// 每帧重置的间接绘制参数
+  reset_args: DrawIndexedArgs,
+  instance_buffer: stats::Buffer,
+  sphere_buffer: stats::Buffer,
+  pub visible_buffer: stats::Buffer,
+  pub args_buffer: stats::Buffer,
+  readbacks: Vec<Readback>,
+  /// 本帧复制实例数的读回缓冲
+  recording: Option<usize>,
+  /// 最近读回的可见实例数
+  visible: Option<u32>,
+}
+
+impl GpuCulling {
+  pub fn new(instance_data: &[InstanceData], spheres: &[BoundingSphere], index_count: u32, device: &wgpu::Device) -> Self {
+    let uniform = CullUniform {
+      planes: [[0.0; 4]; 6],
+      instance_count: instance_data.len() as u32,
+      padding: [0; 3],
+    };
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Cull Uniform Buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let instance_buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Cull Instance Buffer"),
+      contents: bytemuck::cast_slice(instance_data),
+      usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+    });
+    let sphere_data = spheres.iter().map(sphere_to_array).collect::<Vec<_>>();
+    let sphere_buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Cull Sphere Buffer"),
+      contents: bytemuck::cast_slice(&sphere_data),
+      usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+    });
+    let visible_buffer = stats::create_buffer(device, &wgpu::BufferDescriptor {
+      label: Some("GPU Visible Instance Buffer"),
+      size: (instance_data.len().max(1) * std::mem::size_of::<InstanceData>()) as wgpu::BufferAddress,
+      usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+      mapped_at_creation: false
+    });
+    let reset_args = DrawIndexedArgs {
+      index_count,
+      instance_count: 0,
+      first_index: 0,
+      base_vertex: 0,
+      first_instance: 0,
+    };
+    let args_buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Indirect Args Buffer"),
+      contents: bytemuck::cast_slice(&[reset_args]),
+      usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC
+    });
+    let readbacks = (0..READBACK_FRAMES).map(|_| Readback {
+      buffer: stats::create_buffer(device, &wgpu::BufferDescriptor {
+        label: Some("Cull Readback Buffer"),
+        size: INSTANCE_COUNT_SIZE,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false
+      }),
+      future: None,
+      pending: false,
+    }).collect();
+    let storage_entry = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+      binding,
+      visibility: wgpu::ShaderStages::COMPUTE,
+      ty: wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Storage { read_only },
+        has_dynamic_offset: false,
+        min_binding_size: None
+      },
+      count: None
+    };
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("cull bind group layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::COMPUTE,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        },
+        storage_entry(1, true),
+        storage_entry(2, true),
+        storage_entry(3, false),
+        storage_entry(4, false),
+      ]
+    });
+    let group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("cull bind group"),
+      layout: &layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: buffer.as_entire_binding()
+        },
+        wgpu::BindGroupEntry {
+          binding: 1,
+          resource: instance_buffer.as_entire_binding()
+        },
+        wgpu::BindGroupEntry {
+          binding: 2,
+          resource: sphere_buffer.as_entire_binding()
+        },
+        wgpu::BindGroupEntry {
+          binding: 3,
+          resource: visible_buffer.as_entire_binding()
+        },
+        wgpu::BindGroupEntry {
+          binding: 4,
+          resource: args_buffer.as_entire_binding()
+        },
+      ]
+    });
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+      label: Some("Cull Shader"),
+      source: wgpu::ShaderSource::Wgsl(include_str!("culling.wgsl").into())
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Cull Pipeline Layout"),
+      bind_group_layouts: &[&layout],
+      push_constant_ranges: &[]
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+      label: Some("Cull Pipeline"),
+      layout: Some(&pipeline_layout),
+      module: &shader,
+      entry_point: "cs_main"
+    });
+    Self {
+      uniform,
+      buffer,
+      group,
+      pipeline,
+      reset_args,
+      instance_buffer,
+      sphere_buffer,
+      visible_buffer,
+      args_buffer,
+      readbacks,
+      recording: None,
+      visible: None,
+    }
+  }
+
+  /// 实例变换修改后更新第`index`个实例的数据和包围球
+  pub fn update_instance(&self, index: usize, data: &InstanceData, sphere: &BoundingSphere, queue: &wgpu::Queue) {
+    let offset = (index * std::mem::size_of::<InstanceData>()) as wgpu::BufferAddress;
+    queue.write_buffer(&self.instance_buffer, offset, bytemuck::cast_slice(&[*data]));
+    let offset = (index * std::mem::size_of::<[f32; 4]>()) as wgpu::BufferAddress;
+    queue.write_buffer(&self.sphere_buffer, offset, bytemuck::cast_slice(&[sphere_to_array(sphere)]));
+  }
+
+  /// 重置实例数并执行剔除，需在使用`args_buffer`的render pass之前调用
+  pub fn run(&mut self, encoder: &mut wgpu::CommandEncoder, frustum: &Frustum, queue: &wgpu::Queue) {
+    self.uniform.planes = frustum.planes.map(|plane| plane.into());
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    queue.write_buffer(&self.args_buffer, 0, bytemuck::cast_slice(&[self.reset_args]));
+    {
+      let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+        label: Some("Cull Pass")
+      });
+      compute_pass.set_pipeline(&self.pipeline);
+      compute_pass.set_bind_group(0, &self.group, &[]);
+      let workgroups = self.uniform.instance_count.div_ceil(WORKGROUP_SIZE);
+      compute_pass.dispatch(workgroups, 1, 1);
+    }
+    // 复制实例数用于统计；所有读回缓冲都在等待时跳过这一帧
+    self.recording = self.readbacks.iter().position(|readback| !readback.pending);
+    if let Some(index) = self.recording {
+      let readback = &mut self.readbacks[index];
+      encoder.copy_buffer_to_buffer(&self.args_buffer, INSTANCE_COUNT_OFFSET, &readback.buffer, 0, INSTANCE_COUNT_SIZE);
+      readback.pending = true;
+    }
+  }
+
+  /// 提交后映射本帧的读回缓冲
+  pub fn after_submit(&mut self) {
+    if let Some(index) = self.recording.take() {
+      let readback = &mut self.readbacks[index];
+      readback.future = Some(Box::pin(readback.buffer.slice(..).map_async(wgpu::MapMode::Read)));
+    }
+  }
+
+  /// 不阻塞地检查读回是否完成，完成时更新`visible`
+  pub fn poll(&mut self, device: &wgpu::Device) {
+    device.poll(wgpu::Maintain::Poll);
+    let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+    for readback in &mut self.readbacks {
+      let mapped = match readback.future.as_mut().map(|future| future.as_mut().poll(&mut context)) {
+        Some(std::task::Poll::Ready(result)) => result,
+        _ => continue,
+      };
+      readback.future = None;
+      readback.pending = false;
+      if let Err(error) = mapped {
+        eprintln!("cull readback failed: {:?}", error);
+        continue;
+      }
+      {
+        let data = readback.buffer.slice(..).get_mapped_range();
+        self.visible = Some(u32::from_ne_bytes([data[0], data[1], data[2], data[3]]));
+      }
+      readback.buffer.unmap();
+    }
+  }
+
+  /// 几帧前剔除后的可见实例数，尚未读回时为None
+  pub fn visible(&self) -> Option<u32> {
+    self.visible
+  }
+}
+
+/// 与culling.wgsl中的包围球数据（xyz为球心，w为半径）对应
+fn sphere_to_array(sphere: &BoundingSphere) -> [f32; 4] {
+  [sphere.center.x, sphere.center.y, sphere.center.z, sphere.radius]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::camera::OPENGL_TO_WGPU_MATRIX;
+
+  /// 位于原点、朝向-z的相机：视场角90度，宽高比1，近/远平面0.1/100
+  fn frustum() -> Frustum {
+    let view = cgmath::Matrix4::look_at_rh(cgmath::Point3::new(0.0, 0.0, 0.0), cgmath::Point3::new(0.0, 0.0, -1.0), cgmath::Vector3::unit_y());
+    let projection = OPENGL_TO_WGPU_MATRIX * cgmath::perspective(cgmath::Deg(90.0), 1.0, 0.1, 100.0);
+    Frustum::from_matrix(&(projection * view))
+  }
+
+  fn sphere(x: f32, y: f32, z: f32, radius: f32) -> BoundingSphere {
+    BoundingSphere { center: cgmath::Point3::new(x, y, z), radius }
+  }
+
+  #[test]
+  fn from_matrix_normalizes_planes() {
+    for plane in frustum().planes {
+      assert!((plane.truncate().magnitude() - 1.0).abs() < 1e-5);
+    }
+    // 近平面z = -0.1，法线指向-z
+    let near = frustum().planes[4];
+    assert!((near.z + 1.0).abs() < 1e-5);
+    assert!((near.w + 0.1).abs() < 1e-4);
+  }
+
+  #[test]
+  fn intersects_sphere_inside_and_outside() {
+    let frustum = frustum();
+    assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -5.0, 0.5)));
+    assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, 5.0, 0.5))); // 相机后方
+    assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, -0.05, 0.01))); // 近平面之前
+    assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, -101.0, 0.5))); // 远平面之后
+    assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -100.2, 0.5))); // 与远平面相交
+    assert!(!frustum.intersects_sphere(&sphere(0.0, 20.0, -5.0, 1.0))); // 上方
+    assert!(!frustum.intersects_sphere(&sphere(0.0, -20.0, -5.0, 1.0))); // 下方
+    assert!(!frustum.intersects_sphere(&sphere(20.0, 0.0, -5.0, 1.0))); // 右侧
+  }
+
+  #[test]
+  fn intersects_sphere_uses_plane_distance() {
+    let frustum = frustum();
+    // 球心到左平面x = z的距离为5 / sqrt(2) ≈ 3.536
+    assert!(!frustum.intersects_sphere(&sphere(-10.0, 0.0, -5.0, 3.5)));
+    assert!(frustum.intersects_sphere(&sphere(-10.0, 0.0, -5.0, 3.6)));
+  }
+}
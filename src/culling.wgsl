@@ -0,0 +1,56 @@
+// GPU视锥体剔除：每个线程测试一个实例的包围球，可见实例追加到输出缓冲并累加间接绘制的实例数
+
+struct CullUniform {
+  /// 视锥体的6个平面，法线指向内部
+  planes: array<vec4<f32>, 6>;
+  instance_count: u32;
+  padding_0: u32;
+  padding_1: u32;
+  padding_2: u32;
+};
+
+struct Instances {
+  data: array<mat4x4<f32>>;
+};
+
+/// xyz为球心，w为半径
+struct Spheres {
+  data: array<vec4<f32>>;
+};
+
+/// wgpu的draw_indexed_indirect参数布局，与culling.rs中的`DrawIndexedArgs`对应
+struct DrawIndexedArgs {
+  index_count: u32;
+  instance_count: atomic<u32>;
+  first_index: u32;
+  base_vertex: i32;
+  first_instance: u32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> cull: CullUniform;
+[[group(0), binding(1)]]
+var<storage, read> instances: Instances;
+[[group(0), binding(2)]]
+var<storage, read> spheres: Spheres;
+[[group(0), binding(3)]]
+var<storage, read_write> visible: Instances;
+[[group(0), binding(4)]]
+var<storage, read_write> args: DrawIndexedArgs;
+
+[[stage(compute), workgroup_size(64)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+  let index = id.x;
+  if (index >= cull.instance_count) {
+    return;
+  }
+  let sphere = spheres.data[index];
+  let center = vec4<f32>(sphere.xyz, 1.0);
+  for (var i = 0; i < 6; i = i + 1) {
+    if (dot(cull.planes[i], center) < -sphere.w) {
+      return;
+    }
+  }
+  let slot = atomicAdd(&args.instance_count, 1u);
+  visible.data[slot] = instances.data[index];
+}
@@ -0,0 +1,235 @@
+use crate::camera::Camera;
+use crate::shape::{
+  Vertex,
+  InstanceData
+};
+use crate::texture::Texture;
+use crate::stats;
+
+/// 场景的显示方式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DebugView {
+  /// 正常的PBR光照
+  Lit,
+  /// 按相机近/远平面线性化的深度
+  Depth,
+  /// 世界空间法线
+  Normal,
+  /// uv棋盘格
+  Uv,
+  VertexColor,
+  /// 按实例序号着色；此视图不使用剔除结果，保证颜色与实例一一对应
+  InstanceId,
+  /// 过度绘制热度图
+  Overdraw,
+}
+
+/// 调试视图uniform变量，与debug.wgsl中的`DebugUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugUniform {
+  near: f32,
+  far: f32,
+  checker_scale: f32,
+  padding: f32,
+}
+
+/// 各调试视图的渲染管线
+pub struct DebugViews {
+  uniform: DebugUniform,
+  buffer: stats::Buffer,
+  pub group: wgpu::BindGroup,
+  layout: wgpu::BindGroupLayout,
+  pipelines: Vec<(DebugView, wgpu::RenderPipeline)>,
+}
+
+impl DebugView {
+  pub const ALL: [DebugView; 7] = [
+    DebugView::Lit,
+    DebugView::Depth,
+    DebugView::Normal,
+    DebugView::Uv,
+    DebugView::VertexColor,
+    DebugView::InstanceId,
+    DebugView::Overdraw,
+  ];
+
+  /// 命令行中使用的名称
+  pub fn name(&self) -> &'static str {
+    match self {
+      DebugView::Lit => "lit",
+      DebugView::Depth => "depth",
+      DebugView::Normal => "normal",
+      DebugView::Uv => "uv",
+      DebugView::VertexColor => "color",
+      DebugView::InstanceId => "instance",
+      DebugView::Overdraw => "overdraw",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.iter().copied().find(|view| view.name() == name)
+  }
+
+  /// 循环切换到下一个视图
+  pub fn next(&self) -> Self {
+    let index = Self::ALL.iter().position(|view| view == self).unwrap_or(0);
+    Self::ALL[(index + 1) % Self::ALL.len()]
+  }
+
+  fn entry_point(&self) -> Option<&'static str> {
+    match self {
+      DebugView::Lit => None, // 使用主渲染管线
+      DebugView::Depth => Some("fs_depth"),
+      DebugView::Normal => Some("fs_normal"),
+      DebugView::Uv => Some("fs_uv"),
+      DebugView::VertexColor => Some("fs_color"),
+      DebugView::InstanceId => Some("fs_instance"),
+      DebugView::Overdraw => Some("fs_overdraw"),
+    }
+  }
+}
+
+impl DebugViews {
+  pub fn new(camera: &Camera, camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+    let uniform = DebugUniform {
+      near: camera.near,
+      far: camera.far,
+      checker_scale: 8.0,
+      padding: 0.0,
+    };
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Debug buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("debug bind group layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        }
+      ]
+    });
+    let group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("debug bind group"),
+      layout: &layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: buffer.as_entire_binding()
+        }
+      ]
+    });
+    let pipelines = create_pipelines(&layout, camera_layout, sample_count, depth_format, device);
+    Self {
+      uniform,
+      buffer,
+      group,
+      layout,
+      pipelines,
+    }
+  }
+
+  /// 多重采样数变化后重建管线
+  pub fn set_sample_count(&mut self, camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) {
+    self.pipelines = create_pipelines(&self.layout, camera_layout, sample_count, depth_format, device);
+  }
+
+  /// 相机近/远平面变化后更新uniform
+  pub fn update_camera(&mut self, camera: &Camera, queue: &wgpu::Queue) {
+    self.uniform.near = camera.near;
+    self.uniform.far = camera.far;
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+
+  /// 视图对应的渲染管线，`DebugView::Lit`返回None
+  pub fn pipeline(&self, view: DebugView) -> Option<&wgpu::RenderPipeline> {
+    self.pipelines.iter().find(|(v, _)| *v == view).map(|(_, pipeline)| pipeline)
+  }
+}
+
+fn create_pipelines(
+  layout: &wgpu::BindGroupLayout,
+  camera_layout: &wgpu::BindGroupLayout,
+  sample_count: u32,
+  depth_format: wgpu::TextureFormat,
+  device: &wgpu::Device,
+) -> Vec<(DebugView, wgpu::RenderPipeline)> {
+  let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+    label: Some("Debug Shader"),
+    source: wgpu::ShaderSource::Wgsl(include_str!("debug.wgsl").into())
+  });
+  let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    label: Some("Debug Pipeline Layout"),
+    bind_group_layouts: &[layout, camera_layout],
+    push_constant_ranges: &[]
+  });
+  DebugView::ALL.iter().filter_map(|view| {
+    let entry_point = view.entry_point()?;
+    let overdraw = *view == DebugView::Overdraw;
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+      label: Some("Debug Pipeline"),
+      layout: Some(&pipeline_layout),
+      vertex: wgpu::VertexState {
+        module: &shader,
+        entry_point: "vs_main",
+        buffers: &[
+          Vertex::desc(),
+          InstanceData::desc()
+        ]
+      },
+      fragment: Some(wgpu::FragmentState {
+        module: &shader,
+        entry_point,
+        targets: &[wgpu::ColorTargetState {
+          format: Texture::HDR_FORMAT,
+          blend: if overdraw {
+            Some(wgpu::BlendState {
+              color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+              },
+              alpha: wgpu::BlendComponent::REPLACE,
+            })
+          } else {
+            Some(wgpu::BlendState::REPLACE)
+          },
+          write_mask: wgpu::ColorWrites::ALL,
+        }],
+      }),
+      primitive: wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        strip_index_format: None,
+        front_face: wgpu::FrontFace::Ccw,
+        cull_mode: Some(wgpu::Face::Back),
+        polygon_mode: wgpu::PolygonMode::Fill,
+        unclipped_depth: false,
+        conservative: false
+      },
+      // 过度绘制视图需统计所有片元，不做深度测试
+      depth_stencil: Some(wgpu::DepthStencilState {
+        format: depth_format,
+        depth_write_enabled: !overdraw,
+        depth_compare: if overdraw { wgpu::CompareFunction::Always } else { wgpu::CompareFunction::LessEqual },
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default()
+      }),
+      multisample: wgpu::MultisampleState {
+        count: sample_count,
+        mask: !0,
+        alpha_to_coverage_enabled: false,
+      },
+      multiview: None
+    });
+    Some((*view, pipeline))
+  }).collect()
+}
@@ -0,0 +1,103 @@
+// 调试视图：深度、法线、uv、顶点颜色、实例序号、过度绘制
+
+struct VertexInput {
+  [[location(0)]] position: vec3<f32>;
+  [[location(1)]] color: vec3<f32>;
+  [[location(2)]] uv: vec2<f32>;
+  [[location(3)]] normal: vec3<f32>;
+};
+
+struct InstanceInput {
+  [[location(4)]] model_0: vec4<f32>;
+  [[location(5)]] model_1: vec4<f32>;
+  [[location(6)]] model_2: vec4<f32>;
+  [[location(7)]] model_3: vec4<f32>;
+};
+
+struct VertexOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+  [[location(0)]] uv: vec2<f32>;
+  [[location(1)]] color: vec3<f32>;
+  [[location(2)]] world_normal: vec3<f32>;
+  [[location(3), interpolate(flat)]] instance: u32;
+};
+
+struct CameraUnifrom {
+  view_projection: mat4x4<f32>;
+  eye_position: vec4<f32>;
+};
+
+struct DebugUniform {
+  near: f32;
+  far: f32;
+  /// uv棋盘格的格数
+  checker_scale: f32;
+  padding: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> debug: DebugUniform;
+
+[[group(1), binding(0)]]
+var<uniform> camera: CameraUnifrom;
+
+[[stage(vertex)]]
+fn vs_main(inputData: VertexInput, instanceData: InstanceInput, [[builtin(instance_index)]] instance: u32) -> VertexOutput {
+  var outputData: VertexOutput;
+  let model_matrix = mat4x4<f32>(
+    instanceData.model_0,
+    instanceData.model_1,
+    instanceData.model_2,
+    instanceData.model_3
+  );
+  outputData.clip_position = camera.view_projection * (model_matrix * vec4<f32>(inputData.position, 1.0));
+  outputData.uv = inputData.uv;
+  outputData.color = inputData.color;
+  let scale_2 = vec3<f32>(dot(model_matrix[0].xyz, model_matrix[0].xyz), dot(model_matrix[1].xyz, model_matrix[1].xyz), dot(model_matrix[2].xyz, model_matrix[2].xyz));
+  outputData.world_normal = (model_matrix * vec4<f32>(inputData.normal / scale_2, 0.0)).xyz; // 非均匀缩放时保持法线垂直于表面
+  outputData.instance = instance;
+  return outputData;
+}
+
+/// 将透视深度还原为线性深度并归一化到[0, 1]
+[[stage(fragment)]]
+fn fs_depth(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let depth = inputData.clip_position.z;
+  let linear = debug.near * debug.far / (debug.far - depth * (debug.far - debug.near));
+  let value = clamp((linear - debug.near) / (debug.far - debug.near), 0.0, 1.0);
+  return vec4<f32>(vec3<f32>(sqrt(value)), 1.0); // 开方以拉开近处的层次
+}
+
+[[stage(fragment)]]
+fn fs_normal(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  return vec4<f32>(normalize(inputData.world_normal) * 0.5 + vec3<f32>(0.5), 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_uv(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let cell = floor(inputData.uv * debug.checker_scale);
+  let checker = (i32(cell.x) + i32(cell.y)) & 1;
+  let shade = select(0.35, 1.0, checker == 0);
+  return vec4<f32>(fract(inputData.uv.x) * shade, fract(inputData.uv.y) * shade, shade * 0.5, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_color(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  return vec4<f32>(inputData.color, 1.0);
+}
+
+/// 由实例序号散列出颜色；绘制时使用未经剔除压缩的实例缓冲，序号与实例一一对应
+[[stage(fragment)]]
+fn fs_instance(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  var h = inputData.instance * 747796405u + 2891336453u;
+  h = ((h >> ((h >> 28u) + 4u)) ^ h) * 277803737u;
+  h = (h >> 22u) ^ h;
+  let color = vec3<f32>(f32(h & 255u), f32((h >> 8u) & 255u), f32((h >> 16u) & 255u)) / 255.0;
+  return vec4<f32>(color, 1.0);
+}
+
+/// 每个片元叠加固定的值，重叠越多越亮（需关闭深度测试并使用加法混合）
+[[stage(fragment)]]
+fn fs_overdraw(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  return vec4<f32>(0.1, 0.04, 0.01, 1.0);
+}
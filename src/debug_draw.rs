@@ -0,0 +1,236 @@
+use std::mem;
+use cgmath::prelude::*;
+use crate::camera::Camera;
+use crate::shape::Aabb;
+use crate::texture::Texture;
+use crate::stats;
+
+/// 调试线段的顶点，位置为世界坐标
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+  position: [f32; 3],
+  color: [f32; 4],
+}
+
+impl LineVertex {
+  pub fn new(position: cgmath::Point3<f32>, color: [f32; 4]) -> Self {
+    Self { position: position.into(), color }
+  }
+
+  pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+    wgpu::VertexBufferLayout {
+      array_stride: mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+      step_mode: wgpu::VertexStepMode::Vertex,
+      attributes: &[
+        wgpu::VertexAttribute {
+          offset: 0,
+          shader_location: 0,
+          format: wgpu::VertexFormat::Float32x3,
+        }, // position
+        wgpu::VertexAttribute {
+          offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+          shader_location: 1,
+          format: wgpu::VertexFormat::Float32x4,
+        }, // color
+      ]
+    }
+  }
+}
+
+/// 即时模式的调试线段绘制：每帧调用`line`等方法收集线段，`prepare`上传后在render pass中`draw`
+pub struct DebugDraw {
+  vertices: Vec<LineVertex>,
+  buffer: stats::Buffer,
+  /// 缓冲可容纳的顶点数
+  capacity: usize,
+  /// 上一次`prepare`上传的顶点数
+  vertex_num: u32,
+  /// 为true时不做深度测试，线段总是显示在最上层（如变换手柄）
+  overlay: bool,
+  pipeline: wgpu::RenderPipeline,
+}
+
+/// 圆周的分段数
+const CIRCLE_SEGMENTS: usize = 32;
+
+impl DebugDraw {
+  pub fn new(camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+    Self::with_overlay(false, camera_layout, sample_count, depth_format, device)
+  }
+
+  /// 不受场景遮挡的线段绘制
+  pub fn new_overlay(camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+    Self::with_overlay(true, camera_layout, sample_count, depth_format, device)
+  }
+
+  fn with_overlay(overlay: bool, camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+    let capacity = 1024;
+    Self {
+      vertices: vec![],
+      buffer: create_buffer(capacity, device),
+      capacity,
+      vertex_num: 0,
+      overlay,
+      pipeline: create_pipeline(overlay, camera_layout, sample_count, depth_format, device),
+    }
+  }
+
+  /// 多重采样数变化后重建管线
+  pub fn set_sample_count(&mut self, camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) {
+    self.pipeline = create_pipeline(self.overlay, camera_layout, sample_count, depth_format, device);
+  }
+
+  pub fn line(&mut self, a: cgmath::Point3<f32>, b: cgmath::Point3<f32>, color: [f32; 4]) {
+    self.vertices.push(LineVertex::new(a, color));
+    self.vertices.push(LineVertex::new(b, color));
+  }
+
+  /// 包围盒的12条棱
+  pub fn aabb(&mut self, aabb: &Aabb, color: [f32; 4]) {
+    self.box_edges(&aabb.corners(), color);
+  }
+
+  /// 用三个坐标平面上的圆表示球体
+  pub fn sphere(&mut self, center: cgmath::Point3<f32>, radius: f32, color: [f32; 4]) {
+    let axes = [
+      (cgmath::Vector3::unit_x(), cgmath::Vector3::unit_y()),
+      (cgmath::Vector3::unit_y(), cgmath::Vector3::unit_z()),
+      (cgmath::Vector3::unit_z(), cgmath::Vector3::unit_x()),
+    ];
+    for (u, v) in axes {
+      self.circle(center, u, v, radius, color);
+    }
+  }
+
+  /// 由相互垂直的单位向量`u`、`v`张成的平面上的圆
+  pub fn circle(&mut self, center: cgmath::Point3<f32>, u: cgmath::Vector3<f32>, v: cgmath::Vector3<f32>, radius: f32, color: [f32; 4]) {
+    let point = |i: usize| {
+      let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+      center + (u * angle.cos() + v * angle.sin()) * radius
+    };
+    for i in 0..CIRCLE_SEGMENTS {
+      self.line(point(i), point(i + 1), color);
+    }
+  }
+
+  /// 变换的局部坐标轴，x/y/z分别为红/绿/蓝
+  pub fn axes(&mut self, transform: &cgmath::Matrix4<f32>, size: f32) {
+    let origin = transform.transform_point(cgmath::Point3::origin());
+    let colors = [[1.0, 0.2, 0.2, 1.0], [0.2, 1.0, 0.2, 1.0], [0.2, 0.4, 1.0, 1.0]];
+    for (axis, color) in [cgmath::Vector3::unit_x(), cgmath::Vector3::unit_y(), cgmath::Vector3::unit_z()].into_iter().zip(colors) {
+      self.line(origin, transform.transform_point(cgmath::Point3::from_vec(axis * size)), color);
+    }
+  }
+
+  pub fn frustum(&mut self, camera: &Camera, color: [f32; 4]) {
+    self.frustum_from_matrix(&camera.get_view_projection_matrix(), color);
+  }
+
+  /// 由视图投影矩阵反推视锥体的8个顶点（NDC的z范围为[0, 1]）
+  pub fn frustum_from_matrix(&mut self, view_projection: &cgmath::Matrix4<f32>, color: [f32; 4]) {
+    let inverse = match view_projection.invert() {
+      Some(inverse) => inverse,
+      None => return,
+    };
+    let ndc = Aabb {
+      min: cgmath::Point3::new(-1.0, -1.0, 0.0),
+      max: cgmath::Point3::new(1.0, 1.0, 1.0),
+    };
+    self.box_edges(&ndc.corners().map(|corner| inverse.transform_point(corner)), color);
+  }
+
+  /// 按`Aabb::corners`的顶点顺序连接盒子的12条棱
+  fn box_edges(&mut self, corners: &[cgmath::Point3<f32>; 8], color: [f32; 4]) {
+    for i in 0..8 {
+      for bit in [1, 2, 4] {
+        if i & bit == 0 {
+          self.line(corners[i], corners[i | bit], color);
+        }
+      }
+    }
+  }
+
+  /// 上传本帧收集的线段并清空，缓冲不足时扩容
+  pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+    if self.vertices.len() > self.capacity {
+      self.capacity = self.vertices.len().next_power_of_two();
+      self.buffer = create_buffer(self.capacity, device);
+    }
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.vertices));
+    self.vertex_num = self.vertices.len() as u32;
+    self.vertices.clear();
+  }
+
+  pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera_group: &'a wgpu::BindGroup) {
+    if self.vertex_num == 0 {
+      return;
+    }
+    render_pass.set_pipeline(&self.pipeline);
+    render_pass.set_bind_group(0, camera_group, &[]);
+    render_pass.set_vertex_buffer(0, self.buffer.slice(..));
+    render_pass.draw(0..self.vertex_num, 0..1);
+  }
+}
+
+fn create_buffer(capacity: usize, device: &wgpu::Device) -> stats::Buffer {
+  stats::create_buffer(device, &wgpu::BufferDescriptor {
+    label: Some("Debug Line Buffer"),
+    size: (capacity * mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
+    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+    mapped_at_creation: false
+  })
+}
+
+fn create_pipeline(overlay: bool, camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) -> wgpu::RenderPipeline {
+  let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+    label: Some("Debug Line Shader"),
+    source: wgpu::ShaderSource::Wgsl(include_str!("debug_draw.wgsl").into())
+  });
+  let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    label: Some("Debug Line Pipeline Layout"),
+    bind_group_layouts: &[camera_layout],
+    push_constant_ranges: &[]
+  });
+  device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    label: Some("Debug Line Pipeline"),
+    layout: Some(&pipeline_layout),
+    vertex: wgpu::VertexState {
+      module: &shader,
+      entry_point: "vs_main",
+      buffers: &[LineVertex::desc()]
+    },
+    fragment: Some(wgpu::FragmentState {
+      module: &shader,
+      entry_point: "fs_main",
+      targets: &[wgpu::ColorTargetState {
+        format: Texture::HDR_FORMAT,
+        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+        write_mask: wgpu::ColorWrites::ALL,
+      }],
+    }),
+    primitive: wgpu::PrimitiveState {
+      topology: wgpu::PrimitiveTopology::LineList,
+      strip_index_format: None,
+      front_face: wgpu::FrontFace::Ccw,
+      cull_mode: None,
+      polygon_mode: wgpu::PolygonMode::Fill,
+      unclipped_depth: false,
+      conservative: false
+    },
+    // 被场景遮挡的线段不显示，但线段之间不互相遮挡
+    depth_stencil: Some(wgpu::DepthStencilState {
+      format: depth_format,
+      depth_write_enabled: false,
+      depth_compare: if overlay { wgpu::CompareFunction::Always } else { wgpu::CompareFunction::LessEqual },
+      stencil: wgpu::StencilState::default(),
+      bias: wgpu::DepthBiasState::default()
+    }),
+    multisample: wgpu::MultisampleState {
+      count: sample_count,
+      mask: !0,
+      alpha_to_coverage_enabled: false,
+    },
+    multiview: None
+  })
+}
@@ -0,0 +1,32 @@
+// 调试线段：顶点已在世界空间，逐顶点颜色
+
+struct VertexInput {
+  [[location(0)]] position: vec3<f32>;
+  [[location(1)]] color: vec4<f32>;
+};
+
+struct VertexOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+  [[location(0)]] color: vec4<f32>;
+};
+
+struct CameraUnifrom {
+  view_projection: mat4x4<f32>;
+  eye_position: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> camera: CameraUnifrom;
+
+[[stage(vertex)]]
+fn vs_main(inputData: VertexInput) -> VertexOutput {
+  var outputData: VertexOutput;
+  outputData.clip_position = camera.view_projection * vec4<f32>(inputData.position, 1.0);
+  outputData.color = inputData.color;
+  return outputData;
+}
+
+[[stage(fragment)]]
+fn fs_main(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  return inputData.color;
+}
@@ -0,0 +1,381 @@
+use cgmath::prelude::*;
+use crate::camera::Camera;
+use crate::debug_draw::DebugDraw;
+use crate::raycast::Ray;
+use crate::shape::{
+  Aabb,
+  Instance
+};
+
+/// 变换手柄的操作方式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GizmoMode {
+  Translate,
+  Rotate,
+  Scale,
+}
+
+impl GizmoMode {
+  pub const ALL: [GizmoMode; 3] = [GizmoMode::Translate, GizmoMode::Rotate, GizmoMode::Scale];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      GizmoMode::Translate => "translate",
+      GizmoMode::Rotate => "rotate",
+      GizmoMode::Scale => "scale",
+    }
+  }
+
+  pub fn next(&self) -> Self {
+    let index = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+    Self::ALL[(index + 1) % Self::ALL.len()]
+  }
+}
+
+/// 手柄坐标轴所在的空间
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GizmoSpace {
+  World,
+  /// 沿实例旋转后的坐标轴
+  Local,
+}
+
+/// 拖动开始时的状态
+#[derive(Debug, Copy, Clone)]
+struct Drag {
+  axis: usize,
+  /// 拖动过程中保持不变的世界空间轴向
+  direction: cgmath::Vector3<f32>,
+  start: Instance,
+  /// 平移/缩放为起点在轴上的位置，旋转为起点相对中心的方向
+  start_offset: f32,
+  start_vector: cgmath::Vector3<f32>,
+}
+
+/// 手柄长度占视口半高的比例，使手柄在屏幕上大小不变
+const SCREEN_SIZE: f32 = 0.2;
+/// 手柄可点击范围，相对手柄长度
+const PICK_TOLERANCE: f32 = 0.08;
+const AXIS_COLORS: [[f32; 4]; 3] = [[1.0, 0.2, 0.2, 1.0], [0.2, 1.0, 0.2, 1.0], [0.2, 0.4, 1.0, 1.0]];
+const ACTIVE_COLOR: [f32; 4] = [1.0, 1.0, 0.2, 1.0];
+
+/// 选中实例的平移、旋转、缩放手柄
+pub struct Gizmo {
+  pub mode: GizmoMode,
+  pub space: GizmoSpace,
+  pub snapping: bool,
+  /// 平移吸附步长
+  pub translate_snap: f32,
+  /// 旋转吸附角度（度）
+  pub angle_snap: f32,
+  /// 缩放吸附步长
+  pub scale_snap: f32,
+  /// 光标下的坐标轴
+  hovered: Option<usize>,
+  drag: Option<Drag>,
+}
+
+impl Gizmo {
+  pub fn new() -> Self {
+    Self {
+      mode: GizmoMode::Translate,
+      space: GizmoSpace::World,
+      snapping: false,
+      translate_snap: 0.5,
+      angle_snap: 15.0,
+      scale_snap: 0.1,
+      hovered: None,
+      drag: None,
+    }
+  }
+
+  pub fn is_dragging(&self) -> bool {
+    self.drag.is_some()
+  }
+
+  /// 手柄的世界空间长度
+  fn size(&self, instance: &Instance, camera: &Camera) -> f32 {
+    let distance = camera.eye.distance(cgmath::Point3::from_vec(instance.center));
+    distance * cgmath::Deg(camera.fov / 2.0).tan() * SCREEN_SIZE
+  }
+
+  /// 三个坐标轴方向；缩放总是沿实例的局部坐标轴
+  fn axes(&self, instance: &Instance) -> [cgmath::Vector3<f32>; 3] {
+    let units = [cgmath::Vector3::unit_x(), cgmath::Vector3::unit_y(), cgmath::Vector3::unit_z()];
+    if self.space == GizmoSpace::Local || self.mode == GizmoMode::Scale {
+      units.map(|axis| instance.rotation.rotate_vector(axis))
+    } else {
+      units
+    }
+  }
+
+  /// 射线命中的坐标轴（取最近者）
+  fn hit(&self, ray: &Ray, instance: &Instance, camera: &Camera) -> Option<usize> {
+    let center = cgmath::Point3::from_vec(instance.center);
+    let size = self.size(instance, camera);
+    let tolerance = size * PICK_TOLERANCE;
+    let mut closest: Option<(usize, f32)> = None;
+    for (axis, direction) in self.axes(instance).into_iter().enumerate() {
+      let distance = match self.mode {
+        GizmoMode::Translate | GizmoMode::Scale => closest_on_axis(ray, center, direction)
+          .filter(|(offset, distance, hit)| (0.0..=size * 1.1).contains(offset) && *hit < tolerance && *distance >= 0.0)
+          .map(|(_, distance, _)| distance),
+        GizmoMode::Rotate => intersect_plane(ray, center, direction)
+          .filter(|distance| (ray.at(*distance).distance(center) - size).abs() < tolerance),
+      };
+      if let Some(distance) = distance {
+        if closest.is_none_or(|(_, closest)| distance < closest) {
+          closest = Some((axis, distance));
+        }
+      }
+    }
+    closest.map(|(axis, _)| axis)
+  }
+
+  /// 更新光标下高亮的坐标轴
+  pub fn hover(&mut self, ray: &Ray, instance: &Instance, camera: &Camera) {
+    self.hovered = self.hit(ray, instance, camera);
+  }
+
+  /// 射线命中手柄时开始拖动并返回true
+  pub fn begin(&mut self, ray: &Ray, instance: &Instance, camera: &Camera) -> bool {
+    let axis = match self.hit(ray, instance, camera) {
+      Some(axis) => axis,
+      None => return false,
+    };
+    let center = cgmath::Point3::from_vec(instance.center);
+    let direction = self.axes(instance)[axis];
+    let (start_offset, start_vector) = match self.mode {
+      GizmoMode::Translate | GizmoMode::Scale => match closest_on_axis(ray, center, direction) {
+        Some((offset, _, _)) => (offset, cgmath::Vector3::zero()),
+        None => return false,
+      },
+      GizmoMode::Rotate => match intersect_plane(ray, center, direction) {
+        Some(distance) => (0.0, (ray.at(distance) - center).normalize()),
+        None => return false,
+      },
+    };
+    self.drag = Some(Drag {
+      axis,
+      direction,
+      start: *instance,
+      start_offset,
+      start_vector,
+    });
+    true
+  }
+
+  /// 拖动中由射线计算新的变换，实例有变化时返回true
+  pub fn drag(&self, ray: &Ray, instance: &mut Instance) -> bool {
+    let drag = match &self.drag {
+      Some(drag) => drag,
+      None => return false,
+    };
+    let center = cgmath::Point3::from_vec(drag.start.center);
+    let mut next = drag.start;
+    match self.mode {
+      GizmoMode::Translate => {
+        let offset = match closest_on_axis(ray, center, drag.direction) {
+          Some((offset, _, _)) => offset,
+          None => return false,
+        };
+        if self.space == GizmoSpace::World {
+          // 世界坐标轴上吸附到网格
+          next.center = drag.start.center + drag.direction * (offset - drag.start_offset);
+          next.center[drag.axis] = self.snap(next.center[drag.axis], self.translate_snap);
+        } else {
+          next.center = drag.start.center + drag.direction * self.snap(offset - drag.start_offset, self.translate_snap);
+        }
+      },
+      GizmoMode::Rotate => {
+        let vector = match intersect_plane(ray, center, drag.direction) {
+          Some(distance) => (ray.at(distance) - center).normalize(),
+          None => return false,
+        };
+        let angle = drag.direction.dot(drag.start_vector.cross(vector)).atan2(drag.start_vector.dot(vector));
+        let angle = self.snap(angle.to_degrees(), self.angle_snap);
+        next.rotation = cgmath::Quaternion::from_axis_angle(drag.direction, cgmath::Deg(angle)) * drag.start.rotation;
+      },
+      GizmoMode::Scale => {
+        let offset = match closest_on_axis(ray, center, drag.direction) {
+          Some((offset, _, _)) => offset,
+          None => return false,
+        };
+        if drag.start_offset.abs() < f32::EPSILON {
+          return false;
+        }
+        let scale = self.snap(drag.start.scale[drag.axis] * offset / drag.start_offset, self.scale_snap);
+        next.scale[drag.axis] = scale.max(0.01);
+      },
+    }
+    let changed = next != *instance;
+    *instance = next;
+    changed
+  }
+
+  /// 结束拖动，返回拖动开始时的实例状态
+  pub fn end(&mut self) -> Option<Instance> {
+    self.drag.take().map(|drag| drag.start)
+  }
+
+  fn snap(&self, value: f32, step: f32) -> f32 {
+    if self.snapping && step > 0.0 { (value / step).round() * step } else { value }
+  }
+
+  /// 收集手柄线段，需使用不做深度测试的`DebugDraw`
+  pub fn draw(&self, draw: &mut DebugDraw, instance: &Instance, camera: &Camera) {
+    let center = cgmath::Point3::from_vec(instance.center);
+    let size = self.size(instance, camera);
+    let axes = self.axes(instance);
+    let active = self.drag.map(|drag| drag.axis).or(self.hovered);
+    for (axis, direction) in axes.into_iter().enumerate() {
+      let color = if active == Some(axis) { ACTIVE_COLOR } else { AXIS_COLORS[axis] };
+      let (u, v) = (axes[(axis + 1) % 3], axes[(axis + 2) % 3]);
+      let tip = center + direction * size;
+      match self.mode {
+        GizmoMode::Translate => {
+          draw.line(center, tip, color);
+          // 箭头
+          let back = tip - direction * size * 0.15;
+          for side in [u, -u, v, -v] {
+            draw.line(tip, back + side * size * 0.05, color);
+          }
+        },
+        GizmoMode::Rotate => draw.circle(center, u, v, size, color),
+        GizmoMode::Scale => {
+          draw.line(center, tip, color);
+          let half = size * 0.04;
+          let handle = Aabb {
+            min: cgmath::Point3::new(-half, -half, -half),
+            max: cgmath::Point3::new(half, half, half),
+          };
+          let transform = cgmath::Matrix4::from_translation(tip.to_vec()) * cgmath::Matrix4::from(instance.rotation);
+          draw.aabb(&handle.transform(&transform), color);
+        },
+      }
+    }
+  }
+}
+
+/// 射线与过`origin`、方向为`direction`（单位向量）的直线的最近点，
+/// 返回（最近点在直线上的位置，在射线上的距离，两最近点的距离）
+fn closest_on_axis(ray: &Ray, origin: cgmath::Point3<f32>, direction: cgmath::Vector3<f32>) -> Option<(f32, f32, f32)> {
+  let w = origin - ray.origin;
+  let b = direction.dot(ray.direction);
+  let denominator = 1.0 - b * b;
+  if denominator < 1e-6 {
+    return None; // 射线与轴平行
+  }
+  let d = direction.dot(w);
+  let e = ray.direction.dot(w);
+  let offset = (b * e - d) / denominator;
+  let distance = (e - b * d) / denominator;
+  let gap = (origin + direction * offset).distance(ray.at(distance));
+  Some((offset, distance, gap))
+}
+
+/// 射线与过`origin`、法线为`normal`的平面的交点距离
+fn intersect_plane(ray: &Ray, origin: cgmath::Point3<f32>, normal: cgmath::Vector3<f32>) -> Option<f32> {
+  let denominator = normal.dot(ray.direction);
+  if denominator.abs() < 1e-6 {
+    return None;
+  }
+  let distance = normal.dot(origin - ray.origin) / denominator;
+  if distance < 0.0 { None } else { Some(distance) }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ray(origin: [f32; 3], direction: [f32; 3]) -> Ray {
+    Ray { origin: origin.into(), direction: cgmath::Vector3::from(direction).normalize() }
+  }
+
+  fn instance() -> Instance {
+    Instance {
+      center: cgmath::Vector3::zero(),
+      rotation: cgmath::Quaternion::one(),
+      scale: cgmath::Vector3::new(1.0, 1.0, 1.0),
+    }
+  }
+
+  /// 直接构造沿第`axis`个世界坐标轴拖动的状态
+  fn dragging(mode: GizmoMode, axis: usize, start_offset: f32, start_vector: cgmath::Vector3<f32>) -> Gizmo {
+    let mut gizmo = Gizmo::new();
+    gizmo.mode = mode;
+    gizmo.drag = Some(Drag {
+      axis,
+      direction: [cgmath::Vector3::unit_x(), cgmath::Vector3::unit_y(), cgmath::Vector3::unit_z()][axis],
+      start: instance(),
+      start_offset,
+      start_vector,
+    });
+    gizmo
+  }
+
+  #[test]
+  fn closest_on_axis_finds_offset_and_gap() {
+    let origin = cgmath::Point3::new(0.0, 0.0, 0.0);
+    // 射线从x轴上方2处沿-z穿过，与x轴最近点在x=3
+    let (offset, distance, gap) = closest_on_axis(&ray([3.0, 2.0, 5.0], [0.0, 0.0, -1.0]), origin, cgmath::Vector3::unit_x()).unwrap();
+    assert!((offset - 3.0).abs() < 1e-5);
+    assert!((distance - 5.0).abs() < 1e-5);
+    assert!((gap - 2.0).abs() < 1e-5);
+    // 最近点在轴的负方向
+    let (offset, _, gap) = closest_on_axis(&ray([-1.5, 0.0, 4.0], [0.0, 0.0, -1.0]), origin, cgmath::Vector3::unit_x()).unwrap();
+    assert!((offset + 1.5).abs() < 1e-5);
+    assert!(gap < 1e-5);
+    // 与轴平行时没有唯一的最近点
+    assert!(closest_on_axis(&ray([0.0, 1.0, 0.0], [1.0, 0.0, 0.0]), origin, cgmath::Vector3::unit_x()).is_none());
+  }
+
+  #[test]
+  fn intersect_plane_hits_front_only() {
+    let origin = cgmath::Point3::new(0.0, 0.0, 1.0);
+    let normal = cgmath::Vector3::unit_z();
+    let distance = intersect_plane(&ray([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]), origin, normal).unwrap();
+    assert!((distance - 4.0).abs() < 1e-5);
+    // 法线朝向不影响结果
+    let distance = intersect_plane(&ray([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]), origin, -normal).unwrap();
+    assert!((distance - 4.0).abs() < 1e-5);
+    assert!(intersect_plane(&ray([0.0, 0.0, 5.0], [0.0, 0.0, 1.0]), origin, normal).is_none()); // 平面在射线后方
+    assert!(intersect_plane(&ray([0.0, 0.0, 5.0], [1.0, 0.0, 0.0]), origin, normal).is_none()); // 与平面平行
+  }
+
+  #[test]
+  fn snap_rounds_only_when_enabled() {
+    let mut gizmo = Gizmo::new();
+    assert_eq!(gizmo.snap(0.7, 0.5), 0.7);
+    gizmo.snapping = true;
+    assert_eq!(gizmo.snap(0.7, 0.5), 0.5);
+    assert_eq!(gizmo.snap(0.8, 0.5), 1.0);
+    assert_eq!(gizmo.snap(-0.8, 0.5), -1.0);
+    assert_eq!(gizmo.snap(22.0, 15.0), 15.0);
+    assert_eq!(gizmo.snap(0.7, 0.0), 0.7); // 步长为0时不吸附
+  }
+
+  #[test]
+  fn drag_rotates_counterclockwise_about_axis() {
+    let gizmo = dragging(GizmoMode::Rotate, 2, 0.0, cgmath::Vector3::unit_x());
+    let mut target = instance();
+    // 在z=0平面上从+x拖到+y，绕z轴逆时针转90度
+    assert!(gizmo.drag(&ray([0.0, 1.0, 5.0], [0.0, 0.0, -1.0]), &mut target));
+    let x = target.rotation.rotate_vector(cgmath::Vector3::unit_x());
+    assert!((x - cgmath::Vector3::unit_y()).magnitude() < 1e-5);
+    // 拖到-y时为顺时针
+    let mut target = instance();
+    assert!(gizmo.drag(&ray([0.0, -1.0, 5.0], [0.0, 0.0, -1.0]), &mut target));
+    let x = target.rotation.rotate_vector(cgmath::Vector3::unit_x());
+    assert!((x + cgmath::Vector3::unit_y()).magnitude() < 1e-5);
+  }
+
+  #[test]
+  fn drag_translates_along_axis() {
+    let gizmo = dragging(GizmoMode::Translate, 0, 1.0, cgmath::Vector3::zero());
+    let mut target = instance();
+    assert!(gizmo.drag(&ray([3.0, 2.0, 5.0], [0.0, 0.0, -1.0]), &mut target));
+    assert!((target.center - cgmath::Vector3::new(2.0, 0.0, 0.0)).magnitude() < 1e-5);
+    assert!(!gizmo.drag(&ray([3.0, 2.0, 5.0], [0.0, 0.0, -1.0]), &mut target)); // 没有变化
+  }
+}
@@ -0,0 +1,208 @@
+use cgmath::prelude::*;
+use crate::camera::{
+  Camera,
+  OPENGL_TO_WGPU_MATRIX
+};
+use crate::debug_draw::LineVertex;
+use crate::texture::Texture;
+use crate::stats;
+
+/// 网格和坐标轴指示共用的uniform变量，与grid.wgsl中的`GridUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GridUniform {
+  view_projection: [[f32; 4]; 4],
+  inverse_view_projection: [[f32; 4]; 4],
+  gizmo: [[f32; 4]; 4],
+  eye_position: [f32; 4],
+  height: f32,
+  fade_distance: f32,
+  padding: [f32; 2],
+}
+
+/// 无限地面网格与屏幕角落的坐标轴指示
+pub struct Grid {
+  pub show_grid: bool,
+  pub show_gizmo: bool,
+  uniform: GridUniform,
+  buffer: stats::Buffer,
+  group: wgpu::BindGroup,
+  layout: wgpu::BindGroupLayout,
+  grid_pipeline: wgpu::RenderPipeline,
+  gizmo_pipeline: wgpu::RenderPipeline,
+  /// 坐标轴指示的三条线段
+  gizmo_buffer: stats::Buffer,
+}
+
+/// 坐标轴指示的视口边长（像素）
+const GIZMO_SIZE: f32 = 96.0;
+
+impl Grid {
+  pub fn new(camera: &Camera, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+    let mut uniform = GridUniform {
+      view_projection: cgmath::Matrix4::identity().into(),
+      inverse_view_projection: cgmath::Matrix4::identity().into(),
+      gizmo: cgmath::Matrix4::identity().into(),
+      eye_position: [0.0; 4],
+      height: 0.0,
+      fade_distance: 50.0,
+      padding: [0.0; 2],
+    };
+    uniform.update(camera);
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Grid buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("grid bind group layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        }
+      ]
+    });
+    let group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("grid bind group"),
+      layout: &layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: buffer.as_entire_binding()
+        }
+      ]
+    });
+    let origin = cgmath::Point3::origin();
+    let gizmo_vertices = [
+      LineVertex::new(origin, [1.0, 0.2, 0.2, 1.0]),
+      LineVertex::new(cgmath::Point3::new(1.0, 0.0, 0.0), [1.0, 0.2, 0.2, 1.0]),
+      LineVertex::new(origin, [0.2, 1.0, 0.2, 1.0]),
+      LineVertex::new(cgmath::Point3::new(0.0, 1.0, 0.0), [0.2, 1.0, 0.2, 1.0]),
+      LineVertex::new(origin, [0.2, 0.4, 1.0, 1.0]),
+      LineVertex::new(cgmath::Point3::new(0.0, 0.0, 1.0), [0.2, 0.4, 1.0, 1.0]),
+    ];
+    let gizmo_buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Gizmo Vertex Buffer"),
+      contents: bytemuck::cast_slice(&gizmo_vertices),
+      usage: wgpu::BufferUsages::VERTEX
+    });
+    let (grid_pipeline, gizmo_pipeline) = create_pipelines(&layout, sample_count, depth_format, device);
+    Self {
+      show_grid: true,
+      show_gizmo: true,
+      uniform,
+      buffer,
+      group,
+      layout,
+      grid_pipeline,
+      gizmo_pipeline,
+      gizmo_buffer,
+    }
+  }
+
+  /// 多重采样数变化后重建管线
+  pub fn set_sample_count(&mut self, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) {
+    let (grid_pipeline, gizmo_pipeline) = create_pipelines(&self.layout, sample_count, depth_format, device);
+    self.grid_pipeline = grid_pipeline;
+    self.gizmo_pipeline = gizmo_pipeline;
+  }
+
+  pub fn update_camera(&mut self, camera: &Camera, queue: &wgpu::Queue) {
+    self.uniform.update(camera);
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+
+  /// 在场景之后绘制；坐标轴指示会修改视口，需放在render pass的最后
+  pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, size: winit::dpi::PhysicalSize<u32>) {
+    render_pass.set_bind_group(0, &self.group, &[]);
+    if self.show_grid {
+      render_pass.set_pipeline(&self.grid_pipeline);
+      render_pass.draw(0..3, 0..1); // 全屏三角形
+    }
+    if self.show_gizmo && size.height as f32 > GIZMO_SIZE && size.width as f32 > GIZMO_SIZE {
+      render_pass.set_viewport(0.0, size.height as f32 - GIZMO_SIZE, GIZMO_SIZE, GIZMO_SIZE, 0.0, 1.0); // 左下角
+      render_pass.set_pipeline(&self.gizmo_pipeline);
+      render_pass.set_vertex_buffer(0, self.gizmo_buffer.slice(..));
+      render_pass.draw(0..6, 0..1);
+    }
+  }
+}
+
+impl GridUniform {
+  fn update(&mut self, camera: &Camera) {
+    let view_projection = camera.get_view_projection_matrix();
+    self.view_projection = view_projection.into();
+    self.inverse_view_projection = view_projection.invert().unwrap_or_else(cgmath::Matrix4::identity).into();
+    self.eye_position = camera.eye.to_homogeneous().into();
+    // 去掉视图矩阵的平移，坐标轴始终位于视口中心
+    let mut rotation = cgmath::Matrix4::look_at_rh(camera.eye, camera.lookat, camera.up);
+    rotation.w = cgmath::Vector4::unit_w();
+    self.gizmo = (OPENGL_TO_WGPU_MATRIX * cgmath::ortho(-1.2, 1.2, -1.2, 1.2, -2.0, 2.0) * rotation).into();
+  }
+}
+
+fn create_pipelines(layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+  let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+    label: Some("Grid Shader"),
+    source: wgpu::ShaderSource::Wgsl(include_str!("grid.wgsl").into())
+  });
+  let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    label: Some("Grid Pipeline Layout"),
+    bind_group_layouts: &[layout],
+    push_constant_ranges: &[]
+  });
+  let create = |label: &str, vs_entry: &str, fs_entry: &str, buffers: &[wgpu::VertexBufferLayout], topology: wgpu::PrimitiveTopology, depth_compare: wgpu::CompareFunction| {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+      label: Some(label),
+      layout: Some(&pipeline_layout),
+      vertex: wgpu::VertexState {
+        module: &shader,
+        entry_point: vs_entry,
+        buffers
+      },
+      fragment: Some(wgpu::FragmentState {
+        module: &shader,
+        entry_point: fs_entry,
+        targets: &[wgpu::ColorTargetState {
+          format: Texture::HDR_FORMAT,
+          blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+          write_mask: wgpu::ColorWrites::ALL,
+        }],
+      }),
+      primitive: wgpu::PrimitiveState {
+        topology,
+        strip_index_format: None,
+        front_face: wgpu::FrontFace::Ccw,
+        cull_mode: None,
+        polygon_mode: wgpu::PolygonMode::Fill,
+        unclipped_depth: false,
+        conservative: false
+      },
+      depth_stencil: Some(wgpu::DepthStencilState {
+        format: depth_format,
+        depth_write_enabled: false,
+        depth_compare,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default()
+      }),
+      multisample: wgpu::MultisampleState {
+        count: sample_count,
+        mask: !0,
+        alpha_to_coverage_enabled: false,
+      },
+      multiview: None
+    })
+  };
+  // 网格在片元着色器中写入交点的深度，与场景互相遮挡
+  let grid_pipeline = create("Grid Pipeline", "vs_grid", "fs_grid", &[], wgpu::PrimitiveTopology::TriangleList, wgpu::CompareFunction::LessEqual);
+  // 坐标轴指示始终显示在最上层
+  let gizmo_pipeline = create("Gizmo Pipeline", "vs_gizmo", "fs_gizmo", &[LineVertex::desc()], wgpu::PrimitiveTopology::LineList, wgpu::CompareFunction::Always);
+  (grid_pipeline, gizmo_pipeline)
+}
@@ -0,0 +1,110 @@
+// 无限地面网格（全屏三角形中求视线与水平面的交点）与角落的坐标轴指示
+
+struct GridUniform {
+  view_projection: mat4x4<f32>;
+  inverse_view_projection: mat4x4<f32>;
+  /// 坐标轴指示的变换：只保留视图的旋转部分
+  gizmo: mat4x4<f32>;
+  eye_position: vec4<f32>;
+  /// 网格平面高度
+  height: f32;
+  /// 网格在此距离内逐渐淡出
+  fade_distance: f32;
+  padding_0: f32;
+  padding_1: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> grid: GridUniform;
+
+struct GridOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+  [[location(0)]] ndc: vec2<f32>;
+};
+
+struct GridFragment {
+  [[location(0)]] color: vec4<f32>;
+  [[builtin(frag_depth)]] depth: f32;
+};
+
+[[stage(vertex)]]
+fn vs_grid([[builtin(vertex_index)]] in_vertex_index: u32) -> GridOutput {
+  var out: GridOutput;
+  let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+  let y = f32(i32(in_vertex_index >> 1u) * 4 - 1);
+  out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+  out.ndc = vec2<f32>(x, y);
+  return out;
+}
+
+fn unproject(ndc: vec3<f32>) -> vec3<f32> {
+  let position = grid.inverse_view_projection * vec4<f32>(ndc, 1.0);
+  return position.xyz / position.w;
+}
+
+/// 距离最近网格线的覆盖度，线宽约为1像素
+fn grid_line(coord: vec2<f32>, spacing: f32) -> f32 {
+  let scaled = coord / spacing;
+  let derivative = fwidth(scaled);
+  let distance = abs(fract(scaled - vec2<f32>(0.5)) - vec2<f32>(0.5)) / derivative;
+  return 1.0 - min(min(distance.x, distance.y), 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_grid(in: GridOutput) -> GridFragment {
+  let near = unproject(vec3<f32>(in.ndc, 0.0));
+  let far = unproject(vec3<f32>(in.ndc, 1.0));
+  let ray = far - near;
+  let t = (grid.height - near.y) / ray.y;
+  let position = near + ray * t;
+  let coord = position.xz;
+
+  // 先计算导数，再根据交点是否有效丢弃片元
+  let minor = grid_line(coord, 1.0);
+  let major = grid_line(coord, 10.0);
+  let derivative = fwidth(coord);
+  var color = vec3<f32>(0.5);
+  var alpha = max(minor * 0.3, major * 0.7);
+  if (abs(coord.y) < derivative.y) {
+    color = vec3<f32>(1.0, 0.2, 0.2); // x轴
+    alpha = 1.0;
+  }
+  if (abs(coord.x) < derivative.x) {
+    color = vec3<f32>(0.2, 0.4, 1.0); // z轴
+    alpha = 1.0;
+  }
+  let fade = 1.0 - smoothStep(0.0, grid.fade_distance, distance(coord, grid.eye_position.xz));
+  alpha = alpha * fade;
+  if (t <= 0.0 || t > 1.0 || alpha <= 0.0) {
+    discard;
+  }
+
+  let clip = grid.view_projection * vec4<f32>(position, 1.0);
+  var out: GridFragment;
+  out.color = vec4<f32>(color, alpha);
+  out.depth = clip.z / clip.w;
+  return out;
+}
+
+struct GizmoInput {
+  [[location(0)]] position: vec3<f32>;
+  [[location(1)]] color: vec4<f32>;
+};
+
+struct GizmoOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+  [[location(0)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_gizmo(in: GizmoInput) -> GizmoOutput {
+  var out: GizmoOutput;
+  out.clip_position = grid.gizmo * vec4<f32>(in.position, 1.0);
+  out.color = in.color;
+  return out;
+}
+
+[[stage(fragment)]]
+fn fs_gizmo(in: GizmoOutput) -> [[location(0)]] vec4<f32> {
+  return in.color;
+}
@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+use std::time::{
+  Duration,
+  Instant
+};
+use crate::camera::Camera;
+use crate::debug::DebugView;
+use crate::shape::Instance;
+
+/// 一次可撤销的场景修改，保存修改前后的值
+#[derive(Debug, Copy, Clone)]
+pub enum Edit {
+  Instance {
+    index: usize,
+    before: Instance,
+    after: Instance,
+  },
+  Camera {
+    before: Camera,
+    after: Camera,
+  },
+  Background {
+    before: wgpu::Color,
+    after: wgpu::Color,
+  },
+  DebugView {
+    before: DebugView,
+    after: DebugView,
+  },
+}
+
+impl Edit {
+  /// 前后值互换，应用后即撤销原修改
+  pub fn reversed(&self) -> Self {
+    match *self {
+      Edit::Instance { index, before, after } => Edit::Instance { index, before: after, after: before },
+      Edit::Camera { before, after } => Edit::Camera { before: after, after: before },
+      Edit::Background { before, after } => Edit::Background { before: after, after: before },
+      Edit::DebugView { before, after } => Edit::DebugView { before: after, after: before },
+    }
+  }
+
+  /// 将紧接着的同类修改合并到自身（保留最早的修改前的值）；
+  /// 实例修改在拖动结束时才记录，已是一整次拖动，因此不合并
+  fn merge(&mut self, next: &Edit) -> bool {
+    match (self, next) {
+      (Edit::Camera { after, .. }, Edit::Camera { after: next, .. }) => *after = *next,
+      (Edit::Background { after, .. }, Edit::Background { after: next, .. }) => *after = *next,
+      (Edit::DebugView { after, .. }, Edit::DebugView { after: next, .. }) => *after = *next,
+      _ => return false,
+    }
+    true
+  }
+}
+
+/// 间隔小于该时长的同类修改（如连续移动鼠标、按住方向键）合并为一步
+const MERGE_WINDOW: Duration = Duration::from_millis(500);
+
+/// 撤销/重做历史，超过`limit`时丢弃最早的修改
+pub struct History {
+  undo: VecDeque<Edit>,
+  redo: Vec<Edit>,
+  limit: usize,
+  /// 上一次记录修改的时间
+  last_push: Option<Instant>,
+}
+
+impl History {
+  pub fn new(limit: usize) -> Self {
+    Self {
+      undo: VecDeque::new(),
+      redo: vec![],
+      limit,
+      last_push: None,
+    }
+  }
+
+  /// 记录一次已应用的修改，并清空重做记录
+  pub fn push(&mut self, edit: Edit) {
+    let now = Instant::now();
+    let recent = self.last_push.is_some_and(|last| now - last < MERGE_WINDOW);
+    self.last_push = Some(now);
+    self.redo.clear();
+    if recent {
+      if let Some(last) = self.undo.back_mut() {
+        if last.merge(&edit) {
+          return;
+        }
+      }
+    }
+    self.undo.push_back(edit);
+    while self.undo.len() > self.limit {
+      self.undo.pop_front();
+    }
+  }
+
+  pub fn clear(&mut self) {
+    self.undo.clear();
+    self.redo.clear();
+    self.last_push = None;
+  }
+
+  /// 返回需要应用的反向修改
+  pub fn undo(&mut self) -> Option<Edit> {
+    let edit = self.undo.pop_back()?;
+    self.redo.push(edit);
+    self.last_push = None; // 撤销后的新修改不与之前的合并
+    Some(edit.reversed())
+  }
+
+  /// 返回需要重新应用的修改
+  pub fn redo(&mut self) -> Option<Edit> {
+    let edit = self.redo.pop()?;
+    self.undo.push_back(edit);
+    self.last_push = None;
+    Some(edit)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn color(r: f64) -> wgpu::Color {
+    wgpu::Color { r, g: 0.0, b: 0.0, a: 1.0 }
+  }
+
+  fn background(before: f64, after: f64) -> Edit {
+    Edit::Background { before: color(before), after: color(after) }
+  }
+
+  fn view(before: DebugView, after: DebugView) -> Edit {
+    Edit::DebugView { before, after }
+  }
+
+  #[test]
+  fn undo_and_redo_reverse_edits() {
+    let mut history = History::new(10);
+    assert!(history.undo().is_none());
+    history.push(view(DebugView::Lit, DebugView::Depth));
+    assert!(matches!(history.undo(), Some(Edit::DebugView { before: DebugView::Depth, after: DebugView::Lit })));
+    assert!(history.undo().is_none());
+    assert!(matches!(history.redo(), Some(Edit::DebugView { before: DebugView::Lit, after: DebugView::Depth })));
+    assert!(history.redo().is_none());
+    assert!(history.undo().is_some());
+  }
+
+  #[test]
+  fn push_clears_redo() {
+    let mut history = History::new(10);
+    history.push(view(DebugView::Lit, DebugView::Depth));
+    history.undo();
+    history.push(background(0.0, 1.0));
+    assert!(history.redo().is_none());
+    assert!(matches!(history.undo(), Some(Edit::Background { .. })));
+    assert!(history.undo().is_none());
+  }
+
+  #[test]
+  fn merges_consecutive_edits_of_same_kind() {
+    let mut history = History::new(10);
+    history.push(background(0.0, 0.5));
+    history.push(background(0.5, 1.0));
+    // 合并后一次撤销回到最早的值
+    match history.undo() {
+      Some(Edit::Background { before, after }) => {
+        assert_eq!(before, color(1.0));
+        assert_eq!(after, color(0.0));
+      },
+      edit => panic!("unexpected {:?}", edit),
+    }
+    assert!(history.undo().is_none());
+    // 不同类的修改不合并
+    history.push(background(0.0, 0.5));
+    history.push(view(DebugView::Lit, DebugView::Normal));
+    assert!(matches!(history.undo(), Some(Edit::DebugView { .. })));
+    assert!(matches!(history.undo(), Some(Edit::Background { .. })));
+  }
+
+  #[test]
+  fn undo_ends_merging() {
+    let mut history = History::new(10);
+    history.push(background(0.0, 0.5));
+    history.push(view(DebugView::Lit, DebugView::Uv));
+    history.undo();
+    history.push(background(0.5, 1.0)); // 撤销之后的修改不与之前的合并
+    assert!(matches!(history.undo(), Some(Edit::Background { after, .. }) if after == color(0.5)));
+    assert!(matches!(history.undo(), Some(Edit::Background { after, .. }) if after == color(0.0)));
+  }
+
+  #[test]
+  fn drops_oldest_beyond_limit() {
+    let mut history = History::new(2);
+    // 交替不同类的修改，避免合并
+    history.push(background(0.0, 0.1));
+    history.push(view(DebugView::Lit, DebugView::Depth));
+    history.push(background(0.1, 0.2));
+    assert!(matches!(history.undo(), Some(Edit::Background { after, .. }) if after == color(0.1)));
+    assert!(matches!(history.undo(), Some(Edit::DebugView { .. })));
+    assert!(history.undo().is_none());
+  }
+}
@@ -0,0 +1,803 @@
+use std::{
+  fs,
+  num::NonZeroU32,
+  path::{Path, PathBuf},
+};
+use anyhow::*;
+use crate::texture::Texture;
+use crate::stats;
+
+/// 环境立方体贴图单面分辨率
+const ENV_SIZE: u32 = 256;
+/// 环境贴图mip层数（256 -> 1）
+const ENV_MIP_LEVELS: u32 = 9;
+/// 漫反射辐照度贴图单面分辨率（辐照度变化很平缓，分辨率不需要太高）
+const IRRADIANCE_SIZE: u32 = 32;
+/// 镜面反射预过滤贴图单面分辨率
+const PREFILTER_SIZE: u32 = 128;
+/// 预过滤贴图mip层数，第`i`级对应粗糙度`i / (PREFILTER_MIP_LEVELS - 1)`
+pub const PREFILTER_MIP_LEVELS: u32 = 5;
+const PREFILTER_SAMPLE_COUNT: u32 = 1024;
+const BRDF_LUT_SIZE: u32 = 256;
+/// 计算着色器工作组尺寸，与ibl.wgsl中的`workgroup_size`一致
+const WORKGROUP_SIZE: u32 = 8;
+
+/// 预计算结果的磁盘缓存目录，每个环境贴图来源一个子目录
+const CACHE_DIR: &str = "cache/ibl";
+const CACHE_MAGIC: &[u8; 4] = b"IBLC";
+/// 缓存文件格式版本号；ibl.wgsl、尺寸参数和环境贴图内容都计入缓存键，修改后旧缓存自动失效
+const CACHE_VERSION: u32 = 3;
+/// `Rgba32Float`每个纹素的字节数，用于上传等距柱状投影源图
+const SOURCE_BYTES_PER_TEXEL: u32 = 16;
+/// `Rgba16Float`每个纹素的字节数
+const BYTES_PER_TEXEL: u32 = 8;
+
+/// IBL相关uniform变量
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct IblUniform {
+  /// 环境光强度
+  pub intensity: f32,
+  /// 预过滤贴图的最大mip等级
+  pub max_lod: f32,
+  padding: [f32; 2],
+}
+
+/// 预过滤计算参数，与ibl.wgsl中的`PrefilterParams`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PrefilterParams {
+  roughness: f32,
+  resolution: f32,
+  sample_count: u32,
+  padding: u32,
+}
+
+/// IBL预计算得到的纹理
+struct IblTextures {
+  irradiance: Texture,
+  prefiltered: Texture,
+  brdf_lut: Texture,
+}
+
+/// 基于图像的光照（环境光）所需信息
+pub struct IblInfo {
+  pub uniform: IblUniform,
+  pub buffer: stats::Buffer,
+  pub group: wgpu::BindGroup,
+  pub layout: wgpu::BindGroupLayout,
+  /// 只通过bind group访问，持有以保证其存活
+  _textures: IblTextures,
+}
+
+/// 环境贴图来源
+pub enum Environment {
+  /// 程序化天空
+  Sky,
+  /// 等距柱状投影图（`.hdr`或普通图片），保留文件内容用于计算缓存键
+  Equirect {
+    path: PathBuf,
+    bytes: Vec<u8>,
+  },
+}
+
+impl Environment {
+  /// 读取环境贴图文件，未指定或读取失败时使用程序化天空
+  pub fn load(path: Option<&Path>) -> Self {
+    let path = match path {
+      Some(path) => path,
+      None => return Environment::Sky,
+    };
+    match fs::read(path) {
+      Result::Ok(bytes) => Environment::Equirect { path: path.to_path_buf(), bytes },
+      Err(err) => {
+        eprintln!("failed to read environment {}: {}, using procedural sky", path.display(), err);
+        Environment::Sky
+      }
+    }
+  }
+
+  /// 缓存键：对着色器源码、尺寸参数和环境贴图内容取FNV-1a哈希，同时写入缓存文件头
+  ///
+  /// 不使用`DefaultHasher`，其算法未作规定，升级工具链后可能改变，导致缓存无故失效
+  fn cache_key(&self) -> u64 {
+    let mut hash = fnv1a(FNV_OFFSET_BASIS, include_str!("ibl.wgsl").as_bytes());
+    for value in [ENV_SIZE, ENV_MIP_LEVELS, IRRADIANCE_SIZE, PREFILTER_SIZE, PREFILTER_MIP_LEVELS, PREFILTER_SAMPLE_COUNT, BRDF_LUT_SIZE] {
+      hash = fnv1a(hash, &value.to_le_bytes());
+    }
+    match self {
+      Environment::Sky => hash,
+      Environment::Equirect { bytes, .. } => fnv1a(fnv1a(hash, b"equirect"), bytes),
+    }
+  }
+
+  /// 缓存子目录名
+  fn cache_dir(&self) -> PathBuf {
+    let kind = match self {
+      Environment::Sky => "sky",
+      Environment::Equirect { .. } => "equirect",
+    };
+    Path::new(CACHE_DIR).join(format!("{}-{:016x}", kind, self.cache_key()))
+  }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64位FNV-1a，在`hash`的基础上继续累加`bytes`
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+  bytes.iter().fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+impl IblInfo {
+  /// 优先从磁盘缓存加载预计算结果，缓存不可用时在GPU上重新生成并写入缓存
+  pub fn new(environment: &Environment, device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+    let cache_dir = environment.cache_dir();
+    let key = environment.cache_key();
+    let textures = match load_cache(&cache_dir, key, device, queue) {
+      Result::Ok(textures) => textures,
+      Err(err) => {
+        println!("IBL cache unavailable ({}), regenerating", err);
+        let textures = match generate(environment, device, queue) {
+          Result::Ok(textures) => textures,
+          Err(err) => {
+            eprintln!("failed to load environment: {:?}, using procedural sky", err);
+            return Self::new(&Environment::Sky, device, queue);
+          }
+        };
+        if let Err(err) = save_cache(&cache_dir, key, device, queue, &textures) {
+          eprintln!("failed to save IBL cache: {:?}", err);
+        }
+        textures
+      }
+    };
+    let uniform = IblUniform {
+      intensity: 1.0,
+      max_lod: (PREFILTER_MIP_LEVELS - 1) as f32,
+      padding: [0.0; 2],
+    };
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("IBL buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("IBL bind group layout"),
+      entries: &[
+        texture_entry(0, wgpu::TextureViewDimension::Cube), // 漫反射辐照度
+        texture_entry(1, wgpu::TextureViewDimension::Cube), // 镜面反射预过滤
+        texture_entry(2, wgpu::TextureViewDimension::D2), // BRDF查找表
+        wgpu::BindGroupLayoutEntry {
+          binding: 3,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+          count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+          binding: 4,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        }
+      ]
+    });
+    let group = create_group(&layout, &textures, &buffer, device);
+    Self {
+      uniform,
+      buffer,
+      group,
+      layout,
+      _textures: textures,
+    }
+  }
+
+  /// 调整环境光强度
+  pub fn set_intensity(&mut self, intensity: f32, queue: &wgpu::Queue) {
+    self.uniform.intensity = intensity.max(0.0);
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+}
+
+fn create_group(
+  layout: &wgpu::BindGroupLayout,
+  textures: &IblTextures,
+  buffer: &wgpu::Buffer,
+  device: &wgpu::Device,
+) -> wgpu::BindGroup {
+  device.create_bind_group(&wgpu::BindGroupDescriptor {
+    label: Some("IBL bind group"),
+    layout,
+    entries: &[
+      wgpu::BindGroupEntry {
+        binding: 0,
+        resource: wgpu::BindingResource::TextureView(&textures.irradiance.view)
+      },
+      wgpu::BindGroupEntry {
+        binding: 1,
+        resource: wgpu::BindingResource::TextureView(&textures.prefiltered.view)
+      },
+      wgpu::BindGroupEntry {
+        binding: 2,
+        resource: wgpu::BindingResource::TextureView(&textures.brdf_lut.view)
+      },
+      wgpu::BindGroupEntry {
+        binding: 3,
+        resource: wgpu::BindingResource::Sampler(&textures.prefiltered.sampler)
+      },
+      wgpu::BindGroupEntry {
+        binding: 4,
+        resource: buffer.as_entire_binding()
+      }
+    ]
+  })
+}
+
+fn texture_entry(binding: u32, view_dimension: wgpu::TextureViewDimension) -> wgpu::BindGroupLayoutEntry {
+  wgpu::BindGroupLayoutEntry {
+    binding,
+    visibility: wgpu::ShaderStages::FRAGMENT,
+    ty: wgpu::BindingType::Texture {
+      multisampled: false,
+      view_dimension,
+      sample_type: wgpu::TextureSampleType::Float { filterable: true },
+    },
+    count: None
+  }
+}
+
+/// 计算着色器可写的HDR纹理
+fn storage_entry(binding: u32, view_dimension: wgpu::TextureViewDimension) -> wgpu::BindGroupLayoutEntry {
+  wgpu::BindGroupLayoutEntry {
+    binding,
+    visibility: wgpu::ShaderStages::COMPUTE,
+    ty: wgpu::BindingType::StorageTexture {
+      access: wgpu::StorageTextureAccess::WriteOnly,
+      format: Texture::HDR_FORMAT,
+      view_dimension,
+    },
+    count: None
+  }
+}
+
+fn dispatch_size(size: u32) -> u32 {
+  size.div_ceil(WORKGROUP_SIZE)
+}
+
+/// 将sRGB编码的8位分量转换到线性空间
+fn srgb_to_linear(value: u8) -> f32 {
+  let c = value as f32 / 255.0;
+  if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// 解码等距柱状投影图为线性RGBA浮点数据：Radiance `.hdr`保留HDR数值，其他格式按sRGB转换
+fn decode_equirect(bytes: &[u8]) -> Result<(u32, u32, Vec<f32>)> {
+  // Radiance文件以`#?RADIANCE`或`#?RGBE`开头，image不会按内容识别
+  if bytes.starts_with(b"#?") {
+    let decoder = image::codecs::hdr::HdrDecoder::new(std::io::Cursor::new(bytes))?;
+    let meta = decoder.metadata();
+    let data = decoder.read_image_hdr()?.iter().flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 1.0]).collect();
+    Ok((meta.width, meta.height, data))
+  } else {
+    let image = image::load_from_memory(bytes)?.to_rgba8();
+    let data = image.pixels()
+      .flat_map(|pixel| [srgb_to_linear(pixel[0]), srgb_to_linear(pixel[1]), srgb_to_linear(pixel[2]), 1.0])
+      .collect();
+    Ok((image.width(), image.height(), data))
+  }
+}
+
+/// 将等距柱状投影源图上传为`Rgba32Float`纹理（不可过滤，着色器中手动双线性插值）
+fn upload_equirect(path: &Path, bytes: &[u8], device: &wgpu::Device, queue: &wgpu::Queue) -> Result<stats::Texture> {
+  let (width, height, data) = decode_equirect(bytes).with_context(|| format!("decoding {}", path.display()))?;
+  let max = device.limits().max_texture_dimension_2d;
+  ensure!(width <= max && height <= max, "{} is {}x{}, larger than the {} texel limit", path.display(), width, height, max);
+  let size = wgpu::Extent3d {
+    width,
+    height,
+    depth_or_array_layers: 1,
+  };
+  let texture = stats::create_texture(device, &wgpu::TextureDescriptor {
+    label: Some("environment_source"),
+    size,
+    mip_level_count: 1,
+    sample_count: 1,
+    dimension: wgpu::TextureDimension::D2,
+    format: wgpu::TextureFormat::Rgba32Float,
+    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+  });
+  queue.write_texture(
+    wgpu::ImageCopyTexture {
+      aspect: wgpu::TextureAspect::All,
+      texture: &texture,
+      mip_level: 0,
+      origin: wgpu::Origin3d::ZERO,
+    },
+    bytemuck::cast_slice(&data),
+    wgpu::ImageDataLayout {
+      offset: 0,
+      bytes_per_row: NonZeroU32::new(width * SOURCE_BYTES_PER_TEXEL),
+      rows_per_image: NonZeroU32::new(height),
+    },
+    size,
+  );
+  println!("loaded environment {} ({}x{})", path.display(), width, height);
+  Ok(texture)
+}
+
+/// 在GPU上生成环境贴图，并据此预计算辐照度、预过滤贴图和BRDF查找表
+fn generate(environment: &Environment, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<IblTextures> {
+  // 先在CPU上解码源图，失败时还没有提交任何GPU工作
+  let source = match environment {
+    Environment::Sky => None,
+    Environment::Equirect { path, bytes } => Some(upload_equirect(path, bytes, device, queue)?),
+  };
+  let source_view = source.as_ref().map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+  let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+    label: Some("IBL Shader"),
+    source: wgpu::ShaderSource::Wgsl(include_str!("ibl.wgsl").into())
+  });
+  let env = Texture::create_cube(device, ENV_SIZE, ENV_MIP_LEVELS, "environment_cube");
+  let irradiance = Texture::create_cube(device, IRRADIANCE_SIZE, 1, "irradiance_cube");
+  let prefiltered = Texture::create_cube(device, PREFILTER_SIZE, PREFILTER_MIP_LEVELS, "prefiltered_cube");
+  let brdf_lut = Texture::create_storage_2d(device, BRDF_LUT_SIZE, BRDF_LUT_SIZE, "brdf_lut");
+
+  let env_texture_entry = wgpu::BindGroupLayoutEntry {
+    visibility: wgpu::ShaderStages::COMPUTE,
+    ..texture_entry(0, wgpu::TextureViewDimension::Cube)
+  };
+  let env_sampler_entry = wgpu::BindGroupLayoutEntry {
+    binding: 1,
+    visibility: wgpu::ShaderStages::COMPUTE,
+    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+    count: None,
+  };
+  let params_entry = wgpu::BindGroupLayoutEntry {
+    binding: 2,
+    visibility: wgpu::ShaderStages::COMPUTE,
+    ty: wgpu::BindingType::Buffer {
+      ty: wgpu::BufferBindingType::Uniform,
+      has_dynamic_offset: false,
+      min_binding_size: None
+    },
+    count: None
+  };
+  let cube_output_entry = storage_entry(3, wgpu::TextureViewDimension::D2Array);
+  let lut_output_entry = storage_entry(4, wgpu::TextureViewDimension::D2);
+  let source_entry = wgpu::BindGroupLayoutEntry {
+    binding: 5,
+    visibility: wgpu::ShaderStages::COMPUTE,
+    ty: wgpu::BindingType::Texture {
+      multisampled: false,
+      view_dimension: wgpu::TextureViewDimension::D2,
+      sample_type: wgpu::TextureSampleType::Float { filterable: false },
+    },
+    count: None
+  };
+  let env_mip_entry = wgpu::BindGroupLayoutEntry {
+    binding: 6,
+    ty: wgpu::BindingType::Texture {
+      multisampled: false,
+      view_dimension: wgpu::TextureViewDimension::D2Array,
+      sample_type: wgpu::TextureSampleType::Float { filterable: false },
+    },
+    ..source_entry
+  };
+
+  let sky_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    label: Some("IBL sky layout"),
+    entries: &[cube_output_entry],
+  });
+  let equirect_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    label: Some("IBL equirect layout"),
+    entries: &[source_entry, cube_output_entry],
+  });
+  let downsample_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    label: Some("IBL downsample layout"),
+    entries: &[env_mip_entry, cube_output_entry],
+  });
+  let irradiance_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    label: Some("IBL irradiance layout"),
+    entries: &[env_texture_entry, env_sampler_entry, cube_output_entry],
+  });
+  let prefilter_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    label: Some("IBL prefilter layout"),
+    entries: &[env_texture_entry, env_sampler_entry, params_entry, cube_output_entry],
+  });
+  let brdf_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    label: Some("IBL brdf layout"),
+    entries: &[lut_output_entry],
+  });
+  let create_pipeline = |layout: &wgpu::BindGroupLayout, entry_point: &str| {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("IBL Pipeline Layout"),
+      bind_group_layouts: &[layout],
+      push_constant_ranges: &[]
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+      label: Some(entry_point),
+      layout: Some(&pipeline_layout),
+      module: &shader,
+      entry_point,
+    })
+  };
+  let sky_pipeline = create_pipeline(&sky_layout, "cs_sky");
+  let equirect_pipeline = create_pipeline(&equirect_layout, "cs_equirect");
+  let downsample_pipeline = create_pipeline(&downsample_layout, "cs_downsample");
+  let irradiance_pipeline = create_pipeline(&irradiance_layout, "cs_irradiance");
+  let prefilter_pipeline = create_pipeline(&prefilter_layout, "cs_prefilter");
+  let brdf_pipeline = create_pipeline(&brdf_layout, "cs_brdf");
+
+  // 程序化天空的每级mip都直接生成，不需要额外的降采样；源图只采样到第0级，之后逐级降采样
+  let env_views = (0..ENV_MIP_LEVELS).map(|mip| env.mip_layers_view(mip)).collect::<Vec<_>>();
+  let env_groups = match &source_view {
+    None => env_views.iter().map(|view| {
+      device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("IBL sky bind group"),
+        layout: &sky_layout,
+        entries: &[
+          wgpu::BindGroupEntry {
+            binding: 3,
+            resource: wgpu::BindingResource::TextureView(view)
+          }
+        ]
+      })
+    }).collect::<Vec<_>>(),
+    Some(source_view) => {
+      let mut groups = vec![device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("IBL equirect bind group"),
+        layout: &equirect_layout,
+        entries: &[
+          wgpu::BindGroupEntry {
+            binding: 5,
+            resource: wgpu::BindingResource::TextureView(source_view)
+          },
+          wgpu::BindGroupEntry {
+            binding: 3,
+            resource: wgpu::BindingResource::TextureView(&env_views[0])
+          }
+        ]
+      })];
+      groups.extend(env_views.windows(2).map(|pair| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+          label: Some("IBL downsample bind group"),
+          layout: &downsample_layout,
+          entries: &[
+            wgpu::BindGroupEntry {
+              binding: 6,
+              resource: wgpu::BindingResource::TextureView(&pair[0])
+            },
+            wgpu::BindGroupEntry {
+              binding: 3,
+              resource: wgpu::BindingResource::TextureView(&pair[1])
+            }
+          ]
+        })
+      }));
+      groups
+    }
+  };
+  let irradiance_view = irradiance.mip_layers_view(0);
+  let irradiance_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+    label: Some("IBL irradiance bind group"),
+    layout: &irradiance_layout,
+    entries: &[
+      wgpu::BindGroupEntry {
+        binding: 0,
+        resource: wgpu::BindingResource::TextureView(&env.view)
+      },
+      wgpu::BindGroupEntry {
+        binding: 1,
+        resource: wgpu::BindingResource::Sampler(&env.sampler)
+      },
+      wgpu::BindGroupEntry {
+        binding: 3,
+        resource: wgpu::BindingResource::TextureView(&irradiance_view)
+      }
+    ]
+  });
+  let prefilter_views = (0..PREFILTER_MIP_LEVELS).map(|mip| prefiltered.mip_layers_view(mip)).collect::<Vec<_>>();
+  let prefilter_buffers = (0..PREFILTER_MIP_LEVELS).map(|mip| {
+    let params = PrefilterParams {
+      roughness: mip as f32 / (PREFILTER_MIP_LEVELS - 1) as f32,
+      resolution: ENV_SIZE as f32,
+      sample_count: PREFILTER_SAMPLE_COUNT,
+      padding: 0,
+    };
+    stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("IBL prefilter params"),
+      contents: bytemuck::cast_slice(&[params]),
+      usage: wgpu::BufferUsages::UNIFORM
+    })
+  }).collect::<Vec<_>>();
+  let prefilter_groups = prefilter_views.iter().zip(prefilter_buffers.iter()).map(|(view, buffer)| {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("IBL prefilter bind group"),
+      layout: &prefilter_layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: wgpu::BindingResource::TextureView(&env.view)
+        },
+        wgpu::BindGroupEntry {
+          binding: 1,
+          resource: wgpu::BindingResource::Sampler(&env.sampler)
+        },
+        wgpu::BindGroupEntry {
+          binding: 2,
+          resource: buffer.as_entire_binding()
+        },
+        wgpu::BindGroupEntry {
+          binding: 3,
+          resource: wgpu::BindingResource::TextureView(view)
+        }
+      ]
+    })
+  }).collect::<Vec<_>>();
+  let brdf_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+    label: Some("IBL brdf bind group"),
+    layout: &brdf_layout,
+    entries: &[
+      wgpu::BindGroupEntry {
+        binding: 4,
+        resource: wgpu::BindingResource::TextureView(&brdf_lut.view)
+      }
+    ]
+  });
+
+  let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+    label: Some("IBL Encoder")
+  });
+  {
+    let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+      label: Some("IBL Environment Pass")
+    });
+    for (mip, group) in env_groups.iter().enumerate() {
+      let pipeline = match (&source_view, mip) {
+        (None, _) => &sky_pipeline,
+        (Some(_), 0) => &equirect_pipeline,
+        (Some(_), _) => &downsample_pipeline,
+      };
+      let size = (ENV_SIZE >> mip).max(1);
+      pass.set_pipeline(pipeline);
+      pass.set_bind_group(0, group, &[]);
+      pass.dispatch(dispatch_size(size), dispatch_size(size), 6);
+    }
+  }
+  {
+    let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+      label: Some("IBL Convolution Pass")
+    });
+    pass.set_pipeline(&irradiance_pipeline);
+    pass.set_bind_group(0, &irradiance_group, &[]);
+    pass.dispatch(dispatch_size(IRRADIANCE_SIZE), dispatch_size(IRRADIANCE_SIZE), 6);
+    pass.set_pipeline(&prefilter_pipeline);
+    for (mip, group) in prefilter_groups.iter().enumerate() {
+      let size = (PREFILTER_SIZE >> mip).max(1);
+      pass.set_bind_group(0, group, &[]);
+      pass.dispatch(dispatch_size(size), dispatch_size(size), 6);
+    }
+    pass.set_pipeline(&brdf_pipeline);
+    pass.set_bind_group(0, &brdf_group, &[]);
+    pass.dispatch(dispatch_size(BRDF_LUT_SIZE), dispatch_size(BRDF_LUT_SIZE), 1);
+  }
+  queue.submit(std::iter::once(encoder.finish()));
+
+  Ok(IblTextures {
+    irradiance,
+    prefiltered,
+    brdf_lut,
+  })
+}
+
+/// 缓存文件描述：文件名、单面尺寸、层数、mip层数
+struct CacheEntry {
+  name: &'static str,
+  size: u32,
+  layers: u32,
+  mips: u32,
+}
+
+const IRRADIANCE_CACHE: CacheEntry = CacheEntry { name: "irradiance.bin", size: IRRADIANCE_SIZE, layers: 6, mips: 1 };
+const PREFILTER_CACHE: CacheEntry = CacheEntry { name: "prefiltered.bin", size: PREFILTER_SIZE, layers: 6, mips: PREFILTER_MIP_LEVELS };
+const BRDF_CACHE: CacheEntry = CacheEntry { name: "brdf_lut.bin", size: BRDF_LUT_SIZE, layers: 1, mips: 1 };
+
+impl CacheEntry {
+  /// 魔数、版本号、缓存键和纹理尺寸
+  fn header(&self, key: u64) -> Vec<u8> {
+    let mut header = CACHE_MAGIC.to_vec();
+    header.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+    header.extend_from_slice(&key.to_le_bytes());
+    for value in [self.size, self.layers, self.mips] {
+      header.extend_from_slice(&value.to_le_bytes());
+    }
+    header
+  }
+
+  /// 所有mip的纹理数据总字节数（不含行对齐）
+  fn data_len(&self) -> usize {
+    (0..self.mips).map(|mip| {
+      let size = (self.size >> mip).max(1);
+      (size * size * self.layers * BYTES_PER_TEXEL) as usize
+    }).sum()
+  }
+
+  fn read(&self, dir: &Path, key: u64) -> Result<Vec<u8>> {
+    let path = dir.join(self.name);
+    let bytes = fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+    let header = self.header(key);
+    ensure!(bytes.starts_with(&header), "{} is outdated", path.display());
+    ensure!(bytes.len() == header.len() + self.data_len(), "{} is truncated", path.display());
+    Ok(bytes[header.len()..].to_vec())
+  }
+
+  fn write(&self, dir: &Path, key: u64, data: &[u8]) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut bytes = self.header(key);
+    bytes.extend_from_slice(data);
+    fs::write(dir.join(self.name), bytes)?;
+    Ok(())
+  }
+
+  /// 按mip逐级上传数据
+  fn upload(&self, queue: &wgpu::Queue, texture: &Texture, data: &[u8]) {
+    let mut offset = 0;
+    for mip in 0..self.mips {
+      let size = (self.size >> mip).max(1);
+      let len = (size * size * self.layers * BYTES_PER_TEXEL) as usize;
+      queue.write_texture(
+        wgpu::ImageCopyTexture {
+          aspect: wgpu::TextureAspect::All,
+          texture: &texture.texture,
+          mip_level: mip,
+          origin: wgpu::Origin3d::ZERO,
+        },
+        &data[offset..offset + len],
+        wgpu::ImageDataLayout {
+          offset: 0,
+          bytes_per_row: NonZeroU32::new(size * BYTES_PER_TEXEL),
+          rows_per_image: NonZeroU32::new(size),
+        },
+        wgpu::Extent3d {
+          width: size,
+          height: size,
+          depth_or_array_layers: self.layers,
+        },
+      );
+      offset += len;
+    }
+  }
+
+  /// 将纹理所有mip读回CPU，去掉`COPY_BYTES_PER_ROW_ALIGNMENT`带来的行填充
+  fn download(&self, device: &wgpu::Device, queue: &wgpu::Queue, texture: &Texture) -> Result<Vec<u8>> {
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+      label: Some("IBL Readback Encoder")
+    });
+    let buffers = (0..self.mips).map(|mip| {
+      let size = (self.size >> mip).max(1);
+      let padded_row = padded_bytes_per_row(size);
+      let buffer = stats::create_buffer(device, &wgpu::BufferDescriptor {
+        label: Some("IBL readback buffer"),
+        size: (padded_row * size * self.layers) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+      });
+      encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+          aspect: wgpu::TextureAspect::All,
+          texture: &texture.texture,
+          mip_level: mip,
+          origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::ImageCopyBuffer {
+          buffer: &buffer,
+          layout: wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(padded_row),
+            rows_per_image: NonZeroU32::new(size),
+          },
+        },
+        wgpu::Extent3d {
+          width: size,
+          height: size,
+          depth_or_array_layers: self.layers,
+        },
+      );
+      buffer
+    }).collect::<Vec<_>>();
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let mut data = Vec::with_capacity(self.data_len());
+    for (mip, buffer) in buffers.iter().enumerate() {
+      let size = (self.size >> mip).max(1);
+      let row = (size * BYTES_PER_TEXEL) as usize;
+      let padded_row = padded_bytes_per_row(size) as usize;
+      let slice = buffer.slice(..);
+      let mapping = slice.map_async(wgpu::MapMode::Read);
+      device.poll(wgpu::Maintain::Wait);
+      pollster::block_on(mapping)?;
+      {
+        let mapped = slice.get_mapped_range();
+        for padded in mapped.chunks(padded_row) {
+          data.extend_from_slice(&padded[..row]);
+        }
+      }
+      buffer.unmap();
+    }
+    Ok(data)
+  }
+}
+
+fn padded_bytes_per_row(width: u32) -> u32 {
+  let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+  (width * BYTES_PER_TEXEL).div_ceil(align) * align
+}
+
+fn load_cache(dir: &Path, key: u64, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<IblTextures> {
+  let irradiance_data = IRRADIANCE_CACHE.read(dir, key)?;
+  let prefilter_data = PREFILTER_CACHE.read(dir, key)?;
+  let brdf_data = BRDF_CACHE.read(dir, key)?;
+  let irradiance = Texture::create_cube(device, IRRADIANCE_SIZE, 1, "irradiance_cube");
+  let prefiltered = Texture::create_cube(device, PREFILTER_SIZE, PREFILTER_MIP_LEVELS, "prefiltered_cube");
+  let brdf_lut = Texture::create_storage_2d(device, BRDF_LUT_SIZE, BRDF_LUT_SIZE, "brdf_lut");
+  IRRADIANCE_CACHE.upload(queue, &irradiance, &irradiance_data);
+  PREFILTER_CACHE.upload(queue, &prefiltered, &prefilter_data);
+  BRDF_CACHE.upload(queue, &brdf_lut, &brdf_data);
+  Ok(IblTextures {
+    irradiance,
+    prefiltered,
+    brdf_lut,
+  })
+}
+
+fn save_cache(dir: &Path, key: u64, device: &wgpu::Device, queue: &wgpu::Queue, textures: &IblTextures) -> Result<()> {
+  IRRADIANCE_CACHE.write(dir, key, &IRRADIANCE_CACHE.download(device, queue, &textures.irradiance)?)?;
+  PREFILTER_CACHE.write(dir, key, &PREFILTER_CACHE.download(device, queue, &textures.prefiltered)?)?;
+  BRDF_CACHE.write(dir, key, &BRDF_CACHE.download(device, queue, &textures.brdf_lut)?)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn equirect(bytes: &[u8]) -> Environment {
+    Environment::Equirect { path: PathBuf::from("env.hdr"), bytes: bytes.to_vec() }
+  }
+
+  #[test]
+  fn cache_key_depends_on_source() {
+    assert_eq!(Environment::Sky.cache_key(), Environment::Sky.cache_key());
+    assert_eq!(equirect(b"a").cache_key(), equirect(b"a").cache_key());
+    assert_ne!(equirect(b"a").cache_key(), equirect(b"b").cache_key());
+    assert_ne!(Environment::Sky.cache_key(), equirect(b"").cache_key());
+    assert!(equirect(b"a").cache_dir().ends_with(format!("equirect-{:016x}", equirect(b"a").cache_key())));
+  }
+
+  #[test]
+  fn fnv1a_reference_values() {
+    // 与FNV规范中的测试向量一致，不随工具链变化
+    assert_eq!(fnv1a(FNV_OFFSET_BASIS, b""), 0xcbf29ce484222325);
+    assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"foobar"), 0x85944171f73967e8);
+    assert_eq!(fnv1a(fnv1a(FNV_OFFSET_BASIS, b"foo"), b"bar"), fnv1a(FNV_OFFSET_BASIS, b"foobar"));
+  }
+
+  #[test]
+  fn cache_header_contains_key() {
+    let header = BRDF_CACHE.header(0x0123456789abcdef);
+    assert!(header.starts_with(CACHE_MAGIC));
+    assert_eq!(&header[8..16], &0x0123456789abcdefu64.to_le_bytes());
+    assert_ne!(BRDF_CACHE.header(1), BRDF_CACHE.header(2));
+  }
+
+  #[test]
+  fn srgb_to_linear_endpoints() {
+    assert_eq!(srgb_to_linear(0), 0.0);
+    assert!((srgb_to_linear(255) - 1.0).abs() < 1e-6);
+    assert!((srgb_to_linear(188) - 0.5).abs() < 0.01);
+  }
+}
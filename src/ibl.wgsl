@@ -0,0 +1,267 @@
+// 基于图像的光照（IBL）预计算：环境贴图、漫反射辐照度、镜面反射预过滤以及BRDF积分查找表
+
+let PI: f32 = 3.14159265359;
+
+struct PrefilterParams {
+  roughness: f32;
+  /// 源环境贴图单个面的分辨率
+  resolution: f32;
+  sample_count: u32;
+  padding: u32;
+};
+
+[[group(0), binding(0)]]
+var env_t: texture_cube<f32>;
+[[group(0), binding(1)]]
+var env_s: sampler;
+[[group(0), binding(2)]]
+var<uniform> params: PrefilterParams;
+[[group(0), binding(3)]]
+var output_cube: texture_storage_2d_array<rgba16float, write>;
+[[group(0), binding(4)]]
+var output_lut: texture_storage_2d<rgba16float, write>;
+/// 等距柱状投影源图（rgba32float，不可过滤）
+[[group(0), binding(5)]]
+var source_t: texture_2d<f32>;
+/// 降采样时的上一级环境贴图mip
+[[group(0), binding(6)]]
+var env_mip_t: texture_2d_array<f32>;
+
+/// 根据立方体贴图的面索引和面内坐标（以纹素为单位）计算采样方向（与wgpu的立方体贴图约定一致）
+fn cube_direction(face: u32, position: vec2<f32>, size: vec2<i32>) -> vec3<f32> {
+  let uv = position / vec2<f32>(size) * 2.0 - vec2<f32>(1.0);
+  var dir: vec3<f32>;
+  switch (i32(face)) {
+    case 0: { dir = vec3<f32>(1.0, -uv.y, -uv.x); }
+    case 1: { dir = vec3<f32>(-1.0, -uv.y, uv.x); }
+    case 2: { dir = vec3<f32>(uv.x, 1.0, uv.y); }
+    case 3: { dir = vec3<f32>(uv.x, -1.0, -uv.y); }
+    case 4: { dir = vec3<f32>(uv.x, -uv.y, 1.0); }
+    default: { dir = vec3<f32>(-uv.x, -uv.y, -1.0); }
+  }
+  return normalize(dir);
+}
+
+/// 程序化天空：天顶到地平线的渐变，加上一个HDR太阳
+fn sky(dir: vec3<f32>) -> vec3<f32> {
+  let sun_dir = normalize(vec3<f32>(0.4, 0.6, 0.3));
+  let zenith = vec3<f32>(0.12, 0.3, 0.75);
+  let horizon = vec3<f32>(0.75, 0.82, 0.95);
+  let ground = vec3<f32>(0.22, 0.2, 0.18);
+  let up = max(dir.y, 0.0);
+  let down = max(-dir.y, 0.0);
+  let sky_color = mix(horizon, zenith, pow(up, 0.5));
+  let ground_color = mix(horizon * 0.5, ground, pow(down, 0.3));
+  var color = select(ground_color, sky_color, dir.y >= 0.0);
+  let sun = max(dot(dir, sun_dir), 0.0);
+  color = color + vec3<f32>(1.0, 0.92, 0.8) * (pow(sun, 400.0) * 40.0 + pow(sun, 16.0) * 0.4);
+  return color;
+}
+
+/// 读取源图纹素，水平方向环绕，垂直方向钳制
+fn source_texel(x: i32, y: i32, size: vec2<i32>) -> vec3<f32> {
+  let wrapped = ((x % size.x) + size.x) % size.x;
+  return textureLoad(source_t, vec2<i32>(wrapped, clamp(y, 0, size.y - 1)), 0).rgb;
+}
+
+/// 按方向对等距柱状投影源图做双线性采样，图像顶部对应+Y，中心对应-Z
+fn equirect(dir: vec3<f32>) -> vec3<f32> {
+  let size = textureDimensions(source_t);
+  let uv = vec2<f32>(atan2(dir.x, -dir.z) / (2.0 * PI) + 0.5, acos(clamp(dir.y, -1.0, 1.0)) / PI);
+  let position = uv * vec2<f32>(size) - vec2<f32>(0.5);
+  let base = floor(position);
+  let f = position - base;
+  let x = i32(base.x);
+  let y = i32(base.y);
+  let top = mix(source_texel(x, y, size), source_texel(x + 1, y, size), f.x);
+  let bottom = mix(source_texel(x, y + 1, size), source_texel(x + 1, y + 1, size), f.x);
+  return mix(top, bottom, f.y);
+}
+
+/// Van der Corput序列（位反转）
+fn radical_inverse(index: u32) -> f32 {
+  var bits = index;
+  bits = (bits << 16u) | (bits >> 16u);
+  bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+  bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+  bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+  bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+  return f32(bits) * 2.3283064365386963e-10;
+}
+
+fn hammersley(i: u32, n: u32) -> vec2<f32> {
+  return vec2<f32>(f32(i) / f32(n), radical_inverse(i));
+}
+
+/// GGX重要性采样，返回世界空间下的半程向量
+fn importance_sample_ggx(xi: vec2<f32>, n: vec3<f32>, roughness: f32) -> vec3<f32> {
+  let a = roughness * roughness;
+  let phi = 2.0 * PI * xi.x;
+  let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+  let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+  let h = vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+  let up = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 0.0, 1.0), abs(n.z) < 0.999);
+  let tangent = normalize(cross(up, n));
+  let bitangent = cross(n, tangent);
+  return normalize(tangent * h.x + bitangent * h.y + n * h.z);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+  let a = roughness * roughness;
+  let a2 = a * a;
+  let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+  return a2 / (PI * d * d);
+}
+
+/// IBL使用的Schlick-GGX几何遮蔽项（k = a^2 / 2）
+fn geometry_smith_ibl(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+  let k = roughness * roughness / 2.0;
+  let ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+  let ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+  return ggx_v * ggx_l;
+}
+
+/// 生成环境立方体贴图（每级mip单独调度）
+[[stage(compute), workgroup_size(8, 8, 1)]]
+fn cs_sky([[builtin(global_invocation_id)]] id: vec3<u32>) {
+  let size = textureDimensions(output_cube);
+  if (i32(id.x) >= size.x || i32(id.y) >= size.y) {
+    return;
+  }
+  // 每个纹素4x4超采样，低分辨率mip中的太阳也能保留能量
+  var color = vec3<f32>(0.0);
+  for (var sy = 0u; sy < 4u; sy = sy + 1u) {
+    for (var sx = 0u; sx < 4u; sx = sx + 1u) {
+      let offset = (vec2<f32>(f32(sx), f32(sy)) + vec2<f32>(0.5)) / 4.0;
+      color = color + sky(cube_direction(id.z, vec2<f32>(id.xy) + offset, size));
+    }
+  }
+  textureStore(output_cube, vec2<i32>(id.xy), i32(id.z), vec4<f32>(color / 16.0, 1.0));
+}
+
+/// 由等距柱状投影源图生成环境贴图第0级mip
+[[stage(compute), workgroup_size(8, 8, 1)]]
+fn cs_equirect([[builtin(global_invocation_id)]] id: vec3<u32>) {
+  let size = textureDimensions(output_cube);
+  if (i32(id.x) >= size.x || i32(id.y) >= size.y) {
+    return;
+  }
+  var color = vec3<f32>(0.0);
+  for (var sy = 0u; sy < 4u; sy = sy + 1u) {
+    for (var sx = 0u; sx < 4u; sx = sx + 1u) {
+      let offset = (vec2<f32>(f32(sx), f32(sy)) + vec2<f32>(0.5)) / 4.0;
+      color = color + equirect(cube_direction(id.z, vec2<f32>(id.xy) + offset, size));
+    }
+  }
+  // 钳制到rgba16float的最大值，避免极亮的太阳变成无穷大
+  textureStore(output_cube, vec2<i32>(id.xy), i32(id.z), vec4<f32>(min(color / 16.0, vec3<f32>(65504.0)), 1.0));
+}
+
+/// 对上一级mip做2x2盒式降采样，生成环境贴图的下一级mip
+[[stage(compute), workgroup_size(8, 8, 1)]]
+fn cs_downsample([[builtin(global_invocation_id)]] id: vec3<u32>) {
+  let size = textureDimensions(output_cube);
+  if (i32(id.x) >= size.x || i32(id.y) >= size.y) {
+    return;
+  }
+  let position = vec2<i32>(id.xy) * 2;
+  let layer = i32(id.z);
+  let color = textureLoad(env_mip_t, position, layer, 0)
+    + textureLoad(env_mip_t, position + vec2<i32>(1, 0), layer, 0)
+    + textureLoad(env_mip_t, position + vec2<i32>(0, 1), layer, 0)
+    + textureLoad(env_mip_t, position + vec2<i32>(1, 1), layer, 0);
+  textureStore(output_cube, vec2<i32>(id.xy), layer, vec4<f32>(color.rgb * 0.25, 1.0));
+}
+
+/// 漫反射辐照度卷积：对法线所在半球做余弦加权积分
+[[stage(compute), workgroup_size(8, 8, 1)]]
+fn cs_irradiance([[builtin(global_invocation_id)]] id: vec3<u32>) {
+  let size = textureDimensions(output_cube);
+  if (i32(id.x) >= size.x || i32(id.y) >= size.y) {
+    return;
+  }
+  let n = cube_direction(id.z, vec2<f32>(id.xy) + vec2<f32>(0.5), size);
+  let up_axis = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(n.y) < 0.999);
+  let right = normalize(cross(up_axis, n));
+  let up = cross(n, right);
+  let sample_delta = 0.025;
+  var irradiance = vec3<f32>(0.0);
+  var sample_count = 0.0;
+  for (var phi = 0.0; phi < 2.0 * PI; phi = phi + sample_delta) {
+    for (var theta = 0.0; theta < 0.5 * PI; theta = theta + sample_delta) {
+      let tangent_sample = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+      let sample_dir = tangent_sample.x * right + tangent_sample.y * up + tangent_sample.z * n;
+      let radiance = textureSampleLevel(env_t, env_s, sample_dir, 2.0).rgb; // 采样间隔约1.4°，对应64分辨率的mip
+      irradiance = irradiance + radiance * cos(theta) * sin(theta);
+      sample_count = sample_count + 1.0;
+    }
+  }
+  irradiance = PI * irradiance / sample_count;
+  textureStore(output_cube, vec2<i32>(id.xy), i32(id.z), vec4<f32>(irradiance, 1.0));
+}
+
+/// 镜面反射预过滤：按粗糙度对环境贴图做GGX重要性采样卷积，每个mip对应一个粗糙度
+[[stage(compute), workgroup_size(8, 8, 1)]]
+fn cs_prefilter([[builtin(global_invocation_id)]] id: vec3<u32>) {
+  let size = textureDimensions(output_cube);
+  if (i32(id.x) >= size.x || i32(id.y) >= size.y) {
+    return;
+  }
+  let n = cube_direction(id.z, vec2<f32>(id.xy) + vec2<f32>(0.5), size);
+  // 假设视线方向与法线方向一致（split sum近似）
+  let v = n;
+  var color = vec3<f32>(0.0);
+  var total_weight = 0.0;
+  for (var i = 0u; i < params.sample_count; i = i + 1u) {
+    let xi = hammersley(i, params.sample_count);
+    let h = importance_sample_ggx(xi, n, params.roughness);
+    let l = normalize(2.0 * dot(v, h) * h - v);
+    let n_dot_l = dot(n, l);
+    if (n_dot_l > 0.0) {
+      // 根据采样概率密度选择源mip，降低高亮区域的噪点
+      let n_dot_h = max(dot(n, h), 0.0);
+      let h_dot_v = max(dot(h, v), 0.0);
+      let pdf = distribution_ggx(n_dot_h, params.roughness) * n_dot_h / (4.0 * h_dot_v) + 0.0001;
+      let sa_texel = 4.0 * PI / (6.0 * params.resolution * params.resolution);
+      let sa_sample = 1.0 / (f32(params.sample_count) * pdf + 0.0001);
+      let lod = select(0.5 * log2(sa_sample / sa_texel), 0.0, params.roughness == 0.0);
+      color = color + textureSampleLevel(env_t, env_s, l, lod).rgb * n_dot_l;
+      total_weight = total_weight + n_dot_l;
+    }
+  }
+  color = color / max(total_weight, 0.0001);
+  textureStore(output_cube, vec2<i32>(id.xy), i32(id.z), vec4<f32>(color, 1.0));
+}
+
+/// BRDF积分查找表：x轴为n·v，y轴为粗糙度，输出为F0的缩放和偏移
+[[stage(compute), workgroup_size(8, 8, 1)]]
+fn cs_brdf([[builtin(global_invocation_id)]] id: vec3<u32>) {
+  let size = textureDimensions(output_lut);
+  if (i32(id.x) >= size.x || i32(id.y) >= size.y) {
+    return;
+  }
+  let n_dot_v = max((f32(id.x) + 0.5) / f32(size.x), 0.001);
+  let roughness = (f32(id.y) + 0.5) / f32(size.y);
+  let v = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+  let n = vec3<f32>(0.0, 0.0, 1.0);
+  let sample_count = 1024u;
+  var scale = 0.0;
+  var bias = 0.0;
+  for (var i = 0u; i < sample_count; i = i + 1u) {
+    let xi = hammersley(i, sample_count);
+    let h = importance_sample_ggx(xi, n, roughness);
+    let l = normalize(2.0 * dot(v, h) * h - v);
+    let n_dot_l = max(l.z, 0.0);
+    let n_dot_h = max(h.z, 0.0);
+    let v_dot_h = max(dot(v, h), 0.0);
+    if (n_dot_l > 0.0) {
+      let g = geometry_smith_ibl(n_dot_v, n_dot_l, roughness);
+      let g_vis = g * v_dot_h / (n_dot_h * n_dot_v);
+      let fc = pow(1.0 - v_dot_h, 5.0);
+      scale = scale + (1.0 - fc) * g_vis;
+      bias = bias + fc * g_vis;
+    }
+  }
+  let result = vec2<f32>(scale, bias) / f32(sample_count);
+  textureStore(output_lut, vec2<i32>(id.xy), vec4<f32>(result, 0.0, 1.0));
+}
@@ -0,0 +1,409 @@
+use cgmath::InnerSpace;
+use crate::camera::OPENGL_TO_WGPU_MATRIX;
+use crate::shadow::ShadowMap;
+use crate::stats;
+
+/// 可投射阴影的方向光/聚光灯的最大数量（即阴影贴图数组的层数）
+pub const MAX_LIGHTS: usize = 4;
+/// 可投射阴影的点光源的最大数量（即立方体阴影贴图数组的立方体个数），超出的点光源不投射阴影
+pub const MAX_POINT_SHADOWS: usize = 4;
+/// 点光源阴影投影的近平面，远平面为点光源的衰减半径
+pub const POINT_SHADOW_NEAR: f32 = 0.05;
+
+/// 立方体贴图各面（+X、-X、+Y、-Y、+Z、-Z）的朝向与up方向
+const CUBE_FACES: [([f32; 3], [f32; 3]); 6] = [
+  ([1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+  ([-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+  ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+  ([0.0, -1.0, 0.0], [0.0, 0.0, -1.0]),
+  ([0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+  ([0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+];
+
+/// 翻转裁剪空间y轴：立方体贴图的纹理坐标v轴与渲染目标方向相反
+#[rustfmt::skip]
+const FLIP_Y_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
+  1.0, 0.0, 0.0, 0.0,
+  0.0, -1.0, 0.0, 0.0,
+  0.0, 0.0, 1.0, 0.0,
+  0.0, 0.0, 0.0, 1.0,
+);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LightKind {
+  /// 方向光（平行光），使用正交投影生成阴影
+  Directional,
+  /// 聚光灯，使用透视投影生成阴影
+  Spot,
+}
+
+/// 光源
+pub struct Light {
+  pub kind: LightKind,
+  /// 光源位置（方向光仅用于确定阴影相机位置）
+  pub position: cgmath::Point3<f32>,
+  /// 光线照射方向
+  pub direction: cgmath::Vector3<f32>,
+  /// 光源颜色（线性空间）
+  pub color: [f32; 3],
+  pub intensity: f32,
+  /// 聚光灯衰减半径，超出该距离不受光照
+  pub range: f32,
+  /// 聚光灯内锥角（半角），内锥内光照强度不衰减
+  pub inner_angle: cgmath::Deg<f32>,
+  /// 聚光灯外锥角（半角）
+  pub outer_angle: cgmath::Deg<f32>,
+  /// 方向光阴影覆盖范围（以`position`为中心的正交投影半宽）
+  pub shadow_extent: f32,
+}
+
+/// 点光源
+pub struct PointLight {
+  pub position: cgmath::Point3<f32>,
+  /// 光源颜色（线性空间）
+  pub color: [f32; 3],
+  pub intensity: f32,
+  /// 衰减半径，超出该距离不受光照
+  pub radius: f32,
+  /// 是否投射阴影（只有前`MAX_POINT_SHADOWS`个会生效）
+  pub cast_shadow: bool,
+}
+
+/// 单个光源的uniform数据，与pbr.wgsl中的`Light`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightUniform {
+  /// 光源视角的视图投影矩阵，用于阴影
+  view_projection: [[f32; 4]; 4],
+  /// xyz为位置，w为光源类型（0为方向光，1为聚光灯）
+  position: [f32; 4],
+  /// xyz为照射方向，w为外锥角余弦
+  direction: [f32; 4],
+  /// rgb为颜色乘以强度，w为内锥角余弦
+  color: [f32; 4],
+  /// x为衰减半径
+  params: [f32; 4],
+}
+
+/// 所有光源及阴影采样参数
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightsUniform {
+  count: u32,
+  /// PCF滤波半径（以纹素为单位），0表示不做滤波
+  pcf_radius: i32,
+  /// 阴影贴图单个纹素的uv尺寸
+  texel_size: f32,
+  /// 点光源数量，点光源数据位于单独的storage buffer中
+  point_count: u32,
+  lights: [LightUniform; MAX_LIGHTS],
+}
+
+/// 单个点光源的storage数据，与pbr.wgsl中的`PointLight`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLightData {
+  /// xyz为位置，w为衰减半径
+  position: [f32; 4],
+  /// rgb为颜色乘以强度
+  color: [f32; 4],
+  /// 立方体阴影贴图数组中的序号，-1表示不投射阴影
+  shadow_index: i32,
+  shadow_near: f32,
+  padding: [u32; 2],
+}
+
+pub struct LightInfo {
+  pub uniform: LightsUniform,
+  pub buffer: stats::Buffer,
+  pub group: wgpu::BindGroup,
+  pub layout: wgpu::BindGroupLayout,
+  point_buffer: stats::Buffer,
+  /// `point_buffer`可容纳的点光源数量
+  point_capacity: usize,
+}
+
+impl Light {
+  /// 方向光，阴影相机位于原点沿光线反方向后退处
+  pub fn directional(direction: cgmath::Vector3<f32>, color: [f32; 3], intensity: f32, shadow_extent: f32) -> Self {
+    let direction = direction.normalize();
+    Self {
+      kind: LightKind::Directional,
+      position: cgmath::Point3::new(0.0, 0.0, 0.0) - direction * shadow_extent * 2.0,
+      direction,
+      color,
+      intensity,
+      range: 0.0,
+      inner_angle: cgmath::Deg(0.0),
+      outer_angle: cgmath::Deg(0.0),
+      shadow_extent,
+    }
+  }
+
+  pub fn spot(
+    position: cgmath::Point3<f32>,
+    direction: cgmath::Vector3<f32>,
+    color: [f32; 3],
+    intensity: f32,
+    range: f32,
+    inner_angle: cgmath::Deg<f32>,
+    outer_angle: cgmath::Deg<f32>,
+  ) -> Self {
+    Self {
+      kind: LightKind::Spot,
+      position,
+      direction: direction.normalize(),
+      color,
+      intensity,
+      range,
+      inner_angle,
+      outer_angle,
+      shadow_extent: 0.0,
+    }
+  }
+
+  /// 光源视角的视图投影矩阵
+  pub fn get_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
+    // 光线接近竖直时换一个up方向，避免look_at退化
+    let up = if self.direction.y.abs() > 0.99 {
+      cgmath::Vector3::unit_z()
+    } else {
+      cgmath::Vector3::unit_y()
+    };
+    let view = cgmath::Matrix4::look_at_rh(self.position, self.position + self.direction, up);
+    let projection = match self.kind {
+      LightKind::Directional => {
+        let extent = self.shadow_extent;
+        cgmath::ortho(-extent, extent, -extent, extent, 0.1, extent * 4.0)
+      },
+      LightKind::Spot => cgmath::perspective(self.outer_angle * 2.0, 1.0, 0.1, self.range),
+    };
+    OPENGL_TO_WGPU_MATRIX * projection * view
+  }
+
+  fn get_uniform(&self) -> LightUniform {
+    let kind = match self.kind {
+      LightKind::Directional => 0.0,
+      LightKind::Spot => 1.0,
+    };
+    let cos_outer = cgmath::Angle::cos(self.outer_angle);
+    let cos_inner = cgmath::Angle::cos(self.inner_angle);
+    LightUniform {
+      view_projection: self.get_view_projection_matrix().into(),
+      position: [self.position.x, self.position.y, self.position.z, kind],
+      direction: [self.direction.x, self.direction.y, self.direction.z, cos_outer],
+      color: [self.color[0] * self.intensity, self.color[1] * self.intensity, self.color[2] * self.intensity, cos_inner],
+      params: [self.range, 0.0, 0.0, 0.0],
+    }
+  }
+}
+
+impl PointLight {
+  pub fn new(position: cgmath::Point3<f32>, color: [f32; 3], intensity: f32, radius: f32) -> Self {
+    Self {
+      position,
+      color,
+      intensity,
+      radius,
+      cast_shadow: true,
+    }
+  }
+
+  /// 立方体阴影贴图第`face`个面的视图投影矩阵
+  pub fn get_face_view_projection_matrix(&self, face: usize) -> cgmath::Matrix4<f32> {
+    let (direction, up) = CUBE_FACES[face];
+    let view = cgmath::Matrix4::look_at_rh(self.position, self.position + cgmath::Vector3::from(direction), up.into());
+    let projection = cgmath::perspective(cgmath::Deg(90.0), 1.0, POINT_SHADOW_NEAR, self.radius);
+    FLIP_Y_MATRIX * OPENGL_TO_WGPU_MATRIX * projection * view
+  }
+
+  fn get_data(&self, shadow_index: Option<usize>) -> PointLightData {
+    PointLightData {
+      position: [self.position.x, self.position.y, self.position.z, self.radius],
+      color: [self.color[0] * self.intensity, self.color[1] * self.intensity, self.color[2] * self.intensity, 0.0],
+      shadow_index: shadow_index.map_or(-1, |index| index as i32),
+      shadow_near: POINT_SHADOW_NEAR,
+      padding: [0; 2],
+    }
+  }
+}
+
+/// 实际投射阴影的点光源，按顺序对应立方体阴影贴图数组中的序号
+pub fn shadow_casters(point_lights: &[PointLight]) -> impl Iterator<Item = &PointLight> {
+  point_lights.iter().filter(|light| light.cast_shadow).take(MAX_POINT_SHADOWS)
+}
+
+impl LightsUniform {
+  fn new(lights: &[Light], point_lights: &[PointLight], shadow_map: &ShadowMap) -> Self {
+    let mut uniform = Self {
+      count: lights.len().min(MAX_LIGHTS) as u32,
+      pcf_radius: shadow_map.settings.pcf_radius as i32,
+      texel_size: 1.0 / shadow_map.settings.map_size as f32,
+      point_count: point_lights.len() as u32,
+      lights: [LightUniform::zeroed(); MAX_LIGHTS],
+    };
+    for (target, light) in uniform.lights.iter_mut().zip(lights) {
+      *target = light.get_uniform();
+    }
+    uniform
+  }
+}
+
+impl LightUniform {
+  fn zeroed() -> Self {
+    bytemuck::Zeroable::zeroed()
+  }
+}
+
+/// 所有点光源的storage数据
+fn point_light_data(point_lights: &[PointLight]) -> Vec<PointLightData> {
+  let mut shadow_count = 0;
+  point_lights.iter().map(|light| {
+    let shadow_index = if light.cast_shadow && shadow_count < MAX_POINT_SHADOWS {
+      shadow_count += 1;
+      Some(shadow_count - 1)
+    } else {
+      None
+    };
+    light.get_data(shadow_index)
+  }).collect()
+}
+
+impl LightInfo {
+  pub fn new(lights: &[Light], point_lights: &[PointLight], shadow_map: &ShadowMap, device: &wgpu::Device) -> Self {
+    let uniform = LightsUniform::new(lights, point_lights, shadow_map);
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Light buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let point_capacity = point_lights.len().max(1); // storage buffer不能为空
+    let point_buffer = Self::create_point_buffer(point_lights, point_capacity, device);
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("light bind group layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        },
+        wgpu::BindGroupLayoutEntry {
+          binding: 1,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2Array,
+            sample_type: wgpu::TextureSampleType::Depth,
+          },
+          count: None
+        },
+        wgpu::BindGroupLayoutEntry {
+          binding: 2,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+          count: None
+        },
+        wgpu::BindGroupLayoutEntry {
+          binding: 3,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        },
+        wgpu::BindGroupLayoutEntry {
+          binding: 4,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::CubeArray,
+            sample_type: wgpu::TextureSampleType::Depth,
+          },
+          count: None
+        }
+      ]
+    });
+    let group = Self::create_group(&layout, &buffer, &point_buffer, shadow_map, device);
+    Self {
+      uniform,
+      buffer,
+      group,
+      layout,
+      point_buffer,
+      point_capacity,
+    }
+  }
+
+  /// 创建点光源storage buffer，内容为`point_lights`，不足`capacity`的部分补零
+  fn create_point_buffer(point_lights: &[PointLight], capacity: usize, device: &wgpu::Device) -> stats::Buffer {
+    let mut data = point_light_data(point_lights);
+    data.resize(capacity, bytemuck::Zeroable::zeroed());
+    stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Point light buffer"),
+      contents: bytemuck::cast_slice(&data),
+      usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+    })
+  }
+
+  fn create_group(
+    layout: &wgpu::BindGroupLayout,
+    buffer: &wgpu::Buffer,
+    point_buffer: &wgpu::Buffer,
+    shadow_map: &ShadowMap,
+    device: &wgpu::Device,
+  ) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("light bind group"),
+      layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: buffer.as_entire_binding()
+        },
+        wgpu::BindGroupEntry {
+          binding: 1,
+          resource: wgpu::BindingResource::TextureView(&shadow_map.texture.view)
+        },
+        wgpu::BindGroupEntry {
+          binding: 2,
+          resource: wgpu::BindingResource::Sampler(&shadow_map.texture.sampler)
+        },
+        wgpu::BindGroupEntry {
+          binding: 3,
+          resource: point_buffer.as_entire_binding()
+        },
+        wgpu::BindGroupEntry {
+          binding: 4,
+          resource: wgpu::BindingResource::TextureView(&shadow_map.point_texture.view)
+        }
+      ]
+    })
+  }
+
+  /// 光源或阴影参数变化后更新uniform；点光源数量超出容量时按两倍扩容并重建bind group
+  pub fn update(
+    &mut self,
+    lights: &[Light],
+    point_lights: &[PointLight],
+    shadow_map: &ShadowMap,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+  ) {
+    self.uniform = LightsUniform::new(lights, point_lights, shadow_map);
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    if point_lights.len() > self.point_capacity {
+      self.point_capacity = point_lights.len().max(self.point_capacity * 2);
+      self.point_buffer = Self::create_point_buffer(point_lights, self.point_capacity, device);
+      self.group = Self::create_group(&self.layout, &self.buffer, &self.point_buffer, shadow_map, device);
+    } else if !point_lights.is_empty() {
+      queue.write_buffer(&self.point_buffer, 0, bytemuck::cast_slice(&point_light_data(point_lights)));
+    }
+  }
+}
@@ -1,6 +1,28 @@
 mod shape;
 mod texture;
 mod camera;
+mod ibl;
+mod material;
+mod light;
+mod shadow;
+mod post;
+mod bloom;
+mod ssao;
+mod debug;
+mod wireframe;
+mod debug_draw;
+mod grid;
+mod culling;
+mod picking;
+mod outline;
+mod raycast;
+mod gizmo;
+mod history;
+mod ui;
+mod stats;
+mod profiler;
+mod smaa;
+mod obj;
 
 use winit::{
   event::*,
@@ -8,46 +30,404 @@ use winit::{
   window::WindowBuilder,
   window::Window,
 };
+use std::path::PathBuf;
 use cgmath::prelude::*;
-use wgpu::util::DeviceExt;
 use shape::{
   Vertex,
-  get_circle,
+  Mesh,
+  get_sphere,
+  get_plane,
   Instance,
-  InstanceData
+  InstanceData,
+  Aabb,
+  BoundingSphere
 };
 use camera::{
   Camera,
   CameraInfo
 };
+use ibl::{
+  Environment,
+  IblInfo
+};
+use material::{
+  Material,
+  MaterialInfo
+};
+use light::{
+  Light,
+  LightKind,
+  PointLight,
+  LightInfo
+};
+use shadow::{
+  ShadowMap,
+  ShadowSettings
+};
+use ssao::{
+  Ssao,
+  SsaoSettings
+};
+use debug::{
+  DebugView,
+  DebugViews
+};
+use wireframe::{
+  Wireframe,
+  WireframeMode
+};
+use debug_draw::DebugDraw;
+use grid::Grid;
+use culling::{
+  Frustum,
+  CullStats,
+  CullMode,
+  GpuCulling
+};
+use picking::Picking;
+use outline::Outline;
+use gizmo::{
+  Gizmo,
+  GizmoSpace
+};
+use history::{
+  Edit,
+  History
+};
+use ui::Ui;
+use stats::FrameStats;
+use profiler::GpuProfiler;
+use obj::ModelPart;
+use raycast::{
+  Ray,
+  RayHit,
+  raycast_instances
+};
+use post::{
+  PostEffect,
+  AntiAliasing,
+  PostProcess,
+  PostSettings,
+  Tonemap
+};
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// 拾取时使用的网格序号
+const SPHERE_MESH_ID: u32 = 0;
+const GROUND_MESH_ID: u32 = 1;
+const MODEL_MESH_ID: u32 = 2;
+/// 地面高度
+const GROUND_Y: f32 = -0.3;
 
 struct State {
-  surface: wgpu::Surface,
+  /// 无窗口模式下为None，渲染到离屏纹理
+  surface: Option<wgpu::Surface>,
   device: wgpu::Device,
   queue: wgpu::Queue,
   config: wgpu::SurfaceConfiguration,
   size: winit::dpi::PhysicalSize<u32>,
   background: wgpu::Color,
   render_pipeline: wgpu::RenderPipeline,
-  render_pipeline2: wgpu::RenderPipeline,
-  render_pipeline_default: bool,
-  vertex_buffer: wgpu::Buffer,
-  index_buffer: wgpu::Buffer,
-  index_num: u32,
-  texture_bind_group: wgpu::BindGroup,
+  /// 当前的显示方式，非`DebugView::Lit`时使用调试管线并跳过后处理
+  debug_view: DebugView,
+  debug_views: DebugViews,
+  wireframe: Wireframe,
+  debug_draw: DebugDraw,
+  grid: Grid,
+  /// 是否显示实例坐标轴、包围盒和光源等辅助线
+  show_helpers: bool,
+  /// 冻结时的相机，用于从其他视角观察视锥体
+  frozen_camera: Option<Camera>,
+  /// 选中的实例序号
+  selection: Option<usize>,
+  picking: Picking,
+  /// 等待拾取结果的光标位置
+  pick_cursor: Option<(u32, u32)>,
+  /// 选中实例的轮廓，未开启时深度缓冲不带模板
+  outline: Option<Outline>,
+  /// 光标位置（物理像素）
+  cursor: (f64, f64),
+  /// 选中实例的变换手柄
+  gizmo: Gizmo,
+  /// 手柄线段，不受场景遮挡
+  gizmo_draw: DebugDraw,
+  /// 场景修改的撤销/重做历史
+  history: History,
+  /// 当前按下的修饰键
+  modifiers: ModifiersState,
+  /// 无窗口模式下为None
+  ui: Option<Ui>,
+  /// 为true时由场景包围球自动计算近/远平面
+  auto_depth: bool,
+  /// 帧时间和绘制统计
+  stats: FrameStats,
+  /// 各pass的GPU时间，不支持时间戳查询时为None
+  profiler: Option<GpuProfiler>,
+  /// 右键射线检测得到的测量点，最多保留两个
+  measure_points: Vec<cgmath::Point3<f32>>,
+  render_pipeline_layout: wgpu::PipelineLayout,
+  /// 只写深度的预pass管线，为SSAO提供当前帧的深度
+  depth_prepass_pipeline: wgpu::RenderPipeline,
+  ssao: Ssao,
+  /// 多重采样数，1表示不使用MSAA
+  sample_count: u32,
+  /// 多重采样颜色目标，`sample_count`为1时为None
+  msaa_texture: Option<texture::Texture>,
+  post: PostProcess,
+  mesh: Mesh,
+  material: Material,
+  material_info: MaterialInfo,
+  ground: Mesh,
+  ground_material: Material,
+  ground_material_info: MaterialInfo,
+  ground_instance_buffer: stats::Buffer,
+  /// `--model`加载的OBJ模型，每个材质一部分
+  model: Vec<ModelPart>,
+  model_data: InstanceData,
+  model_instance_buffer: stats::Buffer,
   camera: Camera,
   camera_info: CameraInfo,
+  ibl_info: IblInfo,
+  lights: Vec<Light>,
+  point_lights: Vec<PointLight>,
+  light_info: LightInfo,
+  shadow_map: ShadowMap,
   instances: Vec<Instance>,
-  instance_buffer: wgpu::Buffer,
+  /// 所有实例的数据，用于阴影pass
+  instance_buffer: stats::Buffer,
+  instance_data: Vec<InstanceData>,
+  /// 各实例的世界空间包围球，与`instances`一一对应
+  instance_spheres: Vec<BoundingSphere>,
+  /// 视锥体剔除后的实例数据，用于相机视角的pass
+  visible_instance_buffer: stats::Buffer,
+  cull_mode: CullMode,
+  /// CPU剔除的统计，GPU剔除时可见实例数只在GPU上
+  cull_stats: CullStats,
+  gpu_culling: GpuCulling,
+  /// 统计信息每秒输出一次
+  stats_timer: std::time::Instant,
+  /// 相机视角各pass共用的深度格式，开启轮廓时带模板
+  depth_format: wgpu::TextureFormat,
   depth_texture: texture::Texture
 }
 
-const INSTANCE_RANGE: std::ops::Range<i8> = -5..6;
+/// 命令行参数
+struct Options {
+  debug_view: DebugView,
+  /// `--view all`：无窗口模式下为每种显示方式各保存一张PNG
+  all_views: bool,
+  wireframe: WireframeMode,
+  /// 实例网格的边长
+  instance_grid: u32,
+  cull_mode: CullMode,
+  /// 是否用模板缓冲绘制选中实例的轮廓
+  outline: bool,
+  outline_color: Option<[f32; 4]>,
+  /// 退出时将统计写入该JSON文件，未指定时输出到标准输出
+  stats_path: Option<String>,
+  backends: wgpu::Backends,
+  power_preference: wgpu::PowerPreference,
+  /// 强制使用软件渲染的后备适配器
+  fallback_adapter: bool,
+  /// 按名称（不区分大小写的子串）选择适配器
+  adapter_name: Option<String>,
+  /// 不支持时由wgpu退回`Fifo`
+  present_mode: wgpu::PresentMode,
+  /// 只列出所有适配器后退出
+  list_adapters: bool,
+  /// 等距柱状投影环境贴图，未指定时使用程序化天空
+  env_path: Option<PathBuf>,
+  /// 放入场景的OBJ模型（材质来自其MTL文件）
+  model_path: Option<PathBuf>,
+  /// 无窗口模式：渲染`frames`帧后将最后一帧保存为该PNG文件
+  headless: Option<PathBuf>,
+  /// 无窗口模式的渲染尺寸
+  size: (u32, u32),
+  frames: u32,
+  /// 未指定时使用MSAA
+  anti_aliasing: Option<AntiAliasing>,
+  /// 后处理链的顺序，未列出的效果关闭并排在最后
+  post_order: Vec<PostEffect>,
+}
+
+/// 解析`fifo|vsync|on`、`immediate|off`和`mailbox`
+fn parse_present_mode(name: &str) -> Option<wgpu::PresentMode> {
+  match name.to_lowercase().as_str() {
+    "fifo" | "vsync" | "on" => Some(wgpu::PresentMode::Fifo),
+    "immediate" | "off" => Some(wgpu::PresentMode::Immediate),
+    "mailbox" => Some(wgpu::PresentMode::Mailbox),
+    _ => None,
+  }
+}
+
+/// 解析逗号分隔的后端列表，如`vulkan,gl`；无法识别时返回None
+fn parse_backends(list: &str) -> Option<wgpu::Backends> {
+  let backends = match list.to_lowercase().as_str() {
+    "all" => wgpu::Backends::all(),
+    "primary" => wgpu::Backends::PRIMARY,
+    list => wgpu::util::parse_backends_from_comma_list(list),
+  };
+  (!backends.is_empty()).then_some(backends)
+}
+
+impl Options {
+  /// 解析`--view <name>`、`--wireframe <off|wire|overlay>`、`--instances <n>`、`--culling <off|cpu|gpu>`、`--outline <on|off>`、`--outline-color <r,g,b>`、`--stats <path>`、`--env <path>`、`--model <path>`和`--aa <msaa|fxaa|smaa|off>`
+  ///
+  /// `--post <effect,...>`指定后处理链的顺序，如`--post bloom,exposure,tonemap,fxaa,gamma`；
+  /// 未列出的效果关闭并排在最后，FXAA/SMAA的开关仍由抗锯齿方式决定；
+  ///
+  /// 无窗口模式`--headless <out.png>`可配合`--size <w>x<h>`和`--frames <n>`，用于比较不同设置的渲染结果；
+  /// 此时`--view all`将每种显示方式分别保存为`out-<view>.png`；
+  ///
+  /// 适配器相关的`--backend <vulkan,gl,...>`、`--power <low|high>`、`--fallback-adapter`、`--adapter <name>`和`--present-mode <on|off|mailbox>`
+  /// 的默认值分别来自环境变量`WGPU_BACKEND`、`WGPU_POWER_PREF`、`WGPU_FORCE_FALLBACK_ADAPTER`、`WGPU_ADAPTER_NAME`和`WGPU_PRESENT_MODE`
+  fn from_args() -> Self {
+    let mut options = Options {
+      debug_view: DebugView::Lit,
+      all_views: false,
+      wireframe: WireframeMode::Off,
+      instance_grid: 11,
+      cull_mode: CullMode::Cpu,
+      outline: true,
+      outline_color: None,
+      stats_path: None,
+      backends: wgpu::util::backend_bits_from_env().filter(|backends| !backends.is_empty()).unwrap_or_else(wgpu::Backends::all),
+      power_preference: wgpu::util::power_preference_from_env().unwrap_or_default(),
+      fallback_adapter: std::env::var("WGPU_FORCE_FALLBACK_ADAPTER").is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true")),
+      adapter_name: std::env::var("WGPU_ADAPTER_NAME").ok(),
+      present_mode: wgpu::PresentMode::Fifo,
+      list_adapters: false,
+      env_path: None,
+      model_path: None,
+      headless: None,
+      size: (1280, 720),
+      frames: 1,
+      anti_aliasing: None,
+      post_order: PostEffect::ALL.to_vec(),
+    };
+    if let Ok(name) = std::env::var("WGPU_PRESENT_MODE") {
+      match parse_present_mode(&name) {
+        Some(mode) => options.present_mode = mode,
+        None => eprintln!("WGPU_PRESENT_MODE expects fifo, immediate or mailbox"),
+      }
+    }
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+      match arg.as_str() {
+        "--view" => match args.next().as_deref() {
+          Some("all") => options.all_views = true,
+          name => match name.and_then(DebugView::from_name) {
+            Some(view) => options.debug_view = view,
+            None => eprintln!("unknown debug view, expected all or one of: {}", DebugView::ALL.iter().map(DebugView::name).collect::<Vec<_>>().join(", ")),
+          },
+        },
+        "--wireframe" => match args.next().as_deref().and_then(WireframeMode::from_name) {
+          Some(mode) => options.wireframe = mode,
+          None => eprintln!("unknown wireframe mode, expected one of: {}", WireframeMode::ALL.iter().map(WireframeMode::name).collect::<Vec<_>>().join(", ")),
+        },
+        "--instances" => match args.next().and_then(|n| n.parse().ok()) {
+          Some(n) if n > 0 => options.instance_grid = n,
+          _ => eprintln!("--instances expects a positive grid size"),
+        },
+        "--culling" => match args.next().as_deref().and_then(CullMode::from_name) {
+          Some(mode) => options.cull_mode = mode,
+          None => eprintln!("unknown culling mode, expected one of: {}", CullMode::ALL.iter().map(CullMode::name).collect::<Vec<_>>().join(", ")),
+        },
+        "--outline" => match args.next().as_deref() {
+          Some("on") => options.outline = true,
+          Some("off") => options.outline = false,
+          _ => eprintln!("--outline expects on or off"),
+        },
+        "--outline-color" => match args.next().map(|color| color.split(',').map(|c| c.trim().parse::<f32>()).collect::<Result<Vec<_>, _>>()) {
+          Some(Ok(color)) if color.len() == 3 => options.outline_color = Some([color[0], color[1], color[2], 1.0]),
+          _ => eprintln!("--outline-color expects r,g,b in [0, 1]"),
+        },
+        "--stats" => match args.next() {
+          Some(path) => options.stats_path = Some(path),
+          None => eprintln!("--stats expects a file path"),
+        },
+        "--backend" => match args.next().as_deref().and_then(parse_backends) {
+          Some(backends) => options.backends = backends,
+          None => eprintln!("--backend expects a comma separated list of vulkan, gl, metal, dx12, dx11, or primary / all"),
+        },
+        "--power" => match args.next().as_deref() {
+          Some("low") => options.power_preference = wgpu::PowerPreference::LowPower,
+          Some("high") => options.power_preference = wgpu::PowerPreference::HighPerformance,
+          _ => eprintln!("--power expects low or high"),
+        },
+        "--fallback-adapter" => options.fallback_adapter = true,
+        "--adapter" => match args.next() {
+          Some(name) => options.adapter_name = Some(name),
+          None => eprintln!("--adapter expects part of an adapter name, see --list-adapters"),
+        },
+        "--present-mode" | "--vsync" => match args.next().as_deref().and_then(parse_present_mode) {
+          Some(mode) => options.present_mode = mode,
+          None => eprintln!("{} expects on (fifo), off (immediate) or mailbox", arg),
+        },
+        "--list-adapters" => options.list_adapters = true,
+        "--env" => match args.next() {
+          Some(path) => options.env_path = Some(PathBuf::from(path)),
+          None => eprintln!("--env expects an equirectangular image (.hdr, .png, .jpg)"),
+        },
+        "--model" => match args.next() {
+          Some(path) => options.model_path = Some(PathBuf::from(path)),
+          None => eprintln!("--model expects an .obj file, e.g. src/model/Marry.obj"),
+        },
+        "--headless" => match args.next() {
+          Some(path) => options.headless = Some(PathBuf::from(path)),
+          None => eprintln!("--headless expects an output .png path"),
+        },
+        "--size" => match args.next().and_then(|size| size.split_once('x').and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))) {
+          Some((width, height)) if width > 0 && height > 0 => options.size = (width, height),
+          _ => eprintln!("--size expects <width>x<height>, e.g. 1280x720"),
+        },
+        "--frames" => match args.next().and_then(|n| n.parse().ok()) {
+          Some(n) if n > 0 => options.frames = n,
+          _ => eprintln!("--frames expects a positive number of frames"),
+        },
+        "--aa" => match args.next().as_deref().and_then(AntiAliasing::from_name) {
+          Some(mode) => options.anti_aliasing = Some(mode),
+          None => eprintln!("unknown anti-aliasing, expected one of: {}", AntiAliasing::ALL.iter().map(AntiAliasing::name).collect::<Vec<_>>().join(", ")),
+        },
+        "--post" => match args.next().map(|list| list.split(',').map(|name| PostEffect::from_name(name.trim())).collect::<Option<Vec<_>>>()) {
+          Some(Some(order)) => options.post_order = order,
+          _ => eprintln!("--post expects a comma separated list of: {}", PostEffect::ALL.iter().map(PostEffect::name).collect::<Vec<_>>().join(", ")),
+        },
+        _ => eprintln!("unknown argument: {}", arg),
+      }
+    }
+    options
+  }
+}
+
+/// 初始视线方向（从观察目标指向相机）
+const DEFAULT_VIEW_DIRECTION: cgmath::Vector3<f32> = cgmath::Vector3 { x: -1.0, y: 1.0, z: 3.0 };
+
+/// 模型放在实例网格后方，水平居中且底部贴在地面上
+fn get_model_instance(model: &[ModelPart], grid_size: u32) -> Instance {
+  let center = match model.iter().map(|part| part.mesh.bounds).reduce(|a, b| a.union(&b)) {
+    Some(bounds) => cgmath::Vector3::new(
+      -bounds.center().x,
+      GROUND_Y - bounds.min.y,
+      -(grid_size as f32 / 2.0 + 1.0) - bounds.center().z,
+    ),
+    None => cgmath::Vector3::zero(),
+  };
+  Instance {
+    center,
+    rotation: cgmath::Quaternion::one(),
+    scale: cgmath::Vector3::new(1.0, 1.0, 1.0)
+  }
+}
 
-fn get_instances() -> Vec<Instance> {
+/// 生成`grid_size` x `grid_size`个以原点为中心排布的实例
+fn get_instances(grid_size: u32) -> Vec<Instance> {
+  let half = grid_size as i32 / 2;
+  let range = -half..(grid_size as i32 - half);
   // flat_map应该就是map之后应用flat？
-  let instances = INSTANCE_RANGE.flat_map(|z| {
-    INSTANCE_RANGE.map(move |x| {
+  let instances = range.clone().flat_map(|z| {
+    range.clone().map(move |x| {
       let center = cgmath::Vector3 {
         x: x as f32,
         y: 0.0,
@@ -60,207 +440,285 @@ fn get_instances() -> Vec<Instance> {
       };
       Instance {
         center,
-        rotation
+        rotation,
+        scale: cgmath::Vector3::new(1.0, 1.0, 1.0)
       }
     })
   });
   instances.collect::<Vec<_>>()
 }
 
+/// 可选的多重采样数
+///
+/// WebGPU保证所有可渲染格式均支持1和4；wgpu 0.12无法查询`Rgba16Float`颜色和深度格式是否支持2x/8x，
+/// 因此只提供这两种；
+const SAMPLE_COUNTS: [u32; 2] = [1, 4];
+
+fn create_msaa_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, sample_count: u32) -> Option<texture::Texture> {
+  if sample_count > 1 {
+    Some(texture::Texture::create_msaa_texture(device, config, texture::Texture::HDR_FORMAT, sample_count, "msaa_texture"))
+  } else {
+    None
+  }
+}
+
+/// 创建场景渲染管线（PBR管线、测试管线和深度预pass管线），多重采样数变化时需要重建
+fn create_render_pipelines(
+  device: &wgpu::Device,
+  layout: &wgpu::PipelineLayout,
+  format: wgpu::TextureFormat,
+  sample_count: u32,
+  depth_format: wgpu::TextureFormat,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+  let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+    label: Some("Shader"),
+    source: wgpu::ShaderSource::Wgsl(include_str!("pbr.wgsl").into())
+  });
+  let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    label: Some("Render Pipeline"),
+    layout: Some(layout),
+    vertex: wgpu::VertexState {
+      module: &shader,
+      entry_point: "vs_main",
+      buffers: &[
+        Vertex::desc(),
+        InstanceData::desc()
+      ]
+    },
+    fragment: Some(wgpu::FragmentState {
+      module: &shader,
+      entry_point: "fs_main",
+      targets: &[wgpu::ColorTargetState {
+        format,
+        blend: Some(wgpu::BlendState::REPLACE),
+        write_mask: wgpu::ColorWrites::ALL,
+      }],
+    }),
+    primitive: wgpu::PrimitiveState { // 图元设置，如何生成三角
+      topology: wgpu::PrimitiveTopology::TriangleList, // 每三个顶点为一个三角形
+      strip_index_format: None,
+      front_face: wgpu::FrontFace::Ccw, // 逆时针为正面
+      cull_mode: Some(wgpu::Face::Back), // 背面隐藏
+      polygon_mode: wgpu::PolygonMode::Fill, // 填充着色
+      unclipped_depth: false,
+      conservative: false
+    },
+    depth_stencil: Some(wgpu::DepthStencilState {
+      format: depth_format,
+      depth_write_enabled: true,
+      depth_compare: wgpu::CompareFunction::LessEqual, // 开启SSAO时深度已由预pass写入
+      stencil: wgpu::StencilState::default(),
+      bias: wgpu::DepthBiasState::default()
+    }), // 深度模板缓存
+    multisample: wgpu::MultisampleState {
+      count: sample_count,
+      mask: !0,
+      alpha_to_coverage_enabled: false,
+    },
+    multiview: None
+  });
+  // 与PBR管线使用同一个顶点着色器，保证深度完全一致
+  let depth_prepass_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    label: Some("Depth Prepass Pipeline"),
+    layout: Some(layout),
+    vertex: wgpu::VertexState {
+      module: &shader,
+      entry_point: "vs_main",
+      buffers: &[
+        Vertex::desc(),
+        InstanceData::desc()
+      ]
+    },
+    fragment: None,
+    primitive: wgpu::PrimitiveState {
+      topology: wgpu::PrimitiveTopology::TriangleList,
+      strip_index_format: None,
+      front_face: wgpu::FrontFace::Ccw,
+      cull_mode: Some(wgpu::Face::Back),
+      polygon_mode: wgpu::PolygonMode::Fill,
+      unclipped_depth: false,
+      conservative: false
+    },
+    depth_stencil: Some(wgpu::DepthStencilState {
+      format: depth_format,
+      depth_write_enabled: true,
+      depth_compare: wgpu::CompareFunction::Less,
+      stencil: wgpu::StencilState::default(),
+      bias: wgpu::DepthBiasState::default()
+    }),
+    multisample: wgpu::MultisampleState {
+      count: sample_count,
+      mask: !0,
+      alpha_to_coverage_enabled: false,
+    },
+    multiview: None
+  });
+  (render_pipeline, depth_prepass_pipeline)
+}
+
 impl State {
-  pub async fn new(window: &Window) -> Self {
-    let size = window.inner_size();
-    let instance = wgpu::Instance::new(wgpu::Backends::all());
-    let surface = unsafe { instance.create_surface(window) };
-    let adpater = instance.request_adapter(&wgpu::RequestAdapterOptions {
-      power_preference: wgpu::PowerPreference::default(),
-      compatible_surface: Some(&surface),
-      force_fallback_adapter: false,
-    }).await.unwrap();
+  /// `window`为None时不创建交换链和界面，只能通过`render_offscreen`渲染
+  pub async fn new(window: Option<&Window>, options: Options) -> anyhow::Result<Self> {
+    let size = window.map_or(winit::dpi::PhysicalSize::new(options.size.0, options.size.1), Window::inner_size);
+    let instance = wgpu::Instance::new(options.backends);
+    let surface = window.map(|window| unsafe { instance.create_surface(window) });
+    let adpater = select_adapter(&instance, surface.as_ref(), &options).await?;
+    let info = adpater.get_info();
+    println!("adapter: {} ({:?}, {:?}), present mode {:?}", info.name, info.backend, info.device_type, options.present_mode);
     let (device, queue) = adpater.request_device(&wgpu::DeviceDescriptor {
-      features: wgpu::Features::empty(),
-      limits: wgpu::Limits::default(),
+      features: adpater.features() & (
+        wgpu::Features::POLYGON_MODE_LINE // 用于线框，不支持时使用重心坐标着色器
+        | wgpu::Features::TIMESTAMP_QUERY // 用于统计各pass的GPU时间
+      ),
+      limits: wgpu::Limits {
+        max_bind_groups: 5, // pbr.wgsl中group 4为环境光遮蔽
+        ..wgpu::Limits::default()
+      },
       label: None,
-    }, None).await.unwrap();
+    }, None).await.map_err(|error| anyhow::anyhow!(
+      "adapter {} ({:?}) cannot create a device with the required limits: {}; try another --backend or --adapter, see --list-adapters",
+      info.name, info.backend, error
+    ))?;
     let config = wgpu::SurfaceConfiguration {
       usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-      format: surface.get_preferred_format(&adpater).unwrap(),
+      format: match &surface {
+        Some(surface) => surface.get_preferred_format(&adpater)
+          .ok_or_else(|| anyhow::anyhow!("adapter {} ({:?}) cannot present to this window", info.name, info.backend))?,
+        None => wgpu::TextureFormat::Rgba8UnormSrgb, // 与PNG的字节顺序一致
+      },
       width: size.width,
       height: size.height,
-      present_mode: wgpu::PresentMode::Fifo,
+      present_mode: options.present_mode,
     };
+    // 位置和近/远平面在创建完场景后由`frame`计算
     let camera = Camera {
-      eye: (-1.0, 1.0, 3.0).into(), // TOKNOW: into的机制是什么
+      eye: cgmath::Point3::from_vec(DEFAULT_VIEW_DIRECTION),
       lookat: (0.0, 0.0, 0.0).into(),
       up: cgmath::Vector3::unit_y(),
       aspect: config.width as f32 / config.height as f32,
       fov: 45.0,
       near: 0.1,
-      far: 1000.0
+      far: 100.0
     };
     let camera_info = CameraInfo::new(&camera, &device);
+    let sample_count = 4;
+    let depth_format = if options.outline { texture::Texture::DEPTH_STENCIL_FORMAT } else { texture::Texture::DEPTH_FORMAT };
+    let depth_texture = texture::Texture::create_depth_texture(&device, &config, sample_count, depth_format, "depth_texture");
+    let ssao = Ssao::new(SsaoSettings::default(), &camera, &depth_texture, sample_count, &config, &device, &queue);
+    let ibl_info = IblInfo::new(&Environment::load(options.env_path.as_deref()), &device, &queue);
     let background = wgpu::Color {
       r: 1.0,
       g: 0.0,
       b: 0.0,
       a: 1.0,
     };
-    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
-      label: Some("Shader"),
-      source: wgpu::ShaderSource::Wgsl(include_str!("texture-camera.wgsl").into())
-    });
-    let shader2 = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
-      label: Some("Shader"),
-      source: wgpu::ShaderSource::Wgsl(include_str!("test2.wgsl").into())
-    });
     let diffuse_texture = texture::Texture::default(&device, &queue).unwrap();
-    let texture_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-      label: Some("texture_bind_group_layout"),
-      entries: &[
-        wgpu::BindGroupLayoutEntry {
-          binding: 0,
-          visibility: wgpu::ShaderStages::FRAGMENT,
-          ty: wgpu::BindingType::Texture {
-            multisampled: false,
-            view_dimension: wgpu::TextureViewDimension::D2,
-            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-          },
-          count: None
-        },
-        wgpu::BindGroupLayoutEntry {
-          binding: 1,
-          visibility: wgpu::ShaderStages::FRAGMENT,
-          ty: wgpu::BindingType::Sampler(
-            // SamplerBindingType::Comparison is only for TextureSampleType::Depth
-            // SamplerBindingType::Filtering if the sample_type of the texture is:
-            //     TextureSampleType::Float { filterable: true }
-            // Otherwise you'll get an error.
-            wgpu::SamplerBindingType::Filtering,
-          ),
-          count: None,
-        }
-      ]
-    });
-    let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-      label: Some("texture_bind_group"),
-      layout: &texture_bind_group_layout,
-      entries: &[
-        wgpu::BindGroupEntry {
-          binding: 0,
-          resource: wgpu::BindingResource::TextureView(&diffuse_texture.view)
-        },
-        wgpu::BindGroupEntry {
-          binding: 1,
-          resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler)
-        }
-      ]
-    });
+    let material = Material {
+      base_color_texture: Some(diffuse_texture),
+      ..Material::from_gltf([1.0; 4], 0.0, 0.5, [0.0; 3])
+    };
+    let material_layout = MaterialInfo::create_layout(&device);
+    let material_info = MaterialInfo::new(&material, &material_layout, &device, &queue);
+    let ground_material = Material::from_gltf([0.5, 0.5, 0.5, 1.0], 0.0, 0.8, [0.0; 3]);
+    let ground_material_info = MaterialInfo::new(&ground_material, &material_layout, &device, &queue);
+    let lights = vec![
+      Light::directional((-0.4, -0.6, -0.3).into(), [1.0, 0.92, 0.8], 3.0, 8.0), // 与程序化天空的太阳方向一致
+      Light::spot((3.0, 4.0, 3.0).into(), (-3.0, -4.0, -3.0).into(), [1.0, 0.6, 0.3], 40.0, 15.0, cgmath::Deg(20.0), cgmath::Deg(30.0))
+    ];
+    let point_lights = vec![
+      PointLight::new((-2.0, 1.0, -2.0).into(), [1.0, 0.3, 0.2], 8.0, 5.0),
+      PointLight::new((2.0, 1.0, 2.0).into(), [0.2, 0.4, 1.0], 8.0, 5.0)
+    ];
+    let shadow_map = ShadowMap::new(ShadowSettings::default(), &device);
+    shadow_map.update_lights(&lights, &queue);
+    shadow_map.update_point_lights(&point_lights, &queue);
+    let light_info = LightInfo::new(&lights, &point_lights, &shadow_map, &device);
     let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
       label: Some("Render Pipeline Layout"),
       bind_group_layouts: &[
-        &texture_bind_group_layout,
-        &camera_info.layout
+        &material_layout,
+        &camera_info.layout,
+        &ibl_info.layout,
+        &light_info.layout,
+        &ssao.occlusion_layout
       ],
       push_constant_ranges: &[]
     });
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-      label: Some("Render Pipeline"),
-      layout: Some(&render_pipeline_layout),
-      vertex: wgpu::VertexState {
-        module: &shader,
-        entry_point: "vs_main",
-        buffers: &[
-          Vertex::desc(),
-          InstanceData::desc()
-        ]
-      },
-      fragment: Some(wgpu::FragmentState {
-        module: &shader,
-        entry_point: "fs_main",
-        targets: &[wgpu::ColorTargetState {
-          format: config.format,
-          blend: Some(wgpu::BlendState::REPLACE),
-          write_mask: wgpu::ColorWrites::ALL,
-        }],
-      }),
-      primitive: wgpu::PrimitiveState { // 图元设置，如何生成三角
-        topology: wgpu::PrimitiveTopology::TriangleList, // 每三个顶点为一个三角形
-        strip_index_format: None,
-        front_face: wgpu::FrontFace::Ccw, // 逆时针为正面
-        cull_mode: Some(wgpu::Face::Back), // 背面隐藏
-        polygon_mode: wgpu::PolygonMode::Fill, // 填充着色
-        unclipped_depth: false,
-        conservative: false
-      },
-      depth_stencil: Some(wgpu::DepthStencilState {
-        format: texture::Texture::DEPTH_FORMAT,
-        depth_write_enabled: true,
-        depth_compare: wgpu::CompareFunction::Less,
-        stencil: wgpu::StencilState::default(),
-        bias: wgpu::DepthBiasState::default()
-      }), // 深度模板缓存
-      multisample: wgpu::MultisampleState {
-        count: 1,
-        mask: !0,
-        alpha_to_coverage_enabled: false,
-      },
-      multiview: None
-    });
-    let render_pipeline2 = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-      label: Some("Render Pipeline"),
-      layout: Some(&render_pipeline_layout),
-      vertex: wgpu::VertexState {
-        module: &shader2,
-        entry_point: "vs_main",
-        buffers: &[]
-      },
-      fragment: Some(wgpu::FragmentState {
-        module: &shader2,
-        entry_point: "fs_main",
-        targets: &[wgpu::ColorTargetState {
-          format: config.format,
-          blend: Some(wgpu::BlendState::REPLACE),
-          write_mask: wgpu::ColorWrites::ALL,
-        }],
-      }),
-      primitive: wgpu::PrimitiveState { // 图元设置，如何生成三角
-        topology: wgpu::PrimitiveTopology::TriangleList, // 每三个顶点为一个三角形
-        strip_index_format: None,
-        front_face: wgpu::FrontFace::Ccw, // 逆时针为正面
-        cull_mode: Some(wgpu::Face::Back), // 背面隐藏
-        polygon_mode: wgpu::PolygonMode::Fill, // 填充着色
-        unclipped_depth: false,
-        conservative: false
-      },
-      depth_stencil: None, // 深度模板缓存
-      multisample: wgpu::MultisampleState {
-        count: 1,
-        mask: !0,
-        alpha_to_coverage_enabled: false,
-      },
-      multiview: None
-    });
-    let buffer_info = get_circle(64, 0.4, size.width as f32 / size.height as f32);
-    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-      label: Some("Vertex Buffer"),
+    let (render_pipeline, depth_prepass_pipeline) = create_render_pipelines(&device, &render_pipeline_layout, texture::Texture::HDR_FORMAT, sample_count, depth_format);
+    let debug_views = DebugViews::new(&camera, &camera_info.layout, sample_count, depth_format, &device);
+    let wireframe = Wireframe::new(options.wireframe, &camera_info.layout, sample_count, depth_format, &device);
+    println!("wireframe: {}", if wireframe.line_mode() { "polygon mode line" } else { "barycentric fallback" });
+    let debug_draw = DebugDraw::new(&camera_info.layout, sample_count, depth_format, &device);
+    let grid = Grid::new(&camera, sample_count, depth_format, &device);
+    let gizmo_draw = DebugDraw::new_overlay(&camera_info.layout, sample_count, depth_format, &device);
+    let picking = Picking::new(3, &camera_info.layout, &config, &device);
+    let profiler = GpuProfiler::new(&device, &queue);
+    if profiler.is_none() {
+      println!("GPU timestamps unavailable: adapter lacks TIMESTAMP_QUERY");
+    }
+    let ui = window.map(|window| Ui::new(window, &config, &device));
+    let mut outline = options.outline.then(|| Outline::new(&camera_info.layout, sample_count, depth_format, &config, &device));
+    if let (Some(outline), Some(color)) = (&mut outline, options.outline_color) {
+      outline.set_color(color, &queue);
+    }
+    let sphere_info = get_sphere(32, 16, 0.3);
+    let ground_info = get_plane((options.instance_grid as f32 + 2.0).max(20.0), GROUND_Y); // 地面需覆盖所有实例
+    let mut mesh = Mesh::new(&device, &sphere_info, "Sphere");
+    let mut ground = Mesh::new(&device, &ground_info, "Ground");
+    if wireframe.needs_unindexed() {
+      mesh.create_unindexed_buffer(&device, &sphere_info, "Sphere");
+      ground.create_unindexed_buffer(&device, &ground_info, "Ground");
+    }
+    let ground_instance_buffer = stats::create_buffer_init(&device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Ground Instance Buffer"),
       usage: wgpu::BufferUsages::VERTEX,
-      contents: bytemuck::cast_slice(&buffer_info.vertices),
+      contents: bytemuck::cast_slice(&[Instance {
+        center: cgmath::Vector3::zero(),
+        rotation: cgmath::Quaternion::one(),
+        scale: cgmath::Vector3::new(1.0, 1.0, 1.0)
+      }.get_data()]),
     });
-    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-      label: Some("Index Buffer"),
-      usage: wgpu::BufferUsages::INDEX,
-      contents: bytemuck::cast_slice(&buffer_info.indices),
+    let model = match &options.model_path {
+      Some(path) => match obj::load(path) {
+        Ok(model) => {
+          println!("loaded {} ({} parts, {} materials)", path.display(), model.parts.len(), model.materials.len());
+          model.upload(&material_layout, wireframe.needs_unindexed(), &device, &queue)
+        }
+        Err(err) => {
+          eprintln!("failed to load model: {:?}", err);
+          vec![]
+        }
+      },
+      None => vec![],
+    };
+    let model_data = get_model_instance(&model, options.instance_grid).get_data();
+    let model_instance_buffer = stats::create_buffer_init(&device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Model Instance Buffer"),
+      usage: wgpu::BufferUsages::VERTEX,
+      contents: bytemuck::cast_slice(&[model_data]),
     });
-    let instances = get_instances();
+    let instances = get_instances(options.instance_grid);
     let instance_data = instances.iter().map(Instance::get_data).collect::<Vec<_>>();
-    let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+    let instance_spheres = instance_data.iter()
+      .map(|data| mesh.bounding_sphere.transform(&data.model_matrix.into()))
+      .collect::<Vec<_>>();
+    let instance_buffer = stats::create_buffer_init(&device, &wgpu::util::BufferInitDescriptor {
       label: Some("Instance Buffer"),
-      usage: wgpu::BufferUsages::VERTEX,
+      usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST, // 拖动手柄时更新
+      contents: bytemuck::cast_slice(&instance_data),
+    });
+    let gpu_culling = GpuCulling::new(&instance_data, &instance_spheres, mesh.index_num, &device);
+    let visible_instance_buffer = stats::create_buffer_init(&device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Visible Instance Buffer"),
+      usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
       contents: bytemuck::cast_slice(&instance_data),
     });
-    let depth_texture = texture::Texture::create_depth_texture(&device, &config, "depth_texture");
-    surface.configure(&device, &config); // 初始化时一定要进行配置
-    State {
+    let msaa_texture = create_msaa_texture(&device, &config, sample_count);
+    let post = PostProcess::new(&device, &config, &options.post_order);
+    if let Some(surface) = &surface {
+      surface.configure(&device, &config); // 初始化时一定要进行配置
+    }
+    let mut state = State {
       size,
       surface,
       device,
@@ -268,34 +726,215 @@ impl State {
       config,
       background,
       render_pipeline,
-      render_pipeline2,
-      render_pipeline_default: true,
-      vertex_buffer,
-      index_buffer,
-      index_num: buffer_info.indices.len() as u32,
-      texture_bind_group,
+      debug_view: options.debug_view,
+      debug_views,
+      wireframe,
+      debug_draw,
+      grid,
+      show_helpers: false,
+      frozen_camera: None,
+      selection: None,
+      picking,
+      pick_cursor: None,
+      outline,
+      cursor: (0.0, 0.0),
+      gizmo: Gizmo::new(),
+      gizmo_draw,
+      history: History::new(100),
+      modifiers: ModifiersState::empty(),
+      ui,
+      auto_depth: true,
+      stats: FrameStats::new(),
+      profiler,
+      measure_points: vec![],
+      render_pipeline_layout,
+      depth_prepass_pipeline,
+      ssao,
+      sample_count,
+      msaa_texture,
+      post,
+      mesh,
+      material,
+      material_info,
+      ground,
+      ground_material,
+      ground_material_info,
+      ground_instance_buffer,
+      model,
+      model_data,
+      model_instance_buffer,
       camera,
       camera_info,
+      ibl_info,
+      lights,
+      point_lights,
+      light_info,
+      shadow_map,
       instances,
       instance_buffer,
+      instance_data,
+      instance_spheres,
+      visible_instance_buffer,
+      cull_mode: options.cull_mode,
+      cull_stats: CullStats::default(),
+      gpu_culling,
+      stats_timer: std::time::Instant::now(),
+      depth_format,
       depth_texture
+    };
+    if let Some(mode) = options.anti_aliasing {
+      state.set_anti_aliasing(mode);
+    }
+    state.frame(None);
+    Ok(state)
+  }
+
+  /// 第`index`个实例的世界空间包围盒
+  fn instance_bounds(&self, index: usize) -> Aabb {
+    self.mesh.bounds.transform(&self.instance_data[index].model_matrix.into())
+  }
+
+  /// 模型各部分的世界空间包围盒
+  fn model_bounds(&self) -> impl Iterator<Item = Aabb> + '_ {
+    self.model.iter().map(|part| part.mesh.bounds.transform(&self.model_data.model_matrix.into()))
+  }
+
+  /// 整个场景（所有实例、地面和模型）的包围盒
+  fn scene_bounds(&self) -> Aabb {
+    (0..self.instances.len())
+      .map(|index| self.instance_bounds(index))
+      .chain(self.model_bounds())
+      .fold(self.ground.bounds, |bounds, instance| bounds.union(&instance))
+  }
+
+  /// CPU射线检测，返回射线在场景中的最近交点
+  fn raycast(&self, ray: &Ray) -> Option<RayHit> {
+    let ground_data = [Instance {
+      center: cgmath::Vector3::zero(),
+      rotation: cgmath::Quaternion::one(),
+      scale: cgmath::Vector3::new(1.0, 1.0, 1.0)
+    }.get_data()];
+    let sphere_hit = raycast_instances(ray, SPHERE_MESH_ID, &self.mesh.bvh, &self.mesh.bounding_sphere, &self.instance_data, f32::MAX);
+    let max_distance = sphere_hit.map_or(f32::MAX, |hit| hit.hit.distance);
+    let mut hit = raycast_instances(ray, GROUND_MESH_ID, &self.ground.bvh, &self.ground.bounding_sphere, &ground_data, max_distance).or(sphere_hit);
+    for part in &self.model {
+      let max_distance = hit.map_or(f32::MAX, |hit| hit.hit.distance);
+      hit = raycast_instances(ray, MODEL_MESH_ID, &part.mesh.bvh, &part.mesh.bounding_sphere, &[self.model_data], max_distance).or(hit);
+    }
+    hit
+  }
+
+  /// 穿过光标所在像素的世界空间射线
+  fn cursor_ray(&self) -> Option<Ray> {
+    Ray::from_screen(&self.camera, self.cursor.0 as f32, self.cursor.1 as f32, self.size.width, self.size.height)
+  }
+
+  /// 重新生成`grid_size` x `grid_size`个实例，并重建实例缓冲和GPU剔除数据
+  fn set_instance_grid(&mut self, grid_size: u32) {
+    self.instances = get_instances(grid_size);
+    self.instance_data = self.instances.iter().map(Instance::get_data).collect();
+    self.instance_spheres = self.instance_data.iter()
+      .map(|data| self.mesh.bounding_sphere.transform(&data.model_matrix.into()))
+      .collect();
+    self.instance_buffer = stats::create_buffer_init(&self.device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Instance Buffer"),
+      usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+      contents: bytemuck::cast_slice(&self.instance_data),
+    });
+    self.visible_instance_buffer = stats::create_buffer_init(&self.device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Visible Instance Buffer"),
+      usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+      contents: bytemuck::cast_slice(&self.instance_data),
+    });
+    self.gpu_culling = GpuCulling::new(&self.instance_data, &self.instance_spheres, self.mesh.index_num, &self.device);
+    self.selection = None;
+    self.history.clear(); // 历史中的实例序号已失效
+    self.update_camera();
+  }
+
+  /// 修改`instances[index]`后重新计算实例数据和包围球，并上传到GPU
+  fn update_instance(&mut self, index: usize) {
+    let data = self.instances[index].get_data();
+    let sphere = self.mesh.bounding_sphere.transform(&data.model_matrix.into());
+    self.instance_data[index] = data;
+    self.instance_spheres[index] = sphere;
+    let offset = (index * std::mem::size_of::<InstanceData>()) as wgpu::BufferAddress;
+    self.queue.write_buffer(&self.instance_buffer, offset, bytemuck::cast_slice(&[data]));
+    self.gpu_culling.update_instance(index, &data, &sphere, &self.queue); // CPU剔除每帧由instance_data重新计算
+  }
+
+  /// 在光标处放置测量点，已有两个点时重新开始
+  fn measure(&mut self) {
+    let ray = match self.cursor_ray() {
+      Some(ray) => ray,
+      None => return,
+    };
+    let hit = match self.raycast(&ray) {
+      Some(hit) => hit,
+      None => {
+        println!("raycast: no hit");
+        return;
+      }
+    };
+    println!(
+      "raycast: mesh {} instance {} triangle {} at {:?}, distance {:.3}, barycentric {:?}, uv {:?}",
+      hit.mesh_id,
+      hit.instance,
+      hit.hit.triangle,
+      hit.position,
+      hit.hit.distance,
+      hit.hit.barycentric,
+      hit.hit.uv
+    );
+    if self.measure_points.len() >= 2 {
+      self.measure_points.clear();
+    }
+    self.measure_points.push(hit.position);
+    if let [start, end] = self.measure_points[..] {
+      println!("measure: {:.3}", start.distance(end));
     }
   }
 
+  /// 将相机对准选中的实例，`None`时对准整个场景
+  fn frame(&mut self, selection: Option<usize>) {
+    let target = match selection {
+      Some(index) => self.instance_spheres[index],
+      None => self.scene_bounds().bounding_sphere(),
+    };
+    self.camera.frame(&target);
+    self.update_camera();
+  }
+
   /// 窗口尺寸变化相关处理
   pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
     if new_size.width > 0 && new_size.height > 0 {
       self.size = new_size;
       self.config.width = new_size.width;
       self.config.height = new_size.height;
-      self.surface.configure(&self.device, &self.config);
+      if let Some(surface) = &self.surface {
+        surface.configure(&self.device, &self.config);
+      }
     }
-    self.depth_texture = texture::Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
+    self.depth_texture = texture::Texture::create_depth_texture(&self.device, &self.config, self.sample_count, self.depth_format, "depth_texture");
+    self.msaa_texture = create_msaa_texture(&self.device, &self.config, self.sample_count);
+    self.post.resize(&self.device, &self.config);
+    self.picking.resize(&self.config, &self.device);
+    self.pick_cursor = None; // 大小改变后旧的光标位置不再有效
+    if let Some(outline) = &mut self.outline {
+      outline.resize(&self.config, &self.queue);
+    }
+    self.ssao.resize(&self.depth_texture, self.sample_count, &self.config, &self.device);
   }
 
   fn update_camera(&mut self) {
+    if self.auto_depth {
+      self.camera.fit_depth(&self.scene_bounds().bounding_sphere());
+    }
     self.camera_info.update_info(&self.camera, &self.device);
     self.queue.write_buffer(&self.camera_info.buffer, 0, bytemuck::cast_slice(&[self.camera_info.uniform]));
+    self.ssao.update(self.ssao.settings, &self.camera, &self.queue);
+    self.debug_views.update_camera(&self.camera, &self.queue);
+    self.grid.update_camera(&self.camera, &self.queue);
   }
 
   /// 相机控制（事件处理）
@@ -338,137 +977,1314 @@ impl State {
     }
   }
 
+  /// 处理输入，并将相机和显示方式的变化记录到历史中
   pub fn input(&mut self, event: &WindowEvent) -> bool {
+    if self.ui.as_mut().is_some_and(|ui| ui.on_event(event)) {
+      return true; // 界面使用的事件不再控制场景
+    }
     match event {
-      WindowEvent::CursorMoved {
-        device_id: _,
-        position: winit::dpi::PhysicalPosition { x, y },
-        modifiers: _,
-      } => {
-        self.background = wgpu::Color {
-          r: x / (self.size.width as f64),
-          g: y / (self.size.height as f64),
-          b: 0.0,
-          a: 1.0
-        }; // 根据鼠标位置改变背景颜色
-        true
+      WindowEvent::ModifiersChanged(modifiers) => {
+        self.modifiers = *modifiers;
+        return true;
       },
       WindowEvent::KeyboardInput {
         input: KeyboardInput {
           state: ElementState::Pressed,
-          virtual_keycode: Some(VirtualKeyCode::Space),
+          virtual_keycode: Some(VirtualKeyCode::Z),
           ..
         },
         ..
-      } => {
-        self.render_pipeline_default = !self.render_pipeline_default; // 切换渲染管线状态
-        true
-      },
-      _ => {
-        let camera_state = self.camera_control(event);
-        if camera_state {
-          println!("update camera");
-          self.update_camera();
+      } if self.modifiers.ctrl() && !self.gizmo.is_dragging() => {
+        // Ctrl+Z撤销，Ctrl+Shift+Z重做
+        let edit = if self.modifiers.shift() { self.history.redo() } else { self.history.undo() };
+        match edit {
+          Some(edit) => self.apply_edit(edit),
+          None => println!("nothing to {}", if self.modifiers.shift() { "redo" } else { "undo" }),
         }
-        camera_state || false
-      }
+        return true;
+      },
+      _ => {}
     }
+    let settings = (self.camera, self.debug_view);
+    let handled = self.handle_input(event);
+    self.record_settings(settings);
+    handled
   }
 
-  fn update(&mut self) {
-    //
+  /// 将相机和显示方式相对`before`的变化记录到历史中；背景色只在界面中修改时记录
+  fn record_settings(&mut self, (camera, debug_view): (Camera, DebugView)) {
+    if self.camera != camera {
+      self.history.push(Edit::Camera { before: camera, after: self.camera });
+    }
+    if self.debug_view != debug_view {
+      self.history.push(Edit::DebugView { before: debug_view, after: self.debug_view });
+    }
   }
 
-  fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-    let output = self.surface.get_current_texture()?;
-    let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
-    let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-      label: Some("Render Encoder")
-    });
-    {
-      let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-        label: Some("Render Pass"),
-        color_attachments: &[wgpu::RenderPassColorAttachment {
-          view: &view,
-          resolve_target: None,
-          ops: wgpu::Operations {
-            load: wgpu::LoadOp::Clear(self.background),
-            store: true,
-          }
-        }],
-        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-          view: &self.depth_texture.view,
-          depth_ops: Some(wgpu::Operations {
-            load: wgpu::LoadOp::Clear(1.0),
-            store: true
-          }),
-          stencil_ops: None
-        }) // 深度纹理配置
-      });
-      render_pass.set_pipeline(if let true = self.render_pipeline_default {
-        &self.render_pipeline
-      } else {
-        &self.render_pipeline2
-      }); // 根据状态切换渲染管线
-      render_pass.set_bind_group(0, &self.texture_bind_group, &[]); // 绑定到group中
-      render_pass.set_bind_group(1, &self.camera_info.group, &[]);
-      render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-      render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-      render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16); // 指定索引缓冲
-      render_pass.draw_indexed(0..self.index_num, 0, 0..(self.instances.len() as u32)); // 指定顶点数和实例数
+  /// 构建本帧的界面，界面中的修改同样记录到历史中
+  fn build_ui(&mut self, window: &Window) {
+    let (context, input) = match &mut self.ui {
+      Some(ui) if ui.visible => (ui.context(), ui.take_input(window)),
+      _ => return,
+    };
+    let settings = (self.camera, self.debug_view);
+    let output = context.run(input, |context| self.ui_panels(context));
+    self.record_settings(settings);
+    if let Some(ui) = &mut self.ui {
+      ui.finish(window, output);
     }
+  }
 
-    self.queue.submit(std::iter::once(encoder.finish()));
-    output.present();
-
-    Ok(())
+  fn ui_panels(&mut self, context: &egui::Context) {
+    egui::Window::new("Scene").default_width(260.0).show(context, |ui| {
+      ui.collapsing("Stats", |ui| {
+        let times = self.stats.times();
+        ui.label(format!("{:.2} ms/frame ({:.0} fps)", times.avg, times.fps()));
+        ui.label(format!("min {:.2} / max {:.2} / p95 {:.2} / p99 {:.2} ms", times.min, times.max, times.p95, times.p99));
+        ui.label(format!("{}x{}, anti-aliasing {}, MSAA {}x", self.size.width, self.size.height, self.anti_aliasing().name(), self.sample_count));
+        let draws = self.stats.last;
+        ui.label(format!("{} draw calls, {} triangles, {} instances drawn", draws.draw_calls, draws.triangles, draws.instances));
+        let (buffer_bytes, texture_bytes) = stats::allocated();
+        ui.label(format!("allocated: buffers {:.1} MiB, textures {:.1} MiB", buffer_bytes as f64 / MIB, texture_bytes as f64 / MIB));
+        match &self.profiler {
+          Some(profiler) => {
+            ui.label(format!("GPU {:.3} ms", profiler.total()));
+            for (label, ms) in profiler.timings() {
+              ui.label(format!("  {} {:.3} ms", label, ms));
+            }
+          },
+          None => {
+            ui.label("GPU timings unavailable");
+          },
+        }
+        match self.cull_mode {
+          CullMode::Cpu => ui.label(format!("instances: {} visible, {} culled", self.cull_stats.visible, self.cull_stats.culled)),
+          CullMode::Gpu => match self.gpu_culling.visible() {
+            Some(visible) => ui.label(format!("instances: {} visible (GPU, read back)", visible)),
+            None => ui.label(format!("instances: {}, GPU visible count unavailable", self.instances.len())),
+          },
+          CullMode::Off => ui.label(format!("instances: {}", self.instances.len())),
+        };
+      });
+      ui.collapsing("Camera", |ui| {
+        let mut changed = ui.add(egui::Slider::new(&mut self.camera.fov, 10.0..=120.0).text("fov")).changed();
+        changed |= ui.checkbox(&mut self.auto_depth, "fit near/far to scene").changed();
+        ui.add_enabled_ui(!self.auto_depth, |ui| {
+          ui.horizontal(|ui| {
+            ui.label("near");
+            changed |= ui.add(egui::DragValue::new(&mut self.camera.near).speed(0.01).clamp_range(0.001..=1000.0)).changed();
+            ui.label("far");
+            changed |= ui.add(egui::DragValue::new(&mut self.camera.far).speed(0.1).clamp_range(0.01..=10000.0)).changed();
+          });
+        });
+        for (label, point) in [("eye", &mut self.camera.eye), ("lookat", &mut self.camera.lookat)] {
+          ui.horizontal(|ui| {
+            ui.label(label);
+            changed |= ui.add(egui::DragValue::new(&mut point.x).speed(0.05)).changed();
+            changed |= ui.add(egui::DragValue::new(&mut point.y).speed(0.05)).changed();
+            changed |= ui.add(egui::DragValue::new(&mut point.z).speed(0.05)).changed();
+          });
+        }
+        if changed {
+          self.camera.far = self.camera.far.max(self.camera.near * 1.01);
+          self.update_camera();
+        }
+      });
+      ui.collapsing("Background", |ui| {
+        let mut color = [self.background.r as f32, self.background.g as f32, self.background.b as f32];
+        if ui.color_edit_button_rgb(&mut color).changed() {
+          let before = self.background;
+          self.background = wgpu::Color { r: color[0] as f64, g: color[1] as f64, b: color[2] as f64, a: 1.0 };
+          self.history.push(Edit::Background { before, after: self.background });
+        }
+      });
+      ui.collapsing("Rendering", |ui| {
+        egui::ComboBox::from_label("view").selected_text(self.debug_view.name()).show_ui(ui, |ui| {
+          for view in DebugView::ALL {
+            ui.selectable_value(&mut self.debug_view, view, view.name());
+          }
+        });
+        let mut wireframe = self.wireframe.mode;
+        egui::ComboBox::from_label("wireframe").selected_text(wireframe.name()).show_ui(ui, |ui| {
+          for mode in WireframeMode::ALL {
+            ui.selectable_value(&mut wireframe, mode, mode.name());
+          }
+        });
+        if wireframe != self.wireframe.mode {
+          self.wireframe.set_mode(wireframe, &self.queue);
+        }
+        egui::ComboBox::from_label("culling").selected_text(self.cull_mode.name()).show_ui(ui, |ui| {
+          for mode in CullMode::ALL {
+            ui.selectable_value(&mut self.cull_mode, mode, mode.name());
+          }
+        });
+        let mut sample_count = self.sample_count;
+        egui::ComboBox::from_label("MSAA").selected_text(format!("{}x", sample_count)).show_ui(ui, |ui| {
+          for count in SAMPLE_COUNTS {
+            ui.selectable_value(&mut sample_count, count, format!("{}x", count));
+          }
+        });
+        if sample_count != self.sample_count {
+          self.set_sample_count(sample_count);
+        }
+        ui.checkbox(&mut self.grid.show_grid, "grid");
+        ui.checkbox(&mut self.grid.show_gizmo, "axis gizmo");
+        ui.checkbox(&mut self.show_helpers, "helpers");
+      });
+      ui.collapsing("Post", |ui| {
+        // 按执行顺序列出后处理链，抗锯齿pass由下面的抗锯齿方式控制
+        for index in 0..self.post.passes.len() {
+          let pass = self.post.passes[index];
+          let mut enabled = pass.enabled;
+          ui.add_enabled_ui(!pass.effect.is_anti_aliasing(), |ui| {
+            if ui.checkbox(&mut enabled, pass.effect.name()).changed() {
+              self.post.toggle(pass.effect);
+            }
+          });
+        }
+        let mut anti_aliasing = self.anti_aliasing();
+        egui::ComboBox::from_label("anti-aliasing").selected_text(anti_aliasing.name()).show_ui(ui, |ui| {
+          for mode in AntiAliasing::ALL {
+            ui.selectable_value(&mut anti_aliasing, mode, mode.name());
+          }
+        });
+        if anti_aliasing != self.anti_aliasing() {
+          self.set_anti_aliasing(anti_aliasing);
+        }
+        let mut settings = self.post.settings;
+        egui::ComboBox::from_label("tonemap").selected_text(format!("{:?}", settings.tonemap)).show_ui(ui, |ui| {
+          ui.selectable_value(&mut settings.tonemap, Tonemap::Aces, "Aces");
+          ui.selectable_value(&mut settings.tonemap, Tonemap::Reinhard, "Reinhard");
+        });
+        ui.add(egui::Slider::new(&mut settings.exposure, 0.05..=8.0).logarithmic(true).text("exposure"));
+        ui.add(egui::Slider::new(&mut settings.bloom_threshold, 0.0..=4.0).text("bloom threshold"));
+        ui.add(egui::Slider::new(&mut settings.bloom_intensity, 0.0..=0.5).text("bloom intensity"));
+        if settings != self.post.settings {
+          self.post.set_settings(settings, &self.queue);
+        }
+      });
+      ui.collapsing("Shadows", |ui| {
+        let mut settings = self.shadow_map.settings;
+        let changed = [
+          ui.add(egui::Slider::new(&mut settings.pcf_radius, 0..=3).text("PCF radius")).changed(),
+          ui.add(egui::Slider::new(&mut settings.depth_bias, 0..=16).text("depth bias")).changed(),
+          ui.add(egui::Slider::new(&mut settings.slope_scale, 0.0..=8.0).text("slope scale")).changed(),
+        ];
+        if changed.contains(&true) {
+          self.set_shadow_settings(settings);
+        }
+      });
+      ui.collapsing("Instances", |ui| {
+        let mut grid_size = (self.instances.len() as f64).sqrt().round() as u32;
+        if ui.add(egui::Slider::new(&mut grid_size, 1..=200).text("grid size")).changed() {
+          self.set_instance_grid(grid_size);
+        }
+        ui.label(format!("{} instances", self.instances.len()));
+      });
+      ui.collapsing("Materials", |ui| {
+        let model = self.model.iter_mut().map(|part| (part.name.as_str(), &mut part.material, &mut part.material_info));
+        for (label, material, info) in [("sphere", &mut self.material, &mut self.material_info), ("ground", &mut self.ground_material, &mut self.ground_material_info)].into_iter().chain(model) {
+          ui.label(label);
+          let mut changed = ui.horizontal(|ui| {
+            ui.label("base color");
+            ui.color_edit_button_rgba_unmultiplied(&mut material.base_color).changed()
+          }).inner;
+          changed |= ui.add(egui::Slider::new(&mut material.metallic, 0.0..=1.0).text("metallic")).changed();
+          changed |= ui.add(egui::Slider::new(&mut material.roughness, 0.0..=1.0).text("roughness")).changed();
+          changed |= ui.horizontal(|ui| {
+            ui.label("emissive");
+            ui.color_edit_button_rgb(&mut material.emissive).changed()
+          }).inner;
+          if changed {
+            info.update(material, &self.queue);
+          }
+          ui.separator();
+        }
+      });
+    });
   }
-}
 
-fn main() {
-  env_logger::init();
-  let event_loop = EventLoop::new();
-  let window = WindowBuilder::new().build(&event_loop).unwrap();
-  let mut state = pollster::block_on(State::new(&window));
+  /// 应用一次修改（撤销时为反向修改）
+  fn apply_edit(&mut self, edit: Edit) {
+    match edit {
+      Edit::Instance { index, after, .. } => {
+        self.instances[index] = after;
+        self.update_instance(index);
+      },
+      Edit::Camera { after, .. } => {
+        self.camera = Camera { aspect: self.camera.aspect, ..after }; // 宽高比随窗口变化，不属于修改
+        self.update_camera();
+      },
+      Edit::Background { after, .. } => self.background = after,
+      Edit::DebugView { after, .. } => self.debug_view = after,
+    }
+  }
 
-  event_loop.run(move |event, _, control_flow| match event {
-    Event::WindowEvent {
-      ref event,
-      window_id,
-    } if window_id == window.id() => if !state.input(event) {
-      match event {
-        WindowEvent::CloseRequested
-        | WindowEvent::KeyboardInput {
-          input:
-            KeyboardInput {
-              state: ElementState::Pressed,
-              virtual_keycode: Some(VirtualKeyCode::Escape),
-              ..
-            },
-          ..
-        } => *control_flow = ControlFlow::Exit,
-        WindowEvent::Resized(new_size) => {
-          state.resize(*new_size);
-        },
-        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-          state.resize(**new_inner_size); // mut再解引用就是正常的变量了？
-        },
-        _ => {}
-      }
-    },
-    Event::RedrawRequested(window_id) if window_id == window.id() => {
-      state.update();
-      match state.render() {
-        Ok(_) => {},
-        Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
-        Err(wgpu::SurfaceError::OutOfMemory) => {
-          *control_flow = ControlFlow::Exit;
-        },
-        Err(e) => eprintln!("{:?}", e)
-      }
-    },
-    Event::MainEventsCleared => {
-      window.request_redraw();
+  fn handle_input(&mut self, event: &WindowEvent) -> bool {
+    match event {
+      WindowEvent::CursorMoved {
+        device_id: _,
+        position: winit::dpi::PhysicalPosition { x, y },
+        ..
+      } => {
+        self.cursor = (*x, *y);
+        if let (Some(index), Some(ray)) = (self.selection, self.cursor_ray()) {
+          if self.gizmo.is_dragging() {
+            if self.gizmo.drag(&ray, &mut self.instances[index]) {
+              self.update_instance(index);
+            }
+            return true;
+          }
+          self.gizmo.hover(&ray, &self.instances[index], &self.camera);
+        }
+        true
+      },
+      WindowEvent::MouseInput {
+        state: ElementState::Pressed,
+        button: MouseButton::Left,
+        ..
+      } => {
+        if let (Some(index), Some(ray)) = (self.selection, self.cursor_ray()) {
+          if self.gizmo.begin(&ray, &self.instances[index], &self.camera) {
+            return true; // 点中手柄时开始拖动，不改变选中
+          }
+        }
+        // 拾取光标处的实例，结果在之后的帧中取回
+        let cursor = (self.cursor.0 as u32, self.cursor.1 as u32);
+        self.picking.pick(cursor.0, cursor.1);
+        self.pick_cursor = Some(cursor);
+        true
+      },
+      WindowEvent::MouseInput {
+        state: ElementState::Released,
+        button: MouseButton::Left,
+        ..
+      } => {
+        if let (Some(before), Some(index)) = (self.gizmo.end(), self.selection) {
+          let after = self.instances[index];
+          if before != after {
+            self.history.push(Edit::Instance { index, before, after }); // 一次拖动记录为一步
+          }
+        }
+        true
+      },
+      WindowEvent::MouseInput {
+        state: ElementState::Pressed,
+        button: MouseButton::Right,
+        ..
+      } => {
+        self.measure();
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::Space),
+          ..
+        },
+        ..
+      } => {
+        self.debug_view = self.debug_view.next(); // 循环切换显示方式
+        println!("debug view: {}", self.debug_view.name());
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::Key9 | VirtualKeyCode::Key0)),
+          ..
+        },
+        ..
+      } => {
+        if let Some(outline) = &mut self.outline {
+          let delta = if *key == VirtualKeyCode::Key0 { 1.0 } else { -1.0 };
+          outline.set_thickness(outline.thickness() + delta, &self.queue); // 调整轮廓宽度
+          println!("outline thickness: {}", outline.thickness());
+        }
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::X),
+          ..
+        },
+        ..
+      } => {
+        self.show_helpers = !self.show_helpers; // 开关辅助线
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::G | VirtualKeyCode::A)),
+          ..
+        },
+        ..
+      } => {
+        if *key == VirtualKeyCode::G {
+          self.grid.show_grid = !self.grid.show_grid; // 开关地面网格
+        } else {
+          self.grid.show_gizmo = !self.grid.show_gizmo; // 开关坐标轴指示
+        }
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::Tab),
+          ..
+        },
+        ..
+      } => {
+        // 依次选中各实例，最后回到不选中
+        self.selection = match self.selection {
+          None => Some(0),
+          Some(index) if index + 1 < self.instances.len() => Some(index + 1),
+          Some(_) => None,
+        };
+        println!("selection: {:?}", self.selection);
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::F),
+          ..
+        },
+        ..
+      } => {
+        self.frame(self.selection); // 对准选中的实例或整个场景
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::Q),
+          ..
+        },
+        ..
+      } => {
+        self.cull_mode = self.cull_mode.next(); // 切换剔除方式，便于比较帧时间
+        println!("frustum culling: {}", self.cull_mode.name());
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::C),
+          ..
+        },
+        ..
+      } => {
+        // 冻结/解冻当前相机的视锥体
+        self.frozen_camera = match self.frozen_camera {
+          Some(_) => None,
+          None => Some(self.camera),
+        };
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::W),
+          ..
+        },
+        ..
+      } => {
+        self.wireframe.set_mode(self.wireframe.mode.next(), &self.queue); // 关闭、只显示线框、叠加线框之间循环
+        println!("wireframe: {}", self.wireframe.mode.name());
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::R),
+          ..
+        },
+        ..
+      } if !self.gizmo.is_dragging() => {
+        self.gizmo.mode = self.gizmo.mode.next(); // 平移、旋转、缩放之间循环
+        println!("gizmo: {}", self.gizmo.mode.name());
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::V),
+          ..
+        },
+        ..
+      } if !self.gizmo.is_dragging() => {
+        self.gizmo.space = match self.gizmo.space {
+          GizmoSpace::World => GizmoSpace::Local,
+          GizmoSpace::Local => GizmoSpace::World,
+        };
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::S),
+          ..
+        },
+        ..
+      } => {
+        self.gizmo.snapping = !self.gizmo.snapping;
+        println!("gizmo snapping: {} (translate {}, angle {}°, scale {})", self.gizmo.snapping, self.gizmo.translate_snap, self.gizmo.angle_snap, self.gizmo.scale_snap);
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::Key1 | VirtualKeyCode::Key2 | VirtualKeyCode::Key3 | VirtualKeyCode::Key4 | VirtualKeyCode::Key5 | VirtualKeyCode::Key6 | VirtualKeyCode::Key7)),
+          ..
+        },
+        ..
+      } => {
+        let index = match key {
+          VirtualKeyCode::Key1 => 0,
+          VirtualKeyCode::Key2 => 1,
+          VirtualKeyCode::Key3 => 2,
+          VirtualKeyCode::Key4 => 3,
+          VirtualKeyCode::Key5 => 4,
+          VirtualKeyCode::Key6 => 5,
+          _ => 6,
+        };
+        self.debug_view = DebugView::ALL[index]; // 数字键直接选择显示方式
+        println!("debug view: {}", self.debug_view.name());
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::Equals | VirtualKeyCode::Minus)),
+          ..
+        },
+        ..
+      } => {
+        let delta = if *key == VirtualKeyCode::Equals { 0.1 } else { -0.1 };
+        let intensity = self.ibl_info.uniform.intensity + delta;
+        self.ibl_info.set_intensity(intensity, &self.queue); // 调整环境光强度
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::LBracket | VirtualKeyCode::RBracket | VirtualKeyCode::Comma | VirtualKeyCode::Period)),
+          ..
+        },
+        ..
+      } => {
+        match key {
+          VirtualKeyCode::LBracket => self.material.roughness = (self.material.roughness - 0.05).max(0.0),
+          VirtualKeyCode::RBracket => self.material.roughness = (self.material.roughness + 0.05).min(1.0),
+          VirtualKeyCode::Comma => self.material.metallic = (self.material.metallic - 0.1).max(0.0),
+          _ => self.material.metallic = (self.material.metallic + 0.1).min(1.0),
+        }
+        self.material_info.update(&self.material, &self.queue); // 调整材质粗糙度与金属度
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::P | VirtualKeyCode::B)),
+          ..
+        },
+        ..
+      } => {
+        let mut settings = self.shadow_map.settings;
+        if *key == VirtualKeyCode::P {
+          settings.pcf_radius = (settings.pcf_radius + 1) % 4; // PCF半径在0~3之间循环
+        } else {
+          // 深度偏移在几组预设之间循环，便于观察阴影痤疮和漏光
+          let (depth_bias, slope_scale) = match settings.depth_bias {
+            0 => (2, 2.0),
+            2 => (8, 4.0),
+            _ => (0, 0.0),
+          };
+          settings.depth_bias = depth_bias;
+          settings.slope_scale = slope_scale;
+        }
+        self.set_shadow_settings(settings);
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::F1 | VirtualKeyCode::F2 | VirtualKeyCode::F3 | VirtualKeyCode::F4)),
+          ..
+        },
+        ..
+      } => {
+        let effect = match key {
+          VirtualKeyCode::F1 => PostEffect::Bloom,
+          VirtualKeyCode::F2 => PostEffect::Exposure,
+          VirtualKeyCode::F3 => PostEffect::Tonemap,
+          _ => PostEffect::Gamma,
+        };
+        self.post.toggle(effect);
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::F5 | VirtualKeyCode::F7)),
+          ..
+        },
+        ..
+      } => {
+        // F5开关FXAA，F7开关SMAA；关闭时回到MSAA
+        let mode = if *key == VirtualKeyCode::F5 { AntiAliasing::Fxaa } else { AntiAliasing::Smaa };
+        self.set_anti_aliasing(if self.anti_aliasing() == mode { AntiAliasing::Msaa } else { mode });
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::F6),
+          ..
+        },
+        ..
+      } => {
+        if let Some(ui) = &mut self.ui {
+          ui.visible = !ui.visible;
+        }
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::T | VirtualKeyCode::PageDown | VirtualKeyCode::PageUp | VirtualKeyCode::Insert | VirtualKeyCode::Delete | VirtualKeyCode::Home | VirtualKeyCode::End)),
+          ..
+        },
+        ..
+      } => {
+        let settings = match key {
+          VirtualKeyCode::T => PostSettings {
+            tonemap: if self.post.settings.tonemap == Tonemap::Aces { Tonemap::Reinhard } else { Tonemap::Aces },
+            ..self.post.settings
+          },
+          VirtualKeyCode::Home => PostSettings { bloom_threshold: (self.post.settings.bloom_threshold - 0.25).max(0.0), ..self.post.settings },
+          VirtualKeyCode::End => PostSettings { bloom_threshold: self.post.settings.bloom_threshold + 0.25, ..self.post.settings },
+          VirtualKeyCode::Delete => PostSettings { bloom_intensity: (self.post.settings.bloom_intensity - 0.01).max(0.0), ..self.post.settings },
+          VirtualKeyCode::Insert => PostSettings { bloom_intensity: self.post.settings.bloom_intensity + 0.01, ..self.post.settings },
+          VirtualKeyCode::PageDown => PostSettings { exposure: self.post.settings.exposure / 1.25, ..self.post.settings },
+          _ => PostSettings { exposure: self.post.settings.exposure * 1.25, ..self.post.settings },
+        };
+        self.post.set_settings(settings, &self.queue);
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::M),
+          ..
+        },
+        ..
+      } => {
+        // 在1x和4x之间切换
+        let index = SAMPLE_COUNTS.iter().position(|count| *count == self.sample_count).unwrap_or(0);
+        let sample_count = SAMPLE_COUNTS[(index + 1) % SAMPLE_COUNTS.len()];
+        self.set_sample_count(sample_count);
+        if sample_count > 1 {
+          self.post.set_anti_aliasing(AntiAliasing::Msaa); // MSAA与后处理抗锯齿二选一
+        }
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::O | VirtualKeyCode::I | VirtualKeyCode::U | VirtualKeyCode::Y | VirtualKeyCode::H | VirtualKeyCode::J)),
+          ..
+        },
+        ..
+      } => {
+        let mut settings = self.ssao.settings;
+        match key {
+          VirtualKeyCode::O => settings.enabled = !settings.enabled,
+          VirtualKeyCode::I => settings.sample_count = (settings.sample_count * 2) % (ssao::MAX_SAMPLES as u32 * 2), // 8、16、32、64之间循环
+          VirtualKeyCode::U => settings.radius = (settings.radius - 0.1).max(0.1),
+          VirtualKeyCode::Y => settings.radius += 0.1,
+          VirtualKeyCode::H => settings.bias = (settings.bias - 0.005).max(0.0),
+          _ => settings.bias += 0.005,
+        }
+        settings.sample_count = settings.sample_count.max(8);
+        self.ssao.update(settings, &self.camera, &self.queue);
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(VirtualKeyCode::N),
+          ..
+        },
+        ..
+      } => {
+        // 在MSAA、FXAA、SMAA和关闭之间切换抗锯齿方式
+        self.set_anti_aliasing(self.anti_aliasing().next());
+        true
+      },
+      WindowEvent::KeyboardInput {
+        input: KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key @ (VirtualKeyCode::L | VirtualKeyCode::K)),
+          ..
+        },
+        ..
+      } => {
+        if *key == VirtualKeyCode::L {
+          // 新的点光源沿圆周排布，颜色随序号变化
+          let index = self.point_lights.len() as f32;
+          let angle = index * 2.4;
+          let color = [0.5 + 0.5 * angle.cos(), 0.5 + 0.5 * (angle + 2.1).cos(), 0.5 + 0.5 * (angle + 4.2).cos()];
+          self.point_lights.push(PointLight::new((3.0 * angle.cos(), 1.0, 3.0 * angle.sin()).into(), color, 8.0, 5.0));
+        } else {
+          self.point_lights.pop();
+        }
+        println!("point lights: {}", self.point_lights.len());
+        self.shadow_map.update_point_lights(&self.point_lights, &self.queue);
+        self.light_info.update(&self.lights, &self.point_lights, &self.shadow_map, &self.device, &self.queue);
+        true
+      },
+      _ => {
+        let camera_state = self.camera_control(event);
+        if camera_state {
+          println!("update camera");
+          self.update_camera();
+        }
+        camera_state
+      }
+    }
+  }
+
+  /// 修改多重采样数，重建渲染管线和渲染目标
+  /// 修改阴影参数（重建阴影管线）并更新光源数据
+  fn set_shadow_settings(&mut self, settings: ShadowSettings) {
+    self.shadow_map.set_settings(settings, &self.device);
+    self.light_info.update(&self.lights, &self.point_lights, &self.shadow_map, &self.device, &self.queue);
+  }
+
+  fn set_sample_count(&mut self, sample_count: u32) {
+    if !SAMPLE_COUNTS.contains(&sample_count) {
+      eprintln!("unsupported sample count: {}", sample_count);
+      return;
+    }
+    println!("sample count: {}", sample_count);
+    self.sample_count = sample_count;
+    let (render_pipeline, depth_prepass_pipeline) = create_render_pipelines(&self.device, &self.render_pipeline_layout, texture::Texture::HDR_FORMAT, sample_count, self.depth_format);
+    self.render_pipeline = render_pipeline;
+    self.debug_views.set_sample_count(&self.camera_info.layout, sample_count, self.depth_format, &self.device);
+    self.wireframe.set_sample_count(&self.camera_info.layout, sample_count, self.depth_format, &self.device);
+    self.debug_draw.set_sample_count(&self.camera_info.layout, sample_count, self.depth_format, &self.device);
+    self.gizmo_draw.set_sample_count(&self.camera_info.layout, sample_count, self.depth_format, &self.device);
+    self.grid.set_sample_count(sample_count, self.depth_format, &self.device);
+    if let Some(outline) = &mut self.outline {
+      outline.set_sample_count(&self.camera_info.layout, sample_count, self.depth_format, &self.device);
+    }
+    self.depth_prepass_pipeline = depth_prepass_pipeline;
+    self.resize(self.size);
+  }
+
+  /// 当前的抗锯齿方式
+  fn anti_aliasing(&self) -> AntiAliasing {
+    self.post.anti_aliasing().unwrap_or(if self.sample_count > 1 { AntiAliasing::Msaa } else { AntiAliasing::Off })
+  }
+
+  /// MSAA使用4x，其他方式关闭MSAA
+  fn set_anti_aliasing(&mut self, mode: AntiAliasing) {
+    self.post.set_anti_aliasing(mode);
+    let sample_count = if mode == AntiAliasing::Msaa { 4 } else { 1 };
+    if sample_count != self.sample_count {
+      self.set_sample_count(sample_count);
+    }
+    println!("anti-aliasing: {}", mode.name());
+  }
+
+  /// `dt`为距上一帧的时间
+  fn update(&mut self, dt: std::time::Duration) {
+    self.stats.frame(dt);
+    if let Some(profiler) = &mut self.profiler {
+      profiler.poll(&self.device);
+    }
+    self.gpu_culling.poll(&self.device);
+    if self.cull_mode == CullMode::Cpu {
+      self.cull_instances();
+    }
+    if self.stats_timer.elapsed() >= std::time::Duration::from_secs(1) {
+      let times = self.stats.times();
+      let draws = self.stats.last;
+      println!(
+        "{:.2} ms/frame ({:.0} fps, min {:.2}, max {:.2}, p95 {:.2}, p99 {:.2}), {} draw calls, {} triangles",
+        times.avg,
+        times.fps(),
+        times.min,
+        times.max,
+        times.p95,
+        times.p99,
+        draws.draw_calls,
+        draws.triangles
+      );
+      match &self.profiler {
+        Some(profiler) => println!(
+          "GPU {:.3} ms: {}",
+          profiler.total(),
+          profiler.timings().iter().map(|(label, ms)| format!("{} {:.3}", label, ms)).collect::<Vec<_>>().join(", ")
+        ),
+        None => println!("GPU timings unavailable"),
+      }
+      match self.cull_mode {
+        CullMode::Cpu => println!(
+          "instances: {} visible, {} culled, cull {:.3} ms",
+          self.cull_stats.visible,
+          self.cull_stats.culled,
+          self.cull_stats.time.as_secs_f64() * 1000.0
+        ),
+        CullMode::Gpu => match self.gpu_culling.visible() {
+          Some(visible) => println!("instances: {} visible, culling: gpu", visible),
+          None => println!("instances: {}, culling: gpu, visible count unavailable", self.instances.len()),
+        },
+        CullMode::Off => println!("instances: {}, culling: {}", self.instances.len(), self.cull_mode.name()),
+      }
+      self.stats_timer = std::time::Instant::now();
+    }
+    self.picking.poll(&self.device);
+    if let Some((x, y)) = self.pick_cursor {
+      let result = self.picking.pick(x, y);
+      if !self.picking.is_pending() {
+        self.pick_cursor = None;
+        if let Some(result) = result {
+          println!("picked mesh {} instance {} at {:?}", result.mesh_id, result.instance, result.position);
+          self.selection = match result.mesh_id {
+            SPHERE_MESH_ID => Some(result.instance as usize),
+            _ => None, // 地面和模型不可选中
+          };
+        }
+      }
+    }
+    if self.show_helpers {
+      self.draw_helpers();
+    }
+    for point in &self.measure_points {
+      self.debug_draw.sphere(*point, 0.05, [0.0, 1.0, 1.0, 1.0]);
+    }
+    if let [start, end] = self.measure_points[..] {
+      self.debug_draw.line(start, end, [0.0, 1.0, 1.0, 1.0]);
+    }
+    if let Some(index) = self.selection {
+      let bounds = self.instance_bounds(index);
+      self.debug_draw.aabb(&bounds, [1.0, 1.0, 1.0, 1.0]); // 高亮选中的实例
+      self.gizmo.draw(&mut self.gizmo_draw, &self.instances[index], &self.camera);
+    }
+  }
+
+  /// 收集本帧的辅助线：实例坐标轴与包围盒、光源位置与范围、冻结的相机视锥体
+  fn draw_helpers(&mut self) {
+    for index in 0..self.instances.len() {
+      let model_matrix = cgmath::Matrix4::from(self.instance_data[index].model_matrix);
+      let bounds = self.instance_bounds(index);
+      self.debug_draw.axes(&model_matrix, 0.4);
+      self.debug_draw.aabb(&bounds, [1.0, 1.0, 0.0, 0.5]);
+    }
+    self.debug_draw.aabb(&self.ground.bounds, [1.0, 1.0, 0.0, 0.5]);
+    for bounds in self.model_bounds().collect::<Vec<_>>() {
+      self.debug_draw.aabb(&bounds, [1.0, 1.0, 0.0, 0.5]);
+    }
+    for light in &self.lights {
+      let color = [light.color[0], light.color[1], light.color[2], 1.0];
+      match light.kind {
+        LightKind::Directional => self.debug_draw.line(cgmath::Point3::origin() - light.direction.normalize() * 5.0, cgmath::Point3::origin(), color),
+        LightKind::Spot => self.debug_draw.frustum_from_matrix(&light.get_view_projection_matrix(), color),
+      }
+    }
+    for point_light in &self.point_lights {
+      let color = [point_light.color[0], point_light.color[1], point_light.color[2], 1.0];
+      self.debug_draw.sphere(point_light.position, 0.1, color);
+      self.debug_draw.sphere(point_light.position, point_light.radius, [color[0], color[1], color[2], 0.2]); // 光照范围
+    }
+    if let Some(camera) = &self.frozen_camera {
+      self.debug_draw.frustum(camera, [1.0, 1.0, 1.0, 1.0]);
+    }
+  }
+
+  /// 用相机视锥体剔除实例，将可见实例紧凑地写入`visible_instance_buffer`
+  fn cull_instances(&mut self) {
+    let start = std::time::Instant::now();
+    let frustum = Frustum::from_matrix(&self.camera.get_view_projection_matrix());
+    let visible_data = self.instance_data.iter()
+      .zip(&self.instance_spheres)
+      .filter(|(_, sphere)| frustum.intersects_sphere(sphere))
+      .map(|(data, _)| *data)
+      .collect::<Vec<_>>();
+    self.queue.write_buffer(&self.visible_instance_buffer, 0, bytemuck::cast_slice(&visible_data));
+    self.cull_stats = CullStats {
+      visible: visible_data.len() as u32,
+      culled: (self.instance_data.len() - visible_data.len()) as u32,
+      time: start.elapsed(),
+    };
+  }
+
+  /// 实例缓冲及实例数；`culled`为true时使用CPU剔除后的可见实例（相机视角的pass）
+  ///
+  /// GPU剔除的实例数只在GPU上，需由`draw_scene`间接绘制，这里返回全部实例
+  fn instance_buffer(&self, culled: bool) -> (&wgpu::Buffer, std::ops::Range<u32>) {
+    if culled && self.cull_mode == CullMode::Cpu {
+      (&self.visible_instance_buffer, 0..self.cull_stats.visible)
+    } else {
+      (&self.instance_buffer, 0..(self.instances.len() as u32))
+    }
+  }
+
+  /// 绘制场景中的所有网格；`with_material`为false时不绑定材质（如阴影pass）
+  fn draw_scene<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, with_material: bool, culled: bool) {
+    let (instance_buffer, instances) = self.instance_buffer(culled);
+    if with_material {
+      render_pass.set_bind_group(0, &self.material_info.group, &[]); // 绑定到group中
+    }
+    render_pass.set_vertex_buffer(0, self.mesh.vertex_buffer.slice(..));
+    render_pass.set_index_buffer(self.mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16); // 指定索引缓冲
+    if culled && self.cull_mode == CullMode::Gpu {
+      render_pass.set_vertex_buffer(1, self.gpu_culling.visible_buffer.slice(..));
+      render_pass.draw_indexed_indirect(&self.gpu_culling.args_buffer, 0); // 实例数由剔除计算着色器写入
+    } else {
+      render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+      render_pass.draw_indexed(0..self.mesh.index_num, 0, instances); // 指定顶点数和实例数
+    }
+    if with_material {
+      render_pass.set_bind_group(0, &self.ground_material_info.group, &[]);
+    }
+    render_pass.set_vertex_buffer(0, self.ground.vertex_buffer.slice(..));
+    render_pass.set_vertex_buffer(1, self.ground_instance_buffer.slice(..));
+    render_pass.set_index_buffer(self.ground.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+    render_pass.draw_indexed(0..self.ground.index_num, 0, 0..1);
+    render_pass.set_vertex_buffer(1, self.model_instance_buffer.slice(..));
+    for part in &self.model {
+      if with_material {
+        render_pass.set_bind_group(0, &part.material_info.group, &[]);
+      }
+      render_pass.set_vertex_buffer(0, part.mesh.vertex_buffer.slice(..));
+      render_pass.set_index_buffer(part.mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+      render_pass.draw_indexed(0..part.mesh.index_num, 0, 0..1);
+    }
+  }
+
+  /// 统计`passes`次`draw_scene`提交的绘制；GPU剔除时用几帧前读回的可见实例数，尚未读回时不计
+  fn count_scene_draws(&mut self, culled: bool, passes: u32) {
+    let instances = if culled && self.cull_mode == CullMode::Gpu {
+      self.gpu_culling.visible().unwrap_or(0)
+    } else {
+      self.instance_buffer(culled).1.len() as u32
+    };
+    for _ in 0..passes {
+      self.stats.draw(self.mesh.index_num, instances);
+      self.stats.draw(self.ground.index_num, 1);
+      for part in &self.model {
+        self.stats.draw(part.mesh.index_num, 1);
+      }
+    }
+  }
+
+  /// 在`label`阶段结束处写入GPU时间戳
+  fn profile(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) {
+    if let Some(profiler) = &mut self.profiler {
+      profiler.mark(encoder, label);
+    }
+  }
+
+  /// 深度缓冲带模板时清空模板
+  fn stencil_ops(&self) -> Option<wgpu::Operations<u32>> {
+    self.outline.as_ref().map(|_| wgpu::Operations {
+      load: wgpu::LoadOp::Clear(0),
+      store: true
+    })
+  }
+
+  fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    let output = self.surface.as_ref().expect("render needs a window surface").get_current_texture()?;
+    let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+    self.render_to(&view);
+    output.present();
+    Ok(())
+  }
+
+  /// 无窗口模式：渲染一帧到与交换链格式相同的离屏纹理
+  fn render_offscreen(&mut self) -> texture::Texture {
+    let target = texture::Texture::create_readback_target(&self.device, self.config.width, self.config.height, self.config.format, "offscreen_target");
+    self.render_to(&target.view);
+    target
+  }
+
+  /// 将`render_offscreen`的结果读回并保存为PNG
+  fn save_png(&self, target: &texture::Texture, path: &std::path::Path) -> anyhow::Result<()> {
+    let (width, height) = (self.config.width, self.config.height);
+    let row = width * 4;
+    let padded_row = row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = stats::create_buffer(&self.device, &wgpu::BufferDescriptor {
+      label: Some("Offscreen Readback Buffer"),
+      size: (padded_row * height) as wgpu::BufferAddress,
+      usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+      mapped_at_creation: false,
+    });
+    let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+      label: Some("Offscreen Readback Encoder")
+    });
+    encoder.copy_texture_to_buffer(
+      wgpu::ImageCopyTexture {
+        texture: &target.texture,
+        mip_level: 0,
+        origin: wgpu::Origin3d::ZERO,
+        aspect: wgpu::TextureAspect::All,
+      },
+      wgpu::ImageCopyBuffer {
+        buffer: &buffer,
+        layout: wgpu::ImageDataLayout {
+          offset: 0,
+          bytes_per_row: std::num::NonZeroU32::new(padded_row),
+          rows_per_image: std::num::NonZeroU32::new(height),
+        },
+      },
+      wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+      },
+    );
+    self.queue.submit(std::iter::once(encoder.finish()));
+    let slice = buffer.slice(..);
+    let mapping = slice.map_async(wgpu::MapMode::Read);
+    self.device.poll(wgpu::Maintain::Wait);
+    pollster::block_on(mapping)?;
+    let pixels = slice.get_mapped_range()
+      .chunks(padded_row as usize)
+      .flat_map(|padded| padded[..row as usize].to_vec())
+      .collect::<Vec<_>>();
+    buffer.unmap();
+    image::save_buffer(path, &pixels, width, height, image::ColorType::Rgba8)?;
+    Ok(())
+  }
+
+  /// 录制并提交一帧，结果输出到`view`（交换链或离屏纹理）
+  fn render_to(&mut self, view: &wgpu::TextureView) {
+    self.debug_draw.prepare(&self.device, &self.queue); // 上传update中收集的辅助线
+    self.gizmo_draw.prepare(&self.device, &self.queue);
+    let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+      label: Some("Render Encoder")
+    });
+    if let Some(profiler) = &mut self.profiler {
+      profiler.begin_frame(&mut encoder);
+    }
+    for layer in 0..self.lights.len().min(light::MAX_LIGHTS) {
+      let mut shadow_pass = self.shadow_map.begin_pass(&mut encoder, layer); // 从光源视角渲染阴影贴图
+      self.draw_scene(&mut shadow_pass, false, false); // 视野外的物体也可能投射阴影
+    }
+    let shadow_casters = light::shadow_casters(&self.point_lights).count();
+    for shadow_index in 0..shadow_casters {
+      for face in 0..6 {
+        let mut shadow_pass = self.shadow_map.begin_point_pass(&mut encoder, shadow_index, face); // 点光源立方体阴影的六个面
+        self.draw_scene(&mut shadow_pass, false, false); // 视野外的物体也可能投射阴影
+      }
+    }
+    self.count_scene_draws(false, (self.lights.len().min(light::MAX_LIGHTS) + shadow_casters * 6) as u32);
+    self.profile(&mut encoder, "shadow");
+    if self.cull_mode == CullMode::Gpu {
+      let frustum = Frustum::from_matrix(&self.camera.get_view_projection_matrix());
+      self.gpu_culling.run(&mut encoder, &frustum, &self.queue); // 在相机视角的pass之前生成可见实例和间接绘制参数
+      self.profile(&mut encoder, "culling");
+    }
+    // 只显示线框时也需要预pass的深度来隐藏被遮挡的边
+    let wireframe_only = self.wireframe.mode == WireframeMode::Wireframe;
+    let depth_prepass = self.ssao.settings.enabled || wireframe_only;
+    if depth_prepass {
+      let mut prepass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Depth Prepass"),
+        color_attachments: &[],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+          view: &self.depth_texture.view,
+          depth_ops: Some(wgpu::Operations {
+            load: wgpu::LoadOp::Clear(1.0),
+            store: true
+          }),
+          stencil_ops: self.stencil_ops()
+        })
+      });
+      prepass.set_pipeline(&self.depth_prepass_pipeline);
+      prepass.set_bind_group(1, &self.camera_info.group, &[]);
+      prepass.set_bind_group(2, &self.ibl_info.group, &[]);
+      prepass.set_bind_group(3, &self.light_info.group, &[]);
+      prepass.set_bind_group(4, &self.ssao.occlusion_group, &[]);
+      self.draw_scene(&mut prepass, true, true);
+    }
+    if depth_prepass {
+      self.profile(&mut encoder, "depth prepass");
+    }
+    self.ssao.run(&mut encoder); // 由预pass的深度计算环境光遮蔽
+    if self.ssao.settings.enabled {
+      self.profile(&mut encoder, "ssao");
+    }
+    let scene_view = self.post.scene_view();
+    {
+      let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[wgpu::RenderPassColorAttachment {
+          view: self.msaa_texture.as_ref().map_or(scene_view, |msaa| &msaa.view), // 开启MSAA时先渲染到多重采样纹理，再resolve到离屏HDR纹理
+          resolve_target: self.msaa_texture.as_ref().map(|_| scene_view),
+          ops: wgpu::Operations {
+            load: wgpu::LoadOp::Clear(if self.debug_view == DebugView::Lit && !wireframe_only { self.background } else { wgpu::Color::BLACK }), // 调试视图和线框使用黑色背景
+            store: true,
+          }
+        }],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+          view: &self.depth_texture.view,
+          depth_ops: Some(wgpu::Operations {
+            load: if depth_prepass { wgpu::LoadOp::Load } else { wgpu::LoadOp::Clear(1.0) }, // 沿用预pass的深度
+            store: true
+          }),
+          stencil_ops: self.stencil_ops() // 每帧清空选中标记
+        }) // 深度纹理配置
+      });
+      if wireframe_only {
+        // 不绘制表面，只绘制线框
+      } else if let Some(pipeline) = self.debug_views.pipeline(self.debug_view) {
+        render_pass.set_pipeline(pipeline); // 调试视图只需调试参数和相机
+        render_pass.set_bind_group(0, &self.debug_views.group, &[]);
+        render_pass.set_bind_group(1, &self.camera_info.group, &[]);
+        // 实例序号视图使用未压缩的实例缓冲，`instance_index`即实例本身的序号，颜色不随剔除结果变化
+        self.draw_scene(&mut render_pass, false, self.debug_view != DebugView::InstanceId);
+      } else {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(1, &self.camera_info.group, &[]);
+        render_pass.set_bind_group(2, &self.ibl_info.group, &[]);
+        render_pass.set_bind_group(3, &self.light_info.group, &[]);
+        render_pass.set_bind_group(4, &self.ssao.occlusion_group, &[]);
+        self.draw_scene(&mut render_pass, true, true);
+      }
+      if self.wireframe.mode != WireframeMode::Off {
+        self.wireframe.begin(&mut render_pass, &self.camera_info.group);
+        let (instance_buffer, instances) = self.instance_buffer(true);
+        self.wireframe.draw(&mut render_pass, &self.mesh, instance_buffer, instances);
+        self.wireframe.draw(&mut render_pass, &self.ground, &self.ground_instance_buffer, 0..1);
+        for part in &self.model {
+          self.wireframe.draw(&mut render_pass, &part.mesh, &self.model_instance_buffer, 0..1);
+        }
+      }
+      self.debug_draw.draw(&mut render_pass, &self.camera_info.group);
+      self.grid.draw(&mut render_pass, self.size);
+      if let (Some(outline), Some(index)) = (&self.outline, self.selection) {
+        outline.draw(&mut render_pass, &self.camera_info.group, &self.mesh, &self.instance_buffer, index as u32);
+      }
+      self.gizmo_draw.draw(&mut render_pass, &self.camera_info.group); // 最后绘制，手柄位于最上层
+    }
+    self.profile(&mut encoder, "scene");
+    self.count_scene_draws(true, depth_prepass as u32);
+    self.count_scene_draws(self.debug_view != DebugView::InstanceId, !wireframe_only as u32);
+    if self.wireframe.mode != WireframeMode::Off {
+      let instances = self.instance_buffer(true).1.len() as u32;
+      self.stats.draw(self.mesh.index_num, instances);
+      self.stats.draw(self.ground.index_num, 1);
+      for part in &self.model {
+        self.stats.draw(part.mesh.index_num, 1);
+      }
+    }
+    if self.outline.is_some() && self.selection.is_some() {
+      self.stats.draw(self.mesh.index_num, 1); // 模板与轮廓各一次
+      self.stats.draw(self.mesh.index_num, 1);
+    }
+    if self.debug_view == DebugView::Lit {
+      self.post.run(&mut encoder, view); // 后处理并输出到交换链
+    } else {
+      self.post.blit(&mut encoder, view); // 调试视图直接输出原始值
+    }
+    self.profile(&mut encoder, "post");
+    if let Some(ui) = &mut self.ui {
+      ui.render(&mut encoder, view, &self.config, &self.device, &self.queue); // 界面绘制在最上层，不经过后处理
+    }
+    self.profile(&mut encoder, "ui");
+    // 拾取使用完整的实例缓冲，使实例序号与`instances`一致
+    let mut pick_meshes = vec![
+      (SPHERE_MESH_ID, &self.mesh, &*self.instance_buffer, 0..(self.instances.len() as u32)),
+      (GROUND_MESH_ID, &self.ground, &*self.ground_instance_buffer, 0..1),
+    ];
+    pick_meshes.extend(self.model.iter().map(|part| (MODEL_MESH_ID, &part.mesh, &*self.model_instance_buffer, 0..1)));
+    self.picking.record(&mut encoder, &self.camera, &self.camera_info.group, &pick_meshes);
+    self.profile(&mut encoder, "picking");
+    if let Some(profiler) = &mut self.profiler {
+      profiler.end_frame(&mut encoder);
+    }
+
+    self.queue.submit(std::iter::once(encoder.finish()));
+    self.picking.after_submit(); // 提交后才能映射读回缓冲
+    self.gpu_culling.after_submit();
+    if let Some(profiler) = &mut self.profiler {
+      profiler.after_submit();
+    }
+  }
+}
+
+/// 按`options`选择能显示到`surface`的适配器（无窗口时不检查），找不到时列出可用的适配器
+async fn select_adapter(instance: &wgpu::Instance, surface: Option<&wgpu::Surface>, options: &Options) -> anyhow::Result<wgpu::Adapter> {
+  let adapter = match &options.adapter_name {
+    Some(name) => {
+      let name = name.to_lowercase();
+      instance.enumerate_adapters(options.backends)
+        .find(|adapter| adapter.get_info().name.to_lowercase().contains(&name) && surface.is_none_or(|surface| adapter.is_surface_supported(surface)))
+    },
+    None => instance.request_adapter(&wgpu::RequestAdapterOptions {
+      power_preference: options.power_preference,
+      compatible_surface: surface,
+      force_fallback_adapter: options.fallback_adapter,
+    }).await,
+  };
+  adapter.ok_or_else(|| {
+    let available = instance.enumerate_adapters(wgpu::Backends::all())
+      .map(|adapter| {
+        let info = adapter.get_info();
+        format!("\n  {} ({:?}, {:?})", info.name, info.backend, info.device_type)
+      })
+      .collect::<String>();
+    anyhow::anyhow!(
+      "no suitable adapter for backends {:?}{}{}; available adapters:{}\ntry --backend, --adapter or --fallback-adapter",
+      options.backends,
+      options.adapter_name.as_ref().map(|name| format!(" named \"{}\"", name)).unwrap_or_default(),
+      if options.fallback_adapter { " (fallback adapter only)" } else { "" },
+      if available.is_empty() { "\n  (none)".to_string() } else { available }
+    )
+  })
+}
+
+/// 列出`backends`中所有适配器的信息、特性和限制
+fn list_adapters(backends: wgpu::Backends) {
+  let instance = wgpu::Instance::new(backends);
+  let mut count = 0;
+  for adapter in instance.enumerate_adapters(backends) {
+    let info = adapter.get_info();
+    println!("{} ({:?}, {:?}, vendor {:#06x}, device {:#06x})", info.name, info.backend, info.device_type, info.vendor, info.device);
+    println!("  features: {:?}", adapter.features());
+    println!("  limits: {:#?}", adapter.limits());
+    count += 1;
+  }
+  if count == 0 {
+    println!("no adapters found for backends {:?}", backends);
+  }
+}
+
+/// 将运行统计写入`path`，未指定时输出到标准输出
+fn write_stats(stats: &FrameStats, path: Option<&str>) {
+  let json = stats.to_json();
+  match path {
+    Some(path) => match std::fs::write(path, json) {
+      Ok(_) => println!("frame stats written to {}", path),
+      Err(error) => eprintln!("failed to write frame stats to {}: {}", path, error),
+    },
+    None => println!("{}", json),
+  }
+}
+
+/// `--view all`时各显示方式的输出文件：在`path`的文件名后加上`-<view>`
+fn view_path(path: &std::path::Path, view: DebugView) -> PathBuf {
+  let stem = path.file_stem().map_or_else(|| "frame".into(), |stem| stem.to_string_lossy());
+  let extension = path.extension().map_or_else(|| "png".into(), |extension| extension.to_string_lossy());
+  path.with_file_name(format!("{}-{}.{}", stem, view.name(), extension))
+}
+
+/// 无窗口模式：以固定的帧间隔渲染`frames`帧，将最后一帧保存为PNG
+///
+/// `--view all`时最后一帧的场景状态分别以每种显示方式渲染并保存
+fn run_headless(options: Options, path: &std::path::Path) -> anyhow::Result<()> {
+  let frames = options.frames;
+  let stats_path = options.stats_path.clone();
+  let views = if options.all_views { DebugView::ALL.to_vec() } else { vec![options.debug_view] };
+  let all_views = options.all_views;
+  let mut state = pollster::block_on(State::new(None, options))?;
+  for frame in 0..frames {
+    state.update(std::time::Duration::from_secs_f64(1.0 / 60.0));
+    if frame + 1 < frames {
+      state.render_offscreen();
+    }
+  }
+  for view in views {
+    state.debug_view = view;
+    let target = state.render_offscreen();
+    let path = if all_views { view_path(path, view) } else { path.to_path_buf() };
+    state.save_png(&target, &path)?;
+    println!("{} view written to {}", view.name(), path.display());
+  }
+  write_stats(&state.stats, stats_path.as_deref());
+  Ok(())
+}
+
+fn main() {
+  env_logger::init();
+  let options = Options::from_args();
+  if options.list_adapters {
+    list_adapters(options.backends);
+    return;
+  }
+  if let Some(path) = options.headless.clone() {
+    if let Err(error) = run_headless(options, &path) {
+      eprintln!("{}", error);
+      std::process::exit(1);
+    }
+    return;
+  }
+  if options.all_views {
+    eprintln!("--view all only applies with --headless, starting with the {} view", options.debug_view.name());
+  }
+  let event_loop = EventLoop::new();
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+  let stats_path = options.stats_path.clone();
+  let mut state = match pollster::block_on(State::new(Some(&window), options)) {
+    Ok(state) => state,
+    Err(error) => {
+      eprintln!("{}", error);
+      std::process::exit(1);
+    },
+  };
+  let mut last_frame = std::time::Instant::now();
+
+  event_loop.run(move |event, _, control_flow| match event {
+    Event::WindowEvent {
+      ref event,
+      window_id,
+    } if window_id == window.id() && !state.input(event) => {
+      match event {
+        WindowEvent::CloseRequested
+        | WindowEvent::KeyboardInput {
+          input:
+            KeyboardInput {
+              state: ElementState::Pressed,
+              virtual_keycode: Some(VirtualKeyCode::Escape),
+              ..
+            },
+          ..
+        } => *control_flow = ControlFlow::Exit,
+        WindowEvent::Resized(new_size) => {
+          state.resize(*new_size);
+        },
+        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+          state.resize(**new_inner_size); // mut再解引用就是正常的变量了？
+        },
+        _ => {}
+      }
+    },
+    Event::RedrawRequested(window_id) if window_id == window.id() => {
+      let now = std::time::Instant::now();
+      state.update(now - last_frame);
+      last_frame = now;
+      state.build_ui(&window);
+      match state.render() {
+        Ok(_) => {},
+        Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
+        Err(wgpu::SurfaceError::OutOfMemory) => {
+          *control_flow = ControlFlow::Exit;
+        },
+        Err(e) => eprintln!("{:?}", e)
+      }
+    },
+    Event::MainEventsCleared => {
+      window.request_redraw();
+    },
+    Event::LoopDestroyed => {
+      write_stats(&state.stats, stats_path.as_deref());
     },
     _ => {}
   });
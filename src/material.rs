@@ -0,0 +1,250 @@
+use crate::texture::Texture;
+use crate::stats;
+
+/// PBR金属度-粗糙度材质
+///
+/// 贴图均为可选，缺省时使用不影响结果的1x1纹理（白色或平坦法线）；
+/// 最终参数为系数与贴图采样值的乘积，与glTF的约定一致；
+pub struct Material {
+  /// 基础颜色（线性空间），alpha为不透明度
+  pub base_color: [f32; 4],
+  pub base_color_texture: Option<Texture>,
+  pub metallic: f32,
+  pub roughness: f32,
+  /// 金属度-粗糙度贴图：G通道为粗糙度，B通道为金属度（glTF约定）
+  pub metallic_roughness_texture: Option<Texture>,
+  /// 切线空间法线贴图（需为线性格式）
+  pub normal_texture: Option<Texture>,
+  pub normal_scale: f32,
+  /// 环境光遮蔽贴图，使用R通道
+  pub occlusion_texture: Option<Texture>,
+  pub occlusion_strength: f32,
+  /// 自发光颜色（线性空间）
+  pub emissive: [f32; 3],
+  pub emissive_texture: Option<Texture>,
+}
+
+/// 材质相关uniform变量
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialUniform {
+  base_color: [f32; 4],
+  /// 自发光颜色，w分量无意义
+  emissive: [f32; 4],
+  metallic: f32,
+  roughness: f32,
+  normal_scale: f32,
+  occlusion_strength: f32,
+}
+
+pub struct MaterialInfo {
+  pub uniform: MaterialUniform,
+  pub buffer: stats::Buffer,
+  pub group: wgpu::BindGroup,
+}
+
+/// 材质贴图绑定位置：(binding, 缺省颜色, 缺省贴图格式)
+const TEXTURE_SLOTS: [(u32, [u8; 4], wgpu::TextureFormat); 5] = [
+  (1, [255, 255, 255, 255], wgpu::TextureFormat::Rgba8UnormSrgb), // 基础颜色
+  (2, [255, 255, 255, 255], wgpu::TextureFormat::Rgba8Unorm), // 金属度-粗糙度
+  (3, [128, 128, 255, 255], wgpu::TextureFormat::Rgba8Unorm), // 法线（切线空间的+z）
+  (4, [255, 255, 255, 255], wgpu::TextureFormat::Rgba8Unorm), // 环境光遮蔽
+  (5, [255, 255, 255, 255], wgpu::TextureFormat::Rgba8UnormSrgb), // 自发光
+];
+
+impl Default for Material {
+  fn default() -> Self {
+    Self {
+      base_color: [1.0; 4],
+      base_color_texture: None,
+      metallic: 0.0,
+      roughness: 0.5,
+      metallic_roughness_texture: None,
+      normal_texture: None,
+      normal_scale: 1.0,
+      occlusion_texture: None,
+      occlusion_strength: 1.0,
+      emissive: [0.0; 3],
+      emissive_texture: None,
+    }
+  }
+}
+
+impl Material {
+  /// 由MTL文件中的参数构建材质
+  ///
+  /// `diffuse`为`Kd`，`shininess`为`Ns`（Blinn-Phong高光指数），`emissive`为`Ke`；
+  /// MTL没有金属度的概念，统一视为非金属；PBR管线不做透明混合，因此忽略透明度`d`/`Tr`，材质总是不透明
+  pub fn from_mtl(diffuse: [f32; 3], shininess: f32, emissive: [f32; 3]) -> Self {
+    Self {
+      base_color: [diffuse[0], diffuse[1], diffuse[2], 1.0],
+      roughness: shininess_to_roughness(shininess),
+      emissive,
+      ..Default::default()
+    }
+  }
+
+  /// 由glTF `pbrMetallicRoughness`中的系数构建材质，参数含义与glTF完全一致
+  pub fn from_gltf(base_color_factor: [f32; 4], metallic_factor: f32, roughness_factor: f32, emissive_factor: [f32; 3]) -> Self {
+    Self {
+      base_color: base_color_factor,
+      metallic: metallic_factor,
+      roughness: roughness_factor,
+      emissive: emissive_factor,
+      ..Default::default()
+    }
+  }
+
+  fn get_uniform(&self) -> MaterialUniform {
+    MaterialUniform {
+      base_color: self.base_color,
+      emissive: [self.emissive[0], self.emissive[1], self.emissive[2], 0.0],
+      metallic: self.metallic.clamp(0.0, 1.0),
+      roughness: self.roughness.clamp(0.0, 1.0),
+      normal_scale: self.normal_scale,
+      occlusion_strength: self.occlusion_strength.clamp(0.0, 1.0),
+    }
+  }
+
+  fn textures(&self) -> [Option<&Texture>; 5] {
+    [
+      self.base_color_texture.as_ref(),
+      self.metallic_roughness_texture.as_ref(),
+      self.normal_texture.as_ref(),
+      self.occlusion_texture.as_ref(),
+      self.emissive_texture.as_ref(),
+    ]
+  }
+}
+
+/// Blinn-Phong高光指数转换为GGX粗糙度
+///
+/// 两种分布的近似关系为`alpha = sqrt(2 / (n + 2))`，而`alpha = roughness^2`；
+pub fn shininess_to_roughness(shininess: f32) -> f32 {
+  let alpha = (2.0 / (shininess.max(0.0) + 2.0)).sqrt();
+  alpha.sqrt()
+}
+
+impl MaterialInfo {
+  /// 所有材质共用的bind group layout
+  pub fn create_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let mut entries = vec![
+      wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+          ty: wgpu::BufferBindingType::Uniform,
+          has_dynamic_offset: false,
+          min_binding_size: None
+        },
+        count: None
+      }
+    ];
+    for (binding, _, _) in TEXTURE_SLOTS {
+      entries.push(wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+          multisampled: false,
+          view_dimension: wgpu::TextureViewDimension::D2,
+          sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None
+      });
+    }
+    entries.push(wgpu::BindGroupLayoutEntry {
+      binding: 6,
+      visibility: wgpu::ShaderStages::FRAGMENT,
+      ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+      count: None,
+    });
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("material bind group layout"),
+      entries: &entries,
+    })
+  }
+
+  pub fn new(material: &Material, layout: &wgpu::BindGroupLayout, device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+    let uniform = material.get_uniform();
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Material buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let defaults = TEXTURE_SLOTS.iter().zip(material.textures()).map(|((_, color, format), texture)| {
+      match texture {
+        Some(_) => None,
+        None => Some(Texture::from_color(device, queue, *color, *format, "material default texture")),
+      }
+    }).collect::<Vec<_>>();
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+      address_mode_u: wgpu::AddressMode::Repeat,
+      address_mode_v: wgpu::AddressMode::Repeat,
+      address_mode_w: wgpu::AddressMode::Repeat,
+      mag_filter: wgpu::FilterMode::Linear,
+      min_filter: wgpu::FilterMode::Linear,
+      mipmap_filter: wgpu::FilterMode::Nearest,
+      ..Default::default()
+    });
+    let mut entries = vec![
+      wgpu::BindGroupEntry {
+        binding: 0,
+        resource: buffer.as_entire_binding()
+      }
+    ];
+    for (((binding, _, _), texture), default) in TEXTURE_SLOTS.iter().zip(material.textures()).zip(defaults.iter()) {
+      let texture = texture.or(default.as_ref()).unwrap();
+      entries.push(wgpu::BindGroupEntry {
+        binding: *binding,
+        resource: wgpu::BindingResource::TextureView(&texture.view)
+      });
+    }
+    entries.push(wgpu::BindGroupEntry {
+      binding: 6,
+      resource: wgpu::BindingResource::Sampler(&sampler)
+    });
+    let group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("material bind group"),
+      layout,
+      entries: &entries,
+    });
+    Self {
+      uniform,
+      buffer,
+      group,
+    }
+  }
+
+  /// 材质参数变化后更新uniform（贴图变化需要重新创建）
+  pub fn update(&mut self, material: &Material, queue: &wgpu::Queue) {
+    self.uniform = material.get_uniform();
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn shininess_to_roughness_range() {
+    // n = 0时alpha = 1，即最粗糙
+    assert_eq!(shininess_to_roughness(0.0), 1.0);
+    // n = 2时alpha = sqrt(0.5)，roughness = alpha^0.5
+    assert!((shininess_to_roughness(2.0) - 0.5f32.sqrt().sqrt()).abs() < 1e-6);
+    // Marry.mtl中的Ns 900
+    assert!((shininess_to_roughness(900.0) - 0.2170).abs() < 1e-3);
+    // 负值按0处理，高光指数越大越光滑
+    assert_eq!(shininess_to_roughness(-5.0), 1.0);
+    assert!(shininess_to_roughness(10.0) > shininess_to_roughness(100.0));
+  }
+
+  #[test]
+  fn from_mtl_maps_parameters() {
+    let material = Material::from_mtl([0.2, 0.4, 0.6], 900.0, [0.0, 0.0, 1.0]);
+    assert_eq!(material.base_color, [0.2, 0.4, 0.6, 1.0]);
+    assert_eq!(material.metallic, 0.0);
+    assert_eq!(material.roughness, shininess_to_roughness(900.0));
+    assert_eq!(material.emissive, [0.0, 0.0, 1.0]);
+  }
+}
@@ -0,0 +1,435 @@
+use std::{
+  collections::HashMap,
+  fs,
+  path::{Path, PathBuf},
+};
+use anyhow::*;
+use cgmath::InnerSpace;
+use crate::shape::{
+  BuferInfo,
+  Mesh,
+  Vertex
+};
+use crate::material::{
+  Material,
+  MaterialInfo
+};
+use crate::texture::Texture;
+
+/// MTL文件中的材质参数
+///
+/// 不读取透明度`d`/`Tr`：PBR管线不混合（`BlendState::REPLACE`），透明度不会产生效果
+#[derive(Debug, Clone, PartialEq)]
+pub struct MtlMaterial {
+  pub name: String,
+  /// `Kd`
+  pub diffuse: [f32; 3],
+  /// `Ns`
+  pub shininess: f32,
+  /// `Ke`
+  pub emissive: [f32; 3],
+  /// `map_Kd`，已相对MTL文件所在目录解析
+  pub diffuse_map: Option<PathBuf>,
+}
+
+impl MtlMaterial {
+  fn new(name: &str) -> Self {
+    Self {
+      name: name.to_string(),
+      diffuse: [0.8; 3],
+      shininess: 0.0,
+      emissive: [0.0; 3],
+      diffuse_map: None,
+    }
+  }
+}
+
+/// 使用同一材质的一部分网格；顶点数超过`u16`范围时同一材质会拆成多个部分
+pub struct ObjPart {
+  /// `usemtl`指定的材质名，未指定时为None
+  pub material: Option<String>,
+  pub buffer_info: BuferInfo,
+}
+
+/// 加载后的OBJ模型
+pub struct ObjModel {
+  pub parts: Vec<ObjPart>,
+  pub materials: Vec<MtlMaterial>,
+}
+
+/// 已上传到GPU的模型部分
+pub struct ModelPart {
+  /// 材质名，用于界面显示
+  pub name: String,
+  pub mesh: Mesh,
+  pub material: Material,
+  pub material_info: MaterialInfo,
+}
+
+impl ObjModel {
+  pub fn material(&self, name: Option<&str>) -> Option<&MtlMaterial> {
+    name.and_then(|name| self.materials.iter().find(|material| material.name == name))
+  }
+
+  /// 上传各部分网格，并由MTL参数和`map_Kd`贴图创建材质；`unindexed`为true时同时创建展开的顶点缓冲
+  pub fn upload(&self, layout: &wgpu::BindGroupLayout, unindexed: bool, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<ModelPart> {
+    self.parts.iter().map(|part| {
+      let label = part.material.as_deref().unwrap_or("Model");
+      let mut mesh = Mesh::new(device, &part.buffer_info, label);
+      if unindexed {
+        mesh.create_unindexed_buffer(device, &part.buffer_info, label);
+      }
+      let material = match self.material(part.material.as_deref()) {
+        Some(mtl) => Material {
+          base_color_texture: mtl.diffuse_map.as_deref().and_then(|path| match load_texture(path, device, queue) {
+            Result::Ok(texture) => Some(texture),
+            Err(err) => {
+              eprintln!("failed to load {}: {:?}", path.display(), err);
+              None
+            }
+          }),
+          ..Material::from_mtl(mtl.diffuse, mtl.shininess, mtl.emissive)
+        },
+        None => Material::default(),
+      };
+      let material_info = MaterialInfo::new(&material, layout, device, queue);
+      ModelPart {
+        name: label.to_string(),
+        mesh,
+        material,
+        material_info,
+      }
+    }).collect()
+  }
+}
+
+/// 加载颜色贴图（sRGB）
+fn load_texture(path: &Path, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Texture> {
+  let image = image::open(path)?.to_rgba8();
+  let label = path.file_name().map(|name| name.to_string_lossy().into_owned());
+  Ok(Texture::from_rgba8(device, queue, &image, image.dimensions(), wgpu::TextureFormat::Rgba8UnormSrgb, label.as_deref()))
+}
+
+/// 加载OBJ文件及其`mtllib`引用的MTL文件；MTL缺失时只给出警告
+pub fn load(path: &Path) -> Result<ObjModel> {
+  let source = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+  let (parts, libraries) = parse_obj(&source).with_context(|| format!("parsing {}", path.display()))?;
+  let dir = path.parent().unwrap_or_else(|| Path::new(""));
+  let mut materials = vec![];
+  for library in libraries {
+    let mtl_path = dir.join(&library);
+    match fs::read_to_string(&mtl_path) {
+      Result::Ok(source) => materials.extend(parse_mtl(&source, mtl_path.parent().unwrap_or(dir))),
+      Err(err) => eprintln!("failed to read {}: {}", mtl_path.display(), err),
+    }
+  }
+  Ok(ObjModel { parts, materials })
+}
+
+/// 解析`f`中的一个顶点引用（`v`、`v/vt`、`v//vn`或`v/vt/vn`），返回从0开始的序号
+///
+/// OBJ序号从1开始，负数表示相对当前已定义数量的倒数第几个；
+fn parse_index(token: Option<&str>, count: usize) -> Result<Option<usize>> {
+  let token = match token {
+    Some(token) if !token.is_empty() => token,
+    _ => return Ok(None),
+  };
+  let index: i64 = token.parse().with_context(|| format!("invalid index {}", token))?;
+  let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+  ensure!(resolved >= 0 && (resolved as usize) < count, "index {} out of range ({} defined)", index, count);
+  Ok(Some(resolved as usize))
+}
+
+fn parse_floats<const N: usize>(tokens: &mut std::str::SplitWhitespace) -> Result<[f32; N]> {
+  let mut values = [0.0; N];
+  for value in values.iter_mut() {
+    let token = tokens.next().context("missing component")?;
+    *value = token.parse().with_context(|| format!("invalid number {}", token))?;
+  }
+  Ok(values)
+}
+
+/// 构建中的网格部分：按（位置，uv，法线）去重顶点
+struct PartBuilder {
+  material: Option<String>,
+  vertices: Vec<Vertex>,
+  indices: Vec<u16>,
+  lookup: HashMap<(usize, Option<usize>, Option<usize>), u16>,
+  /// 没有`vn`的顶点，最后用面法线补上
+  missing_normals: Vec<bool>,
+}
+
+impl PartBuilder {
+  fn new(material: Option<String>) -> Self {
+    Self {
+      material,
+      vertices: vec![],
+      indices: vec![],
+      lookup: HashMap::new(),
+      missing_normals: vec![],
+    }
+  }
+
+  fn vertex(&mut self, key: (usize, Option<usize>, Option<usize>), positions: &[[f32; 3]], uvs: &[[f32; 2]], normals: &[[f32; 3]]) -> u16 {
+    if let Some(index) = self.lookup.get(&key) {
+      return *index;
+    }
+    let (position, uv, normal) = key;
+    let uv = uv.map_or([0.0; 2], |uv| [uvs[uv][0], 1.0 - uvs[uv][1]]); // OBJ的v轴向上，纹理坐标向下
+    let index = self.vertices.len() as u16;
+    self.vertices.push(Vertex {
+      position: positions[position],
+      color: [1.0; 3],
+      uv,
+      normal: normal.map_or([0.0; 3], |normal| normals[normal]),
+    });
+    self.missing_normals.push(normal.is_none());
+    self.lookup.insert(key, index);
+    index
+  }
+
+  /// 为缺少法线的顶点累加相邻三角形的面法线（按面积加权）
+  fn finish(mut self) -> ObjPart {
+    if self.missing_normals.iter().any(|missing| *missing) {
+      let mut accumulated = vec![cgmath::Vector3::new(0.0, 0.0, 0.0); self.vertices.len()];
+      for triangle in self.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| cgmath::Vector3::from(self.vertices[triangle[i] as usize].position));
+        let normal = (b - a).cross(c - a);
+        for index in triangle {
+          accumulated[*index as usize] += normal;
+        }
+      }
+      for (i, vertex) in self.vertices.iter_mut().enumerate() {
+        if self.missing_normals[i] && accumulated[i].magnitude2() > 0.0 {
+          vertex.normal = accumulated[i].normalize().into();
+        }
+      }
+    }
+    ObjPart {
+      material: self.material,
+      buffer_info: BuferInfo {
+        vertices: self.vertices,
+        indices: self.indices,
+      },
+    }
+  }
+}
+
+/// 解析OBJ源码，按`usemtl`分组并将多边形三角化（扇形），返回网格部分和`mtllib`引用的文件
+pub fn parse_obj(source: &str) -> Result<(Vec<ObjPart>, Vec<String>)> {
+  let mut positions = vec![];
+  let mut uvs = vec![];
+  let mut normals = vec![];
+  let mut libraries = vec![];
+  let mut finished = vec![];
+  let mut builders: Vec<PartBuilder> = vec![];
+  let mut current = None;
+  for (line_number, line) in source.lines().enumerate() {
+    let line = line.split('#').next().unwrap_or("").trim();
+    let mut tokens = line.split_whitespace();
+    let keyword = match tokens.next() {
+      Some(keyword) => keyword,
+      None => continue,
+    };
+    let result = (|| -> Result<()> {
+      match keyword {
+        "v" => positions.push(parse_floats::<3>(&mut tokens)?),
+        "vt" => uvs.push(parse_floats::<2>(&mut tokens)?),
+        "vn" => normals.push(parse_floats::<3>(&mut tokens)?),
+        "mtllib" => libraries.extend(tokens.map(str::to_string)),
+        "usemtl" => {
+          let name = tokens.next().map(str::to_string);
+          current = Some(match builders.iter().position(|builder| builder.material == name) {
+            Some(index) => index,
+            None => {
+              builders.push(PartBuilder::new(name));
+              builders.len() - 1
+            }
+          });
+        }
+        "f" => {
+          let keys = tokens.map(|token| {
+            let mut parts = token.split('/');
+            let position = parse_index(parts.next(), positions.len())?.context("face vertex without position")?;
+            let uv = parse_index(parts.next(), uvs.len())?;
+            let normal = parse_index(parts.next(), normals.len())?;
+            Ok((position, uv, normal))
+          }).collect::<Result<Vec<_>>>()?;
+          ensure!(keys.len() >= 3, "face with {} vertices", keys.len());
+          let index = *current.get_or_insert_with(|| {
+            builders.push(PartBuilder::new(None));
+            builders.len() - 1
+          });
+          // 同一面的顶点须在同一部分中，放不下时先结束当前部分
+          if builders[index].vertices.len() + keys.len() > u16::MAX as usize + 1 {
+            let material = builders[index].material.clone();
+            finished.push(std::mem::replace(&mut builders[index], PartBuilder::new(material)).finish());
+          }
+          let builder = &mut builders[index];
+          let face = keys.into_iter().map(|key| builder.vertex(key, &positions, &uvs, &normals)).collect::<Vec<_>>();
+          for i in 1..face.len() - 1 {
+            builder.indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+          }
+        }
+        _ => {} // o、g、s等不影响几何
+      }
+      Ok(())
+    })();
+    result.with_context(|| format!("line {}: {}", line_number + 1, line))?;
+  }
+  finished.extend(builders.into_iter().filter(|builder| !builder.indices.is_empty()).map(PartBuilder::finish));
+  Ok((finished, libraries))
+}
+
+/// 解析MTL源码，贴图路径相对`dir`解析；不支持的语句会被忽略
+pub fn parse_mtl(source: &str, dir: &Path) -> Vec<MtlMaterial> {
+  let mut materials: Vec<MtlMaterial> = vec![];
+  for line in source.lines() {
+    let line = line.split('#').next().unwrap_or("").trim();
+    let mut tokens = line.split_whitespace();
+    let keyword = match tokens.next() {
+      Some(keyword) => keyword,
+      None => continue,
+    };
+    if keyword == "newmtl" {
+      materials.push(MtlMaterial::new(tokens.next().unwrap_or("")));
+      continue;
+    }
+    let material = match materials.last_mut() {
+      Some(material) => material,
+      None => continue,
+    };
+    match keyword {
+      "Kd" => if let Result::Ok(color) = parse_floats::<3>(&mut tokens) { material.diffuse = color },
+      "Ke" => if let Result::Ok(color) = parse_floats::<3>(&mut tokens) { material.emissive = color },
+      "Ns" => if let Result::Ok([shininess]) = parse_floats::<1>(&mut tokens) { material.shininess = shininess },
+      // 文件名在最后，前面可能有`-bm 1.0`之类的选项
+      "map_Kd" => material.diffuse_map = tokens.last().map(|file| dir.join(file)),
+      _ => {}
+    }
+  }
+  materials
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const QUAD: &str = "
+mtllib quad.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+usemtl red
+f 1/1 2/2 3/3 4/4
+";
+
+  #[test]
+  fn quad_is_fan_triangulated_with_flipped_uv() {
+    let (parts, libraries) = parse_obj(QUAD).unwrap();
+    assert_eq!(libraries, vec!["quad.mtl".to_string()]);
+    assert_eq!(parts.len(), 1);
+    let part = &parts[0];
+    assert_eq!(part.material.as_deref(), Some("red"));
+    assert_eq!(part.buffer_info.indices, vec![0, 1, 2, 0, 2, 3]);
+    assert_eq!(part.buffer_info.vertices[3].uv, [0.0, 0.0]);
+    assert_eq!(part.buffer_info.vertices[0].uv, [0.0, 1.0]);
+    // 没有vn时使用面法线
+    assert_eq!(part.buffer_info.vertices[0].normal, [0.0, 0.0, 1.0]);
+  }
+
+  #[test]
+  fn negative_indices_and_shared_vertices() {
+    let source = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vn 0 0 1
+f -3//1 -2//1 -1//1
+f 1//1 2//1 3//1
+";
+    let (parts, _) = parse_obj(source).unwrap();
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].material, None);
+    assert_eq!(parts[0].buffer_info.vertices.len(), 3);
+    assert_eq!(parts[0].buffer_info.indices, vec![0, 1, 2, 0, 1, 2]);
+  }
+
+  #[test]
+  fn groups_by_material() {
+    let source = "
+v 0 0 0
+v 1 0 0
+v 0 1 0
+usemtl a
+f 1 2 3
+usemtl b
+f 1 2 3
+usemtl a
+f 3 2 1
+";
+    let (parts, _) = parse_obj(source).unwrap();
+    let materials = parts.iter().map(|part| part.material.clone().unwrap()).collect::<Vec<_>>();
+    assert_eq!(materials, vec!["a", "b"]);
+    assert_eq!(parts[0].buffer_info.indices.len(), 6);
+  }
+
+  #[test]
+  fn splits_parts_beyond_u16() {
+    let mut source = String::new();
+    let triangles = 30000;
+    for i in 0..triangles * 3 {
+      source.push_str(&format!("v {} 0 0\n", i));
+    }
+    for i in 0..triangles {
+      source.push_str(&format!("f {} {} {}\n", i * 3 + 1, i * 3 + 2, i * 3 + 3));
+    }
+    let (parts, _) = parse_obj(&source).unwrap();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts.iter().map(|part| part.buffer_info.indices.len()).sum::<usize>(), triangles * 3);
+    assert!(parts.iter().all(|part| part.buffer_info.vertices.len() <= u16::MAX as usize + 1));
+  }
+
+  #[test]
+  fn invalid_index_reports_line() {
+    let error = parse_obj("v 0 0 0\nf 1 2 3\n").err().unwrap();
+    assert!(format!("{:?}", error).contains("line 2"));
+  }
+
+  #[test]
+  fn parses_mtl() {
+    let source = "
+newmtl skin
+Ns 900.0
+Kd 0.8 0.7 0.6
+Ke 0 0 0.5
+d 0.5
+map_Kd -bm 1.0 skin.png
+newmtl plain
+Kd 0.1 0.2 0.3
+";
+    let materials = parse_mtl(source, Path::new("model"));
+    assert_eq!(materials.len(), 2);
+    assert_eq!(materials[0].name, "skin");
+    assert_eq!(materials[0].shininess, 900.0);
+    assert_eq!(materials[0].diffuse, [0.8, 0.7, 0.6]);
+    assert_eq!(materials[0].emissive, [0.0, 0.0, 0.5]);
+    assert_eq!(materials[0].diffuse_map, Some(Path::new("model").join("skin.png")));
+    assert_eq!(materials[1], MtlMaterial { diffuse: [0.1, 0.2, 0.3], ..MtlMaterial::new("plain") });
+  }
+
+  #[test]
+  fn loads_bundled_model() {
+    let model = load(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/src/model/Marry.obj"))).unwrap();
+    assert_eq!(model.parts.len(), 2);
+    assert_eq!(model.parts.iter().map(|part| part.buffer_info.indices.len()).sum::<usize>(), 10174 * 3);
+    let face = model.material(Some("MC003_Kozakura_Mari")).unwrap();
+    assert_eq!(face.diffuse_map.as_ref().and_then(|path| path.file_name()), Some("MC003_Kozakura_Mari.png".as_ref()));
+    assert!(face.diffuse_map.as_ref().unwrap().exists());
+  }
+}
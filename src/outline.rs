@@ -0,0 +1,218 @@
+use crate::shape::{
+  Vertex,
+  InstanceData,
+  Mesh
+};
+use crate::texture::Texture;
+use crate::stats;
+
+/// 轮廓uniform变量，与outline.wgsl中的`OutlineUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OutlineUniform {
+  color: [f32; 4],
+  thickness: f32,
+  padding: f32,
+  viewport: [f32; 2],
+}
+
+/// 写入模板缓冲的选中标记
+const STENCIL_REFERENCE: u32 = 1;
+
+/// 选中实例的模板轮廓，需要带模板的深度格式（`Texture::DEPTH_STENCIL_FORMAT`）
+pub struct Outline {
+  uniform: OutlineUniform,
+  buffer: stats::Buffer,
+  group: wgpu::BindGroup,
+  layout: wgpu::BindGroupLayout,
+  /// 将选中实例写入模板
+  mask_pipeline: wgpu::RenderPipeline,
+  /// 绘制外扩后模板之外的部分
+  outline_pipeline: wgpu::RenderPipeline,
+}
+
+impl Outline {
+  pub fn new(camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device) -> Self {
+    let uniform = OutlineUniform {
+      color: [1.0, 0.6, 0.1, 1.0],
+      thickness: 3.0,
+      padding: 0.0,
+      viewport: [config.width as f32, config.height as f32],
+    };
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Outline buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("outline bind group layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        }
+      ]
+    });
+    let group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("outline bind group"),
+      layout: &layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: buffer.as_entire_binding()
+        }
+      ]
+    });
+    let (mask_pipeline, outline_pipeline) = create_pipelines(&layout, camera_layout, sample_count, depth_format, device);
+    Self {
+      uniform,
+      buffer,
+      group,
+      layout,
+      mask_pipeline,
+      outline_pipeline,
+    }
+  }
+
+  /// 多重采样数变化后重建管线
+  pub fn set_sample_count(&mut self, camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) {
+    let (mask_pipeline, outline_pipeline) = create_pipelines(&self.layout, camera_layout, sample_count, depth_format, device);
+    self.mask_pipeline = mask_pipeline;
+    self.outline_pipeline = outline_pipeline;
+  }
+
+  pub fn resize(&mut self, config: &wgpu::SurfaceConfiguration, queue: &wgpu::Queue) {
+    self.uniform.viewport = [config.width as f32, config.height as f32];
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+
+  pub fn set_color(&mut self, color: [f32; 4], queue: &wgpu::Queue) {
+    self.uniform.color = color;
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+
+  pub fn thickness(&self) -> f32 {
+    self.uniform.thickness
+  }
+
+  /// 设置轮廓宽度（像素）
+  pub fn set_thickness(&mut self, thickness: f32, queue: &wgpu::Queue) {
+    self.uniform.thickness = thickness.max(0.0);
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+
+  /// 绘制实例缓冲中第`instance`个实例的轮廓，轮廓不受深度遮挡
+  pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera_group: &'a wgpu::BindGroup, mesh: &'a Mesh, instance_buffer: &'a wgpu::Buffer, instance: u32) {
+    let stride = std::mem::size_of::<InstanceData>() as wgpu::BufferAddress;
+    let offset = instance as wgpu::BufferAddress * stride;
+    render_pass.set_bind_group(0, &self.group, &[]);
+    render_pass.set_bind_group(1, camera_group, &[]);
+    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+    render_pass.set_vertex_buffer(1, instance_buffer.slice(offset..offset + stride));
+    render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+    render_pass.set_stencil_reference(STENCIL_REFERENCE);
+    render_pass.set_pipeline(&self.mask_pipeline);
+    render_pass.draw_indexed(0..mesh.index_num, 0, 0..1);
+    render_pass.set_pipeline(&self.outline_pipeline);
+    render_pass.draw_indexed(0..mesh.index_num, 0, 0..1);
+  }
+}
+
+fn create_pipelines(
+  layout: &wgpu::BindGroupLayout,
+  camera_layout: &wgpu::BindGroupLayout,
+  sample_count: u32,
+  depth_format: wgpu::TextureFormat,
+  device: &wgpu::Device,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+  let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+    label: Some("Outline Shader"),
+    source: wgpu::ShaderSource::Wgsl(include_str!("outline.wgsl").into())
+  });
+  let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    label: Some("Outline Pipeline Layout"),
+    bind_group_layouts: &[layout, camera_layout],
+    push_constant_ranges: &[]
+  });
+  let create_pipeline = |label: &str, entry_point: &str, write_mask: wgpu::ColorWrites, stencil: wgpu::StencilFaceState, stencil_write_mask: u32| {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+      label: Some(label),
+      layout: Some(&pipeline_layout),
+      vertex: wgpu::VertexState {
+        module: &shader,
+        entry_point,
+        buffers: &[
+          Vertex::desc(),
+          InstanceData::desc()
+        ]
+      },
+      fragment: Some(wgpu::FragmentState {
+        module: &shader,
+        entry_point: "fs_main",
+        targets: &[wgpu::ColorTargetState {
+          format: Texture::HDR_FORMAT,
+          blend: Some(wgpu::BlendState::REPLACE),
+          write_mask,
+        }],
+      }),
+      primitive: wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        strip_index_format: None,
+        front_face: wgpu::FrontFace::Ccw,
+        cull_mode: Some(wgpu::Face::Back),
+        polygon_mode: wgpu::PolygonMode::Fill,
+        unclipped_depth: false,
+        conservative: false
+      },
+      depth_stencil: Some(wgpu::DepthStencilState {
+        format: depth_format,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::Always, // 被遮挡的部分也显示轮廓
+        stencil: wgpu::StencilState {
+          front: stencil,
+          back: stencil,
+          read_mask: 0xff,
+          write_mask: stencil_write_mask,
+        },
+        bias: wgpu::DepthBiasState::default()
+      }),
+      multisample: wgpu::MultisampleState {
+        count: sample_count,
+        mask: !0,
+        alpha_to_coverage_enabled: false,
+      },
+      multiview: None
+    })
+  };
+  let mask_pipeline = create_pipeline(
+    "Outline Mask Pipeline",
+    "vs_mask",
+    wgpu::ColorWrites::empty(),
+    wgpu::StencilFaceState {
+      compare: wgpu::CompareFunction::Always,
+      fail_op: wgpu::StencilOperation::Keep,
+      depth_fail_op: wgpu::StencilOperation::Keep,
+      pass_op: wgpu::StencilOperation::Replace,
+    },
+    0xff,
+  );
+  let outline_pipeline = create_pipeline(
+    "Outline Pipeline",
+    "vs_outline",
+    wgpu::ColorWrites::ALL,
+    wgpu::StencilFaceState {
+      compare: wgpu::CompareFunction::NotEqual, // 跳过物体本身覆盖的像素
+      fail_op: wgpu::StencilOperation::Keep,
+      depth_fail_op: wgpu::StencilOperation::Keep,
+      pass_op: wgpu::StencilOperation::Keep,
+    },
+    0,
+  );
+  (mask_pipeline, outline_pipeline)
+}
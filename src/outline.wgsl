@@ -0,0 +1,67 @@
+// 选中物体的轮廓：先将物体写入模板，再绘制在屏幕空间中沿法线外扩的物体，只保留模板之外的部分
+
+struct VertexInput {
+  [[location(0)]] position: vec3<f32>;
+  [[location(1)]] color: vec3<f32>;
+  [[location(2)]] uv: vec2<f32>;
+  [[location(3)]] normal: vec3<f32>;
+};
+
+struct InstanceInput {
+  [[location(4)]] model_0: vec4<f32>;
+  [[location(5)]] model_1: vec4<f32>;
+  [[location(6)]] model_2: vec4<f32>;
+  [[location(7)]] model_3: vec4<f32>;
+};
+
+struct CameraUnifrom {
+  view_projection: mat4x4<f32>;
+  eye_position: vec4<f32>;
+};
+
+struct OutlineUniform {
+  color: vec4<f32>;
+  /// 轮廓宽度（像素）
+  thickness: f32;
+  padding: f32;
+  viewport: vec2<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> outline: OutlineUniform;
+
+[[group(1), binding(0)]]
+var<uniform> camera: CameraUnifrom;
+
+fn get_model_matrix(instanceData: InstanceInput) -> mat4x4<f32> {
+  return mat4x4<f32>(
+    instanceData.model_0,
+    instanceData.model_1,
+    instanceData.model_2,
+    instanceData.model_3
+  );
+}
+
+/// 模板pass：只写模板
+[[stage(vertex)]]
+fn vs_mask(inputData: VertexInput, instanceData: InstanceInput) -> [[builtin(position)]] vec4<f32> {
+  return camera.view_projection * (get_model_matrix(instanceData) * vec4<f32>(inputData.position, 1.0));
+}
+
+[[stage(vertex)]]
+fn vs_outline(inputData: VertexInput, instanceData: InstanceInput) -> [[builtin(position)]] vec4<f32> {
+  let model_matrix = get_model_matrix(instanceData);
+  let position = camera.view_projection * (model_matrix * vec4<f32>(inputData.position, 1.0));
+  let normal = (camera.view_projection * (model_matrix * vec4<f32>(inputData.normal, 0.0))).xy;
+  if (dot(normal, normal) < 0.000001) {
+    return position; // 法线朝向视线方向时不外扩
+  }
+  // 乘以w使外扩距离在透视除法后为固定像素数
+  let offset = normalize(normal) * outline.thickness * 2.0 / outline.viewport * position.w;
+  return vec4<f32>(position.xy + offset, position.zw);
+}
+
+[[stage(fragment)]]
+fn fs_main() -> [[location(0)]] vec4<f32> {
+  return outline.color;
+}
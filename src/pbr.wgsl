@@ -0,0 +1,320 @@
+// 金属度-粗糙度PBR着色：Cook-Torrance直接光照（方向光、聚光灯、点光源，带阴影） + IBL环境光
+
+let PI: f32 = 3.14159265359;
+
+struct VertexInput {
+  [[location(0)]] position: vec3<f32>;
+  [[location(1)]] color: vec3<f32>;
+  [[location(2)]] uv: vec2<f32>;
+  [[location(3)]] normal: vec3<f32>;
+};
+
+struct InstanceInput {
+  [[location(4)]] model_0: vec4<f32>;
+  [[location(5)]] model_1: vec4<f32>;
+  [[location(6)]] model_2: vec4<f32>;
+  [[location(7)]] model_3: vec4<f32>;
+};
+
+struct VertexOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+  [[location(0)]] uv: vec2<f32>;
+  [[location(1)]] world_position: vec3<f32>;
+  [[location(2)]] world_normal: vec3<f32>;
+};
+
+struct CameraUnifrom {
+  view_projection: mat4x4<f32>;
+  eye_position: vec4<f32>;
+};
+
+struct MaterialUniform {
+  base_color: vec4<f32>;
+  emissive: vec4<f32>;
+  metallic: f32;
+  roughness: f32;
+  normal_scale: f32;
+  occlusion_strength: f32;
+};
+
+struct IblUniform {
+  intensity: f32;
+  max_lod: f32;
+};
+
+struct Light {
+  view_projection: mat4x4<f32>;
+  /// w为光源类型：0为方向光，1为聚光灯
+  position: vec4<f32>;
+  /// w为外锥角余弦
+  direction: vec4<f32>;
+  /// w为内锥角余弦
+  color: vec4<f32>;
+  /// x为衰减半径
+  params: vec4<f32>;
+};
+
+struct Lights {
+  count: u32;
+  pcf_radius: i32;
+  texel_size: f32;
+  point_count: u32;
+  lights: array<Light, 4>;
+};
+
+struct PointLight {
+  /// w为衰减半径
+  position: vec4<f32>;
+  color: vec4<f32>;
+  /// 立方体阴影贴图序号，-1表示不投射阴影
+  shadow_index: i32;
+  shadow_near: f32;
+};
+
+struct PointLights {
+  lights: array<PointLight>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> material: MaterialUniform;
+[[group(0), binding(1)]]
+var base_color_t: texture_2d<f32>;
+[[group(0), binding(2)]]
+var metallic_roughness_t: texture_2d<f32>;
+[[group(0), binding(3)]]
+var normal_t: texture_2d<f32>;
+[[group(0), binding(4)]]
+var occlusion_t: texture_2d<f32>;
+[[group(0), binding(5)]]
+var emissive_t: texture_2d<f32>;
+[[group(0), binding(6)]]
+var material_s: sampler;
+
+[[group(1), binding(0)]]
+var<uniform> camera: CameraUnifrom;
+
+[[group(2), binding(0)]]
+var irradiance_t: texture_cube<f32>;
+[[group(2), binding(1)]]
+var prefiltered_t: texture_cube<f32>;
+[[group(2), binding(2)]]
+var brdf_lut_t: texture_2d<f32>;
+[[group(2), binding(3)]]
+var ibl_s: sampler;
+[[group(2), binding(4)]]
+var<uniform> ibl: IblUniform;
+
+[[group(3), binding(0)]]
+var<uniform> lights: Lights;
+[[group(3), binding(1)]]
+var shadow_t: texture_depth_2d_array;
+[[group(3), binding(2)]]
+var shadow_s: sampler_comparison;
+[[group(3), binding(3)]]
+var<storage, read> point_lights: PointLights;
+[[group(3), binding(4)]]
+var point_shadow_t: texture_depth_cube_array;
+
+/// 屏幕空间环境光遮蔽，与屏幕同尺寸
+[[group(4), binding(0)]]
+var ssao_t: texture_2d<f32>;
+
+[[stage(vertex)]]
+fn vs_main(inputData: VertexInput, instanceData: InstanceInput) -> VertexOutput {
+  var outputData: VertexOutput;
+  let model_matrix = mat4x4<f32>(
+    instanceData.model_0,
+    instanceData.model_1,
+    instanceData.model_2,
+    instanceData.model_3
+  );
+  let world_position = model_matrix * vec4<f32>(inputData.position, 1.0);
+  outputData.clip_position = camera.view_projection * world_position;
+  outputData.uv = inputData.uv;
+  outputData.world_position = world_position.xyz;
+  // model matrix为平移*旋转*缩放，法线需除以缩放的平方（等价于逆转置矩阵）
+  let scale_2 = vec3<f32>(dot(model_matrix[0].xyz, model_matrix[0].xyz), dot(model_matrix[1].xyz, model_matrix[1].xyz), dot(model_matrix[2].xyz, model_matrix[2].xyz));
+  outputData.world_normal = (model_matrix * vec4<f32>(inputData.normal / scale_2, 0.0)).xyz;
+  return outputData;
+}
+
+/// 法线分布函数（Trowbridge-Reitz GGX）
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+  let a = roughness * roughness;
+  let a2 = a * a;
+  let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+  return a2 / (PI * d * d + 0.0000001);
+}
+
+/// 几何遮蔽函数（Schlick-GGX，直接光照使用k = (r + 1)^2 / 8）
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+  let r = roughness + 1.0;
+  let k = r * r / 8.0;
+  let ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+  let ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+  return ggx_v * ggx_l;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+  return f0 + (vec3<f32>(1.0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+/// 考虑粗糙度的Schlick菲涅尔近似（用于环境光）
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+  return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+/// 单个光源的Cook-Torrance反射，`l`为指向光源的单位向量，`radiance`为到达表面的辐射亮度
+fn cook_torrance(albedo: vec3<f32>, metallic: f32, roughness: f32, n: vec3<f32>, v: vec3<f32>, l: vec3<f32>, radiance: vec3<f32>) -> vec3<f32> {
+  let h = normalize(v + l);
+  let n_dot_l = max(dot(n, l), 0.0);
+  let n_dot_v = max(dot(n, v), 0.0001);
+  let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+  let f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+  let d = distribution_ggx(max(dot(n, h), 0.0), roughness);
+  let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+  let specular = d * g * f / (4.0 * n_dot_v * n_dot_l + 0.0001);
+  let kd = (vec3<f32>(1.0) - f) * (1.0 - metallic);
+  return (kd * albedo / PI + specular) * radiance * n_dot_l;
+}
+
+/// 基于图像的环境光（split sum近似）
+fn ambient_ibl(albedo: vec3<f32>, metallic: f32, roughness: f32, n: vec3<f32>, v: vec3<f32>) -> vec3<f32> {
+  let n_dot_v = max(dot(n, v), 0.0);
+  let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+  let f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+  let kd = (vec3<f32>(1.0) - f) * (1.0 - metallic);
+  let diffuse = textureSample(irradiance_t, ibl_s, n).rgb * albedo;
+  let r = reflect(-v, n);
+  let prefiltered = textureSampleLevel(prefiltered_t, ibl_s, r, roughness * ibl.max_lod).rgb;
+  let brdf = textureSample(brdf_lut_t, ibl_s, vec2<f32>(n_dot_v, roughness)).rg;
+  let specular = prefiltered * (f * brdf.x + brdf.y);
+  return (kd * diffuse + specular) * ibl.intensity;
+}
+
+/// 第`index`个光源的可见度（PCF滤波），阴影贴图范围外视为可见
+fn shadow_visibility(index: u32, world_position: vec3<f32>) -> f32 {
+  let clip = lights.lights[index].view_projection * vec4<f32>(world_position, 1.0);
+  if (clip.w <= 0.0) {
+    return 1.0;
+  }
+  let ndc = clip.xyz / clip.w;
+  let uv = ndc.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5);
+  if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 || ndc.z > 1.0) {
+    return 1.0;
+  }
+  var visibility = 0.0;
+  var sample_count = 0.0;
+  for (var y = -lights.pcf_radius; y <= lights.pcf_radius; y = y + 1) {
+    for (var x = -lights.pcf_radius; x <= lights.pcf_radius; x = x + 1) {
+      let offset = vec2<f32>(f32(x), f32(y)) * lights.texel_size;
+      visibility = visibility + textureSampleCompareLevel(shadow_t, shadow_s, uv + offset, i32(index), ndc.z);
+      sample_count = sample_count + 1.0;
+    }
+  }
+  return visibility / sample_count;
+}
+
+/// 第`index`个点光源的可见度，`to_fragment`为光源指向片元的向量
+fn point_shadow_visibility(index: u32, to_fragment: vec3<f32>) -> f32 {
+  let light = point_lights.lights[index];
+  if (light.shadow_index < 0) {
+    return 1.0;
+  }
+  // 片元落在哪个立方体面由绝对值最大的分量决定，该分量即为该面视图空间中的深度
+  let far = light.position.w;
+  let near = light.shadow_near;
+  let abs_d = abs(to_fragment);
+  let view_z = max(abs_d.x, max(abs_d.y, abs_d.z));
+  let depth = far / (far - near) - far * near / ((far - near) * view_z);
+  if (lights.pcf_radius == 0) {
+    return textureSampleCompareLevel(point_shadow_t, shadow_s, to_fragment, light.shadow_index, depth);
+  }
+  // 在方向向量上做三维偏移进行PCF，偏移量约为`pcf_radius`个纹素
+  let texel = 2.0 / f32(textureDimensions(point_shadow_t).x);
+  let offset_scale = view_z * texel * f32(lights.pcf_radius);
+  var visibility = 0.0;
+  for (var z = -1; z <= 1; z = z + 1) {
+    for (var y = -1; y <= 1; y = y + 1) {
+      for (var x = -1; x <= 1; x = x + 1) {
+        let direction = to_fragment + vec3<f32>(f32(x), f32(y), f32(z)) * offset_scale;
+        visibility = visibility + textureSampleCompareLevel(point_shadow_t, shadow_s, direction, light.shadow_index, depth);
+      }
+    }
+  }
+  return visibility / 27.0;
+}
+
+/// 所有点光源的直接光照
+fn point_lighting(albedo: vec3<f32>, metallic: f32, roughness: f32, n: vec3<f32>, v: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+  var color = vec3<f32>(0.0);
+  for (var i = 0u; i < lights.point_count; i = i + 1u) {
+    let light = point_lights.lights[i];
+    let to_light = light.position.xyz - world_position;
+    let distance = length(to_light);
+    if (distance < light.position.w) {
+      let falloff = clamp(1.0 - pow(distance / light.position.w, 4.0), 0.0, 1.0);
+      let attenuation = falloff * falloff / max(distance * distance, 0.01);
+      let radiance = light.color.rgb * attenuation;
+      color = color + cook_torrance(albedo, metallic, roughness, n, v, to_light / distance, radiance) * point_shadow_visibility(i, -to_light);
+    }
+  }
+  return color;
+}
+
+/// 所有方向光和聚光灯的直接光照
+fn direct_lighting(albedo: vec3<f32>, metallic: f32, roughness: f32, n: vec3<f32>, v: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+  var color = vec3<f32>(0.0);
+  for (var i = 0u; i < lights.count; i = i + 1u) {
+    let light = lights.lights[i];
+    var l = -normalize(light.direction.xyz);
+    var radiance = light.color.rgb;
+    if (light.position.w > 0.5) {
+      let to_light = light.position.xyz - world_position;
+      let distance = length(to_light);
+      l = to_light / distance;
+      // 平方反比衰减，并在衰减半径处平滑过渡到0
+      let falloff = clamp(1.0 - pow(distance / light.params.x, 4.0), 0.0, 1.0);
+      let attenuation = falloff * falloff / max(distance * distance, 0.01);
+      let spot = smoothStep(light.direction.w, light.color.w, dot(-l, normalize(light.direction.xyz)));
+      radiance = radiance * attenuation * spot;
+    }
+    color = color + cook_torrance(albedo, metallic, roughness, n, v, l, radiance) * shadow_visibility(i, world_position);
+  }
+  return color;
+}
+
+/// 利用屏幕空间导数构造切线空间（无需顶点切线数据），对法线贴图进行扰动
+fn perturb_normal(n: vec3<f32>, position: vec3<f32>, uv: vec2<f32>) -> vec3<f32> {
+  var tangent_normal = textureSample(normal_t, material_s, uv).xyz * 2.0 - vec3<f32>(1.0);
+  tangent_normal = vec3<f32>(tangent_normal.xy * material.normal_scale, tangent_normal.z);
+  let dp1 = dpdx(position);
+  let dp2 = dpdy(position);
+  let duv1 = dpdx(uv);
+  let duv2 = dpdy(uv);
+  let dp2_perp = cross(dp2, n);
+  let dp1_perp = cross(n, dp1);
+  let t = dp2_perp * duv1.x + dp1_perp * duv2.x;
+  let b = dp2_perp * duv1.y + dp1_perp * duv2.y;
+  let inv_max = inverseSqrt(max(dot(t, t), dot(b, b)) + 0.0000001);
+  let tbn = mat3x3<f32>(t * inv_max, b * inv_max, n);
+  return normalize(tbn * tangent_normal);
+}
+
+[[stage(fragment)]]
+fn fs_main(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let base_color = material.base_color * textureSample(base_color_t, material_s, inputData.uv);
+  let metallic_roughness = textureSample(metallic_roughness_t, material_s, inputData.uv);
+  let metallic = material.metallic * metallic_roughness.b;
+  let roughness = clamp(material.roughness * metallic_roughness.g, 0.04, 1.0);
+  let occlusion = mix(1.0, textureSample(occlusion_t, material_s, inputData.uv).r, material.occlusion_strength);
+  let emissive = material.emissive.rgb * textureSample(emissive_t, material_s, inputData.uv).rgb;
+
+  let n = perturb_normal(normalize(inputData.world_normal), inputData.world_position, inputData.uv);
+  let v = normalize(camera.eye_position.xyz - inputData.world_position);
+  let direct = direct_lighting(base_color.rgb, metallic, roughness, n, v, inputData.world_position)
+    + point_lighting(base_color.rgb, metallic, roughness, n, v, inputData.world_position);
+  let ssao = textureLoad(ssao_t, vec2<i32>(inputData.clip_position.xy), 0).r;
+  let ambient = ambient_ibl(base_color.rgb, metallic, roughness, n, v) * occlusion * ssao;
+  return vec4<f32>(direct + ambient + emissive, base_color.a);
+}
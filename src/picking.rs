@@ -0,0 +1,396 @@
+use std::future::Future;
+use std::pin::Pin;
+use cgmath::prelude::*;
+use crate::camera::Camera;
+use crate::shape::{
+  Vertex,
+  InstanceData,
+  Mesh
+};
+use crate::texture::Texture;
+use crate::stats;
+
+/// 拾取结果
+#[derive(Debug, Copy, Clone)]
+pub struct PickResult {
+  /// 网格序号，即`Picking::new`中`mesh_count`范围内的序号
+  pub mesh_id: u32,
+  /// 实例序号
+  pub instance: u32,
+  /// 命中点的世界坐标，由深度反投影得到
+  pub position: cgmath::Point3<f32>,
+}
+
+/// 拾取pass的uniform变量，与picking.wgsl中的`PickUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PickUniform {
+  mesh_id: u32,
+  padding: [u32; 3],
+}
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+/// 已录制、正在等待读回的拾取
+struct InFlight {
+  x: u32,
+  y: u32,
+  inverse_view_projection: cgmath::Matrix4<f32>,
+  /// 提交命令后才开始映射
+  future: Option<MapFuture>,
+  /// 录制后窗口大小改变过，读回的结果已失效
+  stale: bool,
+}
+
+/// 已完成的拾取，未命中时`hit`为`None`
+struct Completed {
+  x: u32,
+  y: u32,
+  hit: Option<PickResult>,
+}
+
+/// 读回缓冲中ID和深度的偏移，需满足复制时每行256字节对齐
+const DEPTH_OFFSET: u64 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64;
+
+/// GPU拾取：只在光标所在像素渲染网格和实例序号，再异步读回
+pub struct Picking {
+  id_target: Texture,
+  depth_target: Texture,
+  depth_texture: Texture,
+  width: u32,
+  height: u32,
+  /// 每个网格一个绑定组，保存网格序号
+  mesh_groups: Vec<wgpu::BindGroup>,
+  pipeline: wgpu::RenderPipeline,
+  readback_buffer: stats::Buffer,
+  /// 等待录制的拾取位置，多次请求只保留最新的
+  request: Option<(u32, u32)>,
+  in_flight: Option<InFlight>,
+  /// 读回完成、等待`pick`取走的结果
+  completed: Option<Completed>,
+}
+
+impl Picking {
+  pub const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+  pub const DEPTH_TARGET_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+  pub fn new(mesh_count: u32, camera_layout: &wgpu::BindGroupLayout, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device) -> Self {
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("pick bind group layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        }
+      ]
+    });
+    let mesh_groups = (0..mesh_count).map(|mesh_id| {
+      let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+        label: Some("Pick buffer"),
+        contents: bytemuck::cast_slice(&[PickUniform { mesh_id, padding: [0; 3] }]),
+        usage: wgpu::BufferUsages::UNIFORM
+      });
+      device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("pick bind group"),
+        layout: &layout,
+        entries: &[
+          wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding()
+          }
+        ]
+      })
+    }).collect();
+    let readback_buffer = stats::create_buffer(device, &wgpu::BufferDescriptor {
+      label: Some("Pick Readback Buffer"),
+      size: DEPTH_OFFSET * 2,
+      usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+      mapped_at_creation: false
+    });
+    let (id_target, depth_target, depth_texture) = create_targets(config, device);
+    Self {
+      id_target,
+      depth_target,
+      depth_texture,
+      width: config.width,
+      height: config.height,
+      mesh_groups,
+      pipeline: create_pipeline(&layout, camera_layout, device),
+      readback_buffer,
+      request: None,
+      in_flight: None,
+      completed: None,
+    }
+  }
+
+  pub fn resize(&mut self, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device) {
+    let (id_target, depth_target, depth_texture) = create_targets(config, device);
+    self.id_target = id_target;
+    self.depth_target = depth_target;
+    self.depth_texture = depth_texture;
+    self.width = config.width;
+    self.height = config.height;
+    // 旧的请求坐标可能超出新的目标，正在读回的结果也要按旧的大小反投影，都不再使用
+    self.request = None;
+    self.completed = None;
+    if let Some(in_flight) = &mut self.in_flight {
+      in_flight.stale = true; // 缓冲可能正在映射，等读回完成后再丢弃
+    }
+  }
+
+  /// 拾取像素(x, y)处的物体，该位置的读回已完成时返回结果
+  ///
+  /// 拾取是异步的，分为几步：第一次调用`pick`记录请求，下一次渲染时`record`录制拾取pass，
+  /// 提交后`after_submit`开始映射读回缓冲，之后每帧调用`poll`推进读回；
+  /// 读回完成后再以相同坐标调用`pick`即返回结果，这样拾取不会阻塞渲染，代价是结果晚一到两帧。
+  /// 未命中或请求尚未完成时都返回`None`，可用`is_pending`区分。读回未完成时新的请求会覆盖旧的请求
+  pub fn pick(&mut self, x: u32, y: u32) -> Option<PickResult> {
+    if let Some(completed) = self.completed.take() {
+      if (completed.x, completed.y) == (x, y) {
+        return completed.hit;
+      }
+    }
+    let in_flight = self.in_flight.as_ref().is_some_and(|in_flight| !in_flight.stale && (in_flight.x, in_flight.y) == (x, y));
+    if !in_flight && x < self.width && y < self.height {
+      self.request = Some((x, y));
+    }
+    None
+  }
+
+  /// 是否还有未完成的拾取
+  pub fn is_pending(&self) -> bool {
+    self.request.is_some() || self.in_flight.as_ref().is_some_and(|in_flight| !in_flight.stale) || self.completed.is_some()
+  }
+
+  /// 有未处理的请求且没有正在读回的拾取时，录制拾取pass并复制光标处的像素
+  ///
+  /// `meshes`中每项为（网格序号，网格，实例缓冲，实例范围），实例序号须与缓冲中的位置一致
+  pub fn record(
+    &mut self,
+    encoder: &mut wgpu::CommandEncoder,
+    camera: &Camera,
+    camera_group: &wgpu::BindGroup,
+    meshes: &[(u32, &Mesh, &wgpu::Buffer, std::ops::Range<u32>)],
+  ) {
+    if self.in_flight.is_some() {
+      return;
+    }
+    let (x, y) = match self.request.take() {
+      Some(request) => request,
+      None => return,
+    };
+    {
+      let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Pick Pass"),
+        color_attachments: &[
+          wgpu::RenderPassColorAttachment {
+            view: &self.id_target.view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+              load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT), // 0表示未命中
+              store: true,
+            }
+          },
+          wgpu::RenderPassColorAttachment {
+            view: &self.depth_target.view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+              load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+              store: true,
+            }
+          }
+        ],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+          view: &self.depth_texture.view,
+          depth_ops: Some(wgpu::Operations {
+            load: wgpu::LoadOp::Clear(1.0),
+            store: true
+          }),
+          stencil_ops: None
+        })
+      });
+      render_pass.set_scissor_rect(x, y, 1, 1); // 只需光标所在的像素
+      render_pass.set_pipeline(&self.pipeline);
+      render_pass.set_bind_group(1, camera_group, &[]);
+      for (mesh_id, mesh, instance_buffer, instances) in meshes {
+        render_pass.set_bind_group(0, &self.mesh_groups[*mesh_id as usize], &[]);
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..mesh.index_num, 0, instances.clone());
+      }
+    }
+    for (target, offset) in [(&self.id_target, 0), (&self.depth_target, DEPTH_OFFSET)] {
+      encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+          texture: &target.texture,
+          mip_level: 0,
+          origin: wgpu::Origin3d { x, y, z: 0 },
+          aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+          buffer: &self.readback_buffer,
+          layout: wgpu::ImageDataLayout {
+            offset,
+            bytes_per_row: std::num::NonZeroU32::new(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+            rows_per_image: None,
+          },
+        },
+        wgpu::Extent3d {
+          width: 1,
+          height: 1,
+          depth_or_array_layers: 1,
+        },
+      );
+    }
+    self.in_flight = Some(InFlight {
+      x,
+      y,
+      inverse_view_projection: camera.get_view_projection_matrix().invert().unwrap_or_else(cgmath::Matrix4::identity),
+      future: None,
+      stale: false,
+    });
+  }
+
+  /// 提交命令后调用，开始映射读回缓冲
+  pub fn after_submit(&mut self) {
+    if let Some(in_flight) = &mut self.in_flight {
+      if in_flight.future.is_none() {
+        in_flight.future = Some(Box::pin(self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read)));
+      }
+    }
+  }
+
+  /// 不阻塞地推进读回，完成后结果留给`pick`取走
+  pub fn poll(&mut self, device: &wgpu::Device) {
+    let in_flight = match &mut self.in_flight {
+      Some(in_flight) => in_flight,
+      None => return,
+    };
+    let future = match &mut in_flight.future {
+      Some(future) => future,
+      None => return,
+    };
+    device.poll(wgpu::Maintain::Poll);
+    let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+    let mapped = match future.as_mut().poll(&mut context) {
+      std::task::Poll::Pending => return,
+      std::task::Poll::Ready(result) => result,
+    };
+    let in_flight = match self.in_flight.take() {
+      Some(in_flight) => in_flight,
+      None => return,
+    };
+    if let Err(error) = mapped {
+      eprintln!("pick readback failed: {:?}", error);
+      if !in_flight.stale {
+        self.completed = Some(Completed { x: in_flight.x, y: in_flight.y, hit: None });
+      }
+      return;
+    }
+    if in_flight.stale {
+      self.readback_buffer.unmap();
+      return;
+    }
+    let hit = self.read_hit(&in_flight);
+    self.completed = Some(Completed { x: in_flight.x, y: in_flight.y, hit });
+  }
+
+  /// 从已映射的读回缓冲解出命中结果并解除映射
+  fn read_hit(&self, in_flight: &InFlight) -> Option<PickResult> {
+    let (id, depth) = {
+      let data = self.readback_buffer.slice(..).get_mapped_range();
+      let id = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]);
+      let offset = DEPTH_OFFSET as usize;
+      let depth = f32::from_ne_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+      (id, depth)
+    };
+    self.readback_buffer.unmap();
+    if id == 0 {
+      return None;
+    }
+    // 像素中心的NDC坐标，y轴向上
+    let ndc = cgmath::Point3::new(
+      (in_flight.x as f32 + 0.5) / self.width as f32 * 2.0 - 1.0,
+      1.0 - (in_flight.y as f32 + 0.5) / self.height as f32 * 2.0,
+      depth,
+    );
+    Some(PickResult {
+      mesh_id: (id >> 24) - 1,
+      instance: id & 0xffffff,
+      position: in_flight.inverse_view_projection.transform_point(ndc),
+    })
+  }
+}
+
+fn create_targets(config: &wgpu::SurfaceConfiguration, device: &wgpu::Device) -> (Texture, Texture, Texture) {
+  (
+    Texture::create_readback_target(device, config.width, config.height, Picking::ID_FORMAT, "pick_id_target"),
+    Texture::create_readback_target(device, config.width, config.height, Picking::DEPTH_TARGET_FORMAT, "pick_depth_target"),
+    Texture::create_depth_texture(device, config, 1, Texture::DEPTH_FORMAT, "pick_depth_texture"),
+  )
+}
+
+fn create_pipeline(layout: &wgpu::BindGroupLayout, camera_layout: &wgpu::BindGroupLayout, device: &wgpu::Device) -> wgpu::RenderPipeline {
+  let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+    label: Some("Pick Shader"),
+    source: wgpu::ShaderSource::Wgsl(include_str!("picking.wgsl").into())
+  });
+  let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    label: Some("Pick Pipeline Layout"),
+    bind_group_layouts: &[layout, camera_layout],
+    push_constant_ranges: &[]
+  });
+  device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    label: Some("Pick Pipeline"),
+    layout: Some(&pipeline_layout),
+    vertex: wgpu::VertexState {
+      module: &shader,
+      entry_point: "vs_main",
+      buffers: &[
+        Vertex::desc(),
+        InstanceData::desc()
+      ]
+    },
+    fragment: Some(wgpu::FragmentState {
+      module: &shader,
+      entry_point: "fs_main",
+      targets: &[
+        wgpu::ColorTargetState {
+          format: Picking::ID_FORMAT,
+          blend: None, // 整数格式不支持混合
+          write_mask: wgpu::ColorWrites::ALL,
+        },
+        wgpu::ColorTargetState {
+          format: Picking::DEPTH_TARGET_FORMAT,
+          blend: None,
+          write_mask: wgpu::ColorWrites::ALL,
+        }
+      ],
+    }),
+    primitive: wgpu::PrimitiveState {
+      topology: wgpu::PrimitiveTopology::TriangleList,
+      strip_index_format: None,
+      front_face: wgpu::FrontFace::Ccw,
+      cull_mode: Some(wgpu::Face::Back),
+      polygon_mode: wgpu::PolygonMode::Fill,
+      unclipped_depth: false,
+      conservative: false
+    },
+    depth_stencil: Some(wgpu::DepthStencilState {
+      format: Texture::DEPTH_FORMAT,
+      depth_write_enabled: true,
+      depth_compare: wgpu::CompareFunction::Less,
+      stencil: wgpu::StencilState::default(),
+      bias: wgpu::DepthBiasState::default()
+    }),
+    multisample: wgpu::MultisampleState::default(),
+    multiview: None
+  })
+}
@@ -0,0 +1,66 @@
+// 拾取：将网格序号和实例序号写入R32Uint纹理，深度写入R32Float纹理
+
+struct VertexInput {
+  [[location(0)]] position: vec3<f32>;
+  [[location(1)]] color: vec3<f32>;
+  [[location(2)]] uv: vec2<f32>;
+  [[location(3)]] normal: vec3<f32>;
+};
+
+struct InstanceInput {
+  [[location(4)]] model_0: vec4<f32>;
+  [[location(5)]] model_1: vec4<f32>;
+  [[location(6)]] model_2: vec4<f32>;
+  [[location(7)]] model_3: vec4<f32>;
+};
+
+struct VertexOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+  [[location(0), interpolate(flat)]] instance: u32;
+};
+
+struct CameraUnifrom {
+  view_projection: mat4x4<f32>;
+  eye_position: vec4<f32>;
+};
+
+struct PickUniform {
+  mesh_id: u32;
+  padding_0: u32;
+  padding_1: u32;
+  padding_2: u32;
+};
+
+struct PickOutput {
+  /// 高8位为网格序号+1（0表示未命中），低24位为实例序号
+  [[location(0)]] id: u32;
+  [[location(1)]] depth: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> pick: PickUniform;
+
+[[group(1), binding(0)]]
+var<uniform> camera: CameraUnifrom;
+
+[[stage(vertex)]]
+fn vs_main(inputData: VertexInput, instanceData: InstanceInput, [[builtin(instance_index)]] instance: u32) -> VertexOutput {
+  var outputData: VertexOutput;
+  let model_matrix = mat4x4<f32>(
+    instanceData.model_0,
+    instanceData.model_1,
+    instanceData.model_2,
+    instanceData.model_3
+  );
+  outputData.clip_position = camera.view_projection * (model_matrix * vec4<f32>(inputData.position, 1.0));
+  outputData.instance = instance;
+  return outputData;
+}
+
+[[stage(fragment)]]
+fn fs_main(inputData: VertexOutput) -> PickOutput {
+  var out: PickOutput;
+  out.id = ((pick.mesh_id + 1u) << 24u) | (inputData.instance & 0xffffffu);
+  out.depth = inputData.clip_position.z;
+  return out;
+}
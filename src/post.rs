@@ -0,0 +1,472 @@
+use crate::texture::Texture;
+use crate::bloom::Bloom;
+use crate::smaa::Smaa;
+use crate::stats;
+
+/// 色调映射算子
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Tonemap {
+  Reinhard,
+  /// ACES filmic曲线
+  Aces,
+}
+
+/// 后处理效果，除泛光外每个效果对应post.wgsl中的一个片元着色器
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PostEffect {
+  /// 泛光，由多个pass组成，见bloom.rs
+  Bloom,
+  Exposure,
+  Tonemap,
+  Gamma,
+  /// 快速近似抗锯齿，可替代MSAA
+  Fxaa,
+  /// 形态学抗锯齿，由多个pass组成，见smaa.rs
+  Smaa,
+}
+
+/// 抗锯齿方式；MSAA与后处理抗锯齿二选一
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AntiAliasing {
+  Msaa,
+  Fxaa,
+  Smaa,
+  Off,
+}
+
+impl AntiAliasing {
+  pub const ALL: [AntiAliasing; 4] = [AntiAliasing::Msaa, AntiAliasing::Fxaa, AntiAliasing::Smaa, AntiAliasing::Off];
+
+  /// 命令行中使用的名称
+  pub fn name(&self) -> &'static str {
+    match self {
+      AntiAliasing::Msaa => "msaa",
+      AntiAliasing::Fxaa => "fxaa",
+      AntiAliasing::Smaa => "smaa",
+      AntiAliasing::Off => "off",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.iter().copied().find(|mode| mode.name() == name)
+  }
+
+  pub fn next(&self) -> Self {
+    let index = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+    Self::ALL[(index + 1) % Self::ALL.len()]
+  }
+}
+
+/// 后处理链中的一个pass
+#[derive(Debug, Copy, Clone)]
+pub struct PostPass {
+  pub effect: PostEffect,
+  pub enabled: bool,
+}
+
+/// 后处理参数
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PostSettings {
+  pub exposure: f32,
+  pub tonemap: Tonemap,
+  pub gamma: f32,
+  /// 产生泛光的亮度阈值（HDR线性值）
+  pub bloom_threshold: f32,
+  pub bloom_intensity: f32,
+}
+
+impl Default for PostSettings {
+  fn default() -> Self {
+    Self {
+      exposure: 1.0,
+      tonemap: Tonemap::Aces,
+      gamma: 2.2,
+      bloom_threshold: 1.0,
+      bloom_intensity: 0.05,
+    }
+  }
+}
+
+/// 后处理相关uniform变量，与post.wgsl中的`PostUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PostUniform {
+  exposure: f32,
+  gamma: f32,
+  tonemap: u32,
+  srgb_target: u32,
+}
+
+/// 后处理链：场景先渲染到离屏HDR纹理，再依次经过各个全屏pass，最后输出到交换链
+///
+/// 各pass在两张HDR纹理之间交替读写（ping-pong）；
+pub struct PostProcess {
+  pub settings: PostSettings,
+  /// 按执行顺序排列的pass
+  pub passes: Vec<PostPass>,
+  uniform: PostUniform,
+  buffer: stats::Buffer,
+  layout: wgpu::BindGroupLayout,
+  targets: [Texture; 2],
+  groups: [wgpu::BindGroup; 2],
+  effect_pipelines: Vec<(PostEffect, wgpu::RenderPipeline)>,
+  blit_pipeline: wgpu::RenderPipeline,
+  bloom: Bloom,
+  smaa: Smaa,
+}
+
+impl PostEffect {
+  pub const ALL: [PostEffect; 6] = [
+    PostEffect::Bloom,
+    PostEffect::Exposure,
+    PostEffect::Tonemap,
+    PostEffect::Gamma,
+    PostEffect::Fxaa,
+    PostEffect::Smaa,
+  ];
+
+  /// 命令行中使用的名称
+  pub fn name(&self) -> &'static str {
+    match self {
+      PostEffect::Bloom => "bloom",
+      PostEffect::Exposure => "exposure",
+      PostEffect::Tonemap => "tonemap",
+      PostEffect::Gamma => "gamma",
+      PostEffect::Fxaa => "fxaa",
+      PostEffect::Smaa => "smaa",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.iter().copied().find(|effect| effect.name() == name)
+  }
+
+  /// 抗锯齿pass的开关由抗锯齿方式决定，见`PostProcess::set_anti_aliasing`
+  pub fn is_anti_aliasing(&self) -> bool {
+    matches!(self, PostEffect::Fxaa | PostEffect::Smaa)
+  }
+
+  fn entry_point(&self) -> Option<&'static str> {
+    match self {
+      PostEffect::Bloom | PostEffect::Smaa => None,
+      PostEffect::Exposure => Some("fs_exposure"),
+      PostEffect::Tonemap => Some("fs_tonemap"),
+      PostEffect::Gamma => Some("fs_gamma"),
+      PostEffect::Fxaa => Some("fs_fxaa"),
+    }
+  }
+}
+
+impl PostSettings {
+  fn get_uniform(&self, srgb_target: bool) -> PostUniform {
+    PostUniform {
+      exposure: self.exposure.max(0.0),
+      gamma: self.gamma.max(0.01),
+      tonemap: match self.tonemap {
+        Tonemap::Reinhard => 0,
+        Tonemap::Aces => 1,
+      },
+      srgb_target: srgb_target as u32,
+    }
+  }
+}
+
+/// 按`order`排列后处理链：列出的效果依次启用，未列出的效果关闭并按`PostEffect::ALL`的顺序排在最后，
+/// 以便之后再打开；抗锯齿pass只决定位置，初始关闭（默认使用MSAA）
+fn create_passes(order: &[PostEffect]) -> Vec<PostPass> {
+  let mut passes = Vec::<PostPass>::new();
+  for effect in order {
+    if !passes.iter().any(|pass| pass.effect == *effect) {
+      passes.push(PostPass { effect: *effect, enabled: !effect.is_anti_aliasing() });
+    }
+  }
+  for effect in PostEffect::ALL {
+    if !passes.iter().any(|pass| pass.effect == effect) {
+      passes.push(PostPass { effect, enabled: false });
+    }
+  }
+  passes
+}
+
+impl PostProcess {
+  /// `order`为后处理链的执行顺序，见`create_passes`
+  pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, order: &[PostEffect]) -> Self {
+    let settings = PostSettings::default();
+    let uniform = settings.get_uniform(config.format.describe().srgb);
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Post buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("post bind group layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+          },
+          count: None
+        },
+        wgpu::BindGroupLayoutEntry {
+          binding: 1,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+          count: None
+        },
+        wgpu::BindGroupLayoutEntry {
+          binding: 2,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        }
+      ]
+    });
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+      label: Some("Post Shader"),
+      source: wgpu::ShaderSource::Wgsl(include_str!("post.wgsl").into())
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Post Pipeline Layout"),
+      bind_group_layouts: &[&layout],
+      push_constant_ranges: &[]
+    });
+    let effect_pipelines = PostEffect::ALL.iter().filter_map(|effect| {
+      let entry_point = effect.entry_point()?;
+      Some((*effect, create_pipeline(device, &pipeline_layout, &shader, entry_point, Texture::HDR_FORMAT)))
+    }).collect::<Vec<_>>();
+    let blit_pipeline = create_pipeline(device, &pipeline_layout, &shader, "fs_blit", config.format);
+    let (targets, groups) = create_targets(device, config, &layout, &buffer);
+    let bloom = Bloom::new(device, config, &targets, settings.bloom_threshold, settings.bloom_intensity);
+    let smaa = Smaa::new(device, config, &targets);
+    Self {
+      settings,
+      passes: create_passes(order),
+      uniform,
+      buffer,
+      layout,
+      targets,
+      groups,
+      effect_pipelines,
+      blit_pipeline,
+      bloom,
+      smaa,
+    }
+  }
+
+  /// 窗口尺寸变化后重建离屏纹理
+  pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+    let (targets, groups) = create_targets(device, config, &self.layout, &self.buffer);
+    self.targets = targets;
+    self.groups = groups;
+    self.bloom.resize(device, config, &self.targets);
+    self.smaa.resize(device, config, &self.targets);
+  }
+
+  /// 场景渲染的目标纹理（HDR）
+  pub fn scene_view(&self) -> &wgpu::TextureView {
+    &self.targets[0].view
+  }
+
+  pub fn set_settings(&mut self, settings: PostSettings, queue: &wgpu::Queue) {
+    self.settings = settings;
+    self.uniform = settings.get_uniform(self.uniform.srgb_target == 1);
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+    self.bloom.set_params(settings.bloom_threshold, settings.bloom_intensity, queue);
+  }
+
+  /// 切换`effect`的开关，返回切换后是否启用；抗锯齿pass须通过`set_anti_aliasing`切换
+  pub fn toggle(&mut self, effect: PostEffect) -> bool {
+    debug_assert!(!effect.is_anti_aliasing());
+    let enabled = !self.is_enabled(effect);
+    self.set_enabled(effect, enabled);
+    enabled
+  }
+
+  pub fn is_enabled(&self, effect: PostEffect) -> bool {
+    self.passes.iter().any(|pass| pass.effect == effect && pass.enabled)
+  }
+
+  /// 开关链中所有`effect`类型的pass
+  pub fn set_enabled(&mut self, effect: PostEffect, enabled: bool) {
+    for pass in self.passes.iter_mut().filter(|pass| pass.effect == effect) {
+      pass.enabled = enabled;
+    }
+  }
+
+  /// 当前启用的后处理抗锯齿，都未启用时为None
+  pub fn anti_aliasing(&self) -> Option<AntiAliasing> {
+    if self.is_enabled(PostEffect::Smaa) {
+      Some(AntiAliasing::Smaa)
+    } else if self.is_enabled(PostEffect::Fxaa) {
+      Some(AntiAliasing::Fxaa)
+    } else {
+      None
+    }
+  }
+
+  /// 按抗锯齿方式开关FXAA和SMAA pass
+  pub fn set_anti_aliasing(&mut self, mode: AntiAliasing) {
+    self.set_enabled(PostEffect::Fxaa, mode == AntiAliasing::Fxaa);
+    self.set_enabled(PostEffect::Smaa, mode == AntiAliasing::Smaa);
+  }
+
+  /// 依次执行所有启用的pass，结果输出到`output`
+  pub fn run(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+    let mut current = 0; // 当前结果所在的纹理
+    for pass in self.passes.iter().filter(|pass| pass.enabled) {
+      let output = &self.targets[1 - current].view;
+      if pass.effect == PostEffect::Bloom {
+        self.bloom.run(encoder, current, output);
+      } else if pass.effect == PostEffect::Smaa {
+        self.smaa.run(encoder, current, output);
+      } else {
+        let pipeline = self.effect_pipelines.iter()
+          .find(|(effect, _)| *effect == pass.effect)
+          .map(|(_, pipeline)| pipeline)
+          .unwrap();
+        self.draw(encoder, pipeline, current, output, "Post Pass");
+      }
+      current = 1 - current;
+    }
+    self.draw(encoder, &self.blit_pipeline, current, output, "Blit Pass");
+  }
+
+  /// 跳过所有后处理，直接将场景输出到交换链（用于调试视图）
+  pub fn blit(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+    self.draw(encoder, &self.blit_pipeline, 0, output, "Blit Pass");
+  }
+
+  fn draw(&self, encoder: &mut wgpu::CommandEncoder, pipeline: &wgpu::RenderPipeline, input: usize, output: &wgpu::TextureView, label: &str) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some(label),
+      color_attachments: &[wgpu::RenderPassColorAttachment {
+        view: output,
+        resolve_target: None,
+        ops: wgpu::Operations {
+          load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+          store: true,
+        }
+      }],
+      depth_stencil_attachment: None
+    });
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, &self.groups[input], &[]);
+    render_pass.draw(0..3, 0..1); // 全屏三角形
+  }
+}
+
+/// 创建全屏pass的渲染管线
+fn create_pipeline(
+  device: &wgpu::Device,
+  layout: &wgpu::PipelineLayout,
+  shader: &wgpu::ShaderModule,
+  entry_point: &str,
+  format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+  device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    label: Some("Post Pipeline"),
+    layout: Some(layout),
+    vertex: wgpu::VertexState {
+      module: shader,
+      entry_point: "vs_main",
+      buffers: &[]
+    },
+    fragment: Some(wgpu::FragmentState {
+      module: shader,
+      entry_point,
+      targets: &[wgpu::ColorTargetState {
+        format,
+        blend: None,
+        write_mask: wgpu::ColorWrites::ALL,
+      }],
+    }),
+    primitive: wgpu::PrimitiveState::default(),
+    depth_stencil: None,
+    multisample: wgpu::MultisampleState::default(),
+    multiview: None
+  })
+}
+
+/// 创建两张用于ping-pong的HDR纹理，以及以它们为输入的bind group
+fn create_targets(
+  device: &wgpu::Device,
+  config: &wgpu::SurfaceConfiguration,
+  layout: &wgpu::BindGroupLayout,
+  buffer: &wgpu::Buffer,
+) -> ([Texture; 2], [wgpu::BindGroup; 2]) {
+  let targets = [
+    Texture::create_render_target(device, config.width, config.height, 1, Texture::HDR_FORMAT, "post_target_0"),
+    Texture::create_render_target(device, config.width, config.height, 1, Texture::HDR_FORMAT, "post_target_1"),
+  ];
+  let groups = [&targets[0], &targets[1]].map(|target| {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("post bind group"),
+      layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: wgpu::BindingResource::TextureView(&target.view)
+        },
+        wgpu::BindGroupEntry {
+          binding: 1,
+          resource: wgpu::BindingResource::Sampler(&target.sampler)
+        },
+        wgpu::BindGroupEntry {
+          binding: 2,
+          resource: buffer.as_entire_binding()
+        }
+      ]
+    })
+  });
+  (targets, groups)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn effects(passes: &[PostPass]) -> Vec<(PostEffect, bool)> {
+    passes.iter().map(|pass| (pass.effect, pass.enabled)).collect()
+  }
+
+  #[test]
+  fn default_chain_disables_anti_aliasing() {
+    assert_eq!(effects(&create_passes(&PostEffect::ALL)), vec![
+      (PostEffect::Bloom, true),
+      (PostEffect::Exposure, true),
+      (PostEffect::Tonemap, true),
+      (PostEffect::Gamma, true),
+      (PostEffect::Fxaa, false),
+      (PostEffect::Smaa, false),
+    ]);
+  }
+
+  #[test]
+  fn chain_follows_order_and_appends_missing_effects() {
+    let order = [PostEffect::Exposure, PostEffect::Smaa, PostEffect::Tonemap, PostEffect::Exposure, PostEffect::Gamma];
+    assert_eq!(effects(&create_passes(&order)), vec![
+      (PostEffect::Exposure, true),
+      (PostEffect::Smaa, false),
+      (PostEffect::Tonemap, true),
+      (PostEffect::Gamma, true),
+      (PostEffect::Bloom, false),
+      (PostEffect::Fxaa, false),
+    ]);
+  }
+
+  #[test]
+  fn effect_names_round_trip() {
+    for effect in PostEffect::ALL {
+      assert_eq!(PostEffect::from_name(effect.name()), Some(effect));
+    }
+    assert_eq!(PostEffect::from_name("vignette"), None);
+  }
+}
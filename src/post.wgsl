@@ -0,0 +1,117 @@
+// 后处理：全屏三角形 + 逐像素效果
+
+struct VertexOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+  [[location(0)]] uv: vec2<f32>;
+};
+
+struct PostUniform {
+  exposure: f32;
+  gamma: f32;
+  /// 0为Reinhard，1为ACES
+  tonemap: u32;
+  /// 输出目标为sRGB格式时为1，需抵消硬件的sRGB编码
+  srgb_target: u32;
+};
+
+[[group(0), binding(0)]]
+var input_t: texture_2d<f32>;
+[[group(0), binding(1)]]
+var input_s: sampler;
+[[group(0), binding(2)]]
+var<uniform> post: PostUniform;
+
+/// 用3个顶点覆盖整个屏幕的三角形，无需顶点缓冲
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] in_vertex_index: u32) -> VertexOutput {
+  var out: VertexOutput;
+  let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+  let y = f32(i32(in_vertex_index >> 1u) * 4 - 1);
+  out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+  out.uv = vec2<f32>(x * 0.5 + 0.5, 0.5 - y * 0.5);
+  return out;
+}
+
+[[stage(fragment)]]
+fn fs_exposure(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let color = textureSample(input_t, input_s, in.uv);
+  return vec4<f32>(color.rgb * post.exposure, color.a);
+}
+
+/// ACES filmic曲线（Narkowicz拟合）
+fn aces(x: vec3<f32>) -> vec3<f32> {
+  return clamp(x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+[[stage(fragment)]]
+fn fs_tonemap(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let color = textureSample(input_t, input_s, in.uv);
+  var mapped: vec3<f32>;
+  if (post.tonemap == 1u) {
+    mapped = aces(color.rgb);
+  } else {
+    mapped = color.rgb / (color.rgb + vec3<f32>(1.0));
+  }
+  return vec4<f32>(mapped, color.a);
+}
+
+[[stage(fragment)]]
+fn fs_gamma(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let color = textureSample(input_t, input_s, in.uv);
+  return vec4<f32>(pow(max(color.rgb, vec3<f32>(0.0)), vec3<f32>(1.0 / post.gamma)), color.a);
+}
+
+let FXAA_SPAN_MAX: f32 = 8.0;
+let FXAA_REDUCE_MUL: f32 = 0.125;
+let FXAA_REDUCE_MIN: f32 = 0.0078125;
+
+fn fxaa_sample(uv: vec2<f32>) -> vec3<f32> {
+  return textureSampleLevel(input_t, input_s, uv, 0.0).rgb;
+}
+
+/// 快速近似抗锯齿（FXAA），应在色调映射和gamma校正之后执行
+[[stage(fragment)]]
+fn fs_fxaa(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let texel = 1.0 / vec2<f32>(textureDimensions(input_t, 0));
+  let weights = vec3<f32>(0.299, 0.587, 0.114);
+  let color = textureSampleLevel(input_t, input_s, in.uv, 0.0);
+  let luma_nw = dot(fxaa_sample(in.uv + vec2<f32>(-1.0, -1.0) * texel), weights);
+  let luma_ne = dot(fxaa_sample(in.uv + vec2<f32>(1.0, -1.0) * texel), weights);
+  let luma_sw = dot(fxaa_sample(in.uv + vec2<f32>(-1.0, 1.0) * texel), weights);
+  let luma_se = dot(fxaa_sample(in.uv + vec2<f32>(1.0, 1.0) * texel), weights);
+  let luma_m = dot(color.rgb, weights);
+  let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+  let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+  // 沿垂直于亮度梯度的方向（即边缘方向）采样
+  var dir = vec2<f32>(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+  let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+  let rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+  dir = clamp(dir * rcp_dir_min, vec2<f32>(-FXAA_SPAN_MAX), vec2<f32>(FXAA_SPAN_MAX)) * texel;
+
+  let rgb_a = 0.5 * (fxaa_sample(in.uv + dir * (1.0 / 3.0 - 0.5)) + fxaa_sample(in.uv + dir * (2.0 / 3.0 - 0.5)));
+  let rgb_b = rgb_a * 0.5 + 0.25 * (fxaa_sample(in.uv - dir * 0.5) + fxaa_sample(in.uv + dir * 0.5));
+  let luma_b = dot(rgb_b, weights);
+  // 采样范围过大跨越了其他边缘时退回较小的范围
+  if (luma_b < luma_min || luma_b > luma_max) {
+    return vec4<f32>(rgb_a, color.a);
+  }
+  return vec4<f32>(rgb_b, color.a);
+}
+
+/// sRGB编码值转线性
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+  let low = c / 12.92;
+  let high = pow((c + vec3<f32>(0.055)) / 1.055, vec3<f32>(2.4));
+  return select(high, low, c <= vec3<f32>(0.04045));
+}
+
+/// 输出到交换链
+[[stage(fragment)]]
+fn fs_blit(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let color = textureSample(input_t, input_s, in.uv);
+  if (post.srgb_target == 1u) {
+    return vec4<f32>(srgb_to_linear(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0))), color.a);
+  }
+  return color;
+}
@@ -0,0 +1,146 @@
+use std::future::Future;
+use std::pin::Pin;
+use crate::stats;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+/// 读回缓冲的数量，结果在几帧之后才读取，不等待GPU
+const FRAMES_IN_FLIGHT: usize = 3;
+/// 每帧最多写入的时间戳数
+const MAX_TIMESTAMPS: u32 = 32;
+const TIMESTAMP_SIZE: wgpu::BufferAddress = wgpu::QUERY_SIZE as wgpu::BufferAddress;
+
+/// 一帧的读回缓冲
+struct Frame {
+  readback_buffer: stats::Buffer,
+  /// 各时间戳结束的阶段，第一个时间戳为帧开始
+  labels: Vec<&'static str>,
+  future: Option<MapFuture>,
+  /// 已录制、等待读回
+  pending: bool,
+}
+
+/// 用时间戳查询统计各pass的GPU时间，需要`Features::TIMESTAMP_QUERY`
+pub struct GpuProfiler {
+  query_set: wgpu::QuerySet,
+  frames: Vec<Frame>,
+  /// 正在录制的帧
+  recording: Option<usize>,
+  /// 时间戳的单位（纳秒）
+  period: f32,
+  /// 最近读回的各阶段GPU时间（毫秒）
+  timings: Vec<(&'static str, f32)>,
+}
+
+impl GpuProfiler {
+  /// 设备不支持时间戳查询时返回None
+  pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+    if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+      return None;
+    }
+    let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+      label: Some("Timestamp Query Set"),
+      ty: wgpu::QueryType::Timestamp,
+      count: MAX_TIMESTAMPS,
+    });
+    let size = MAX_TIMESTAMPS as wgpu::BufferAddress * TIMESTAMP_SIZE;
+    let frames = (0..FRAMES_IN_FLIGHT).map(|_| Frame {
+      readback_buffer: stats::create_buffer(device, &wgpu::BufferDescriptor {
+        label: Some("Timestamp Readback Buffer"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false
+      }),
+      labels: vec![],
+      future: None,
+      pending: false,
+    }).collect();
+    Some(Self {
+      query_set,
+      frames,
+      recording: None,
+      period: queue.get_timestamp_period(),
+      timings: vec![],
+    })
+  }
+
+  /// 在帧开始时写入第一个时间戳；所有读回缓冲都在等待时跳过这一帧
+  pub fn begin_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+    self.recording = self.frames.iter().position(|frame| !frame.pending);
+    if let Some(index) = self.recording {
+      let frame = &mut self.frames[index];
+      frame.labels.clear();
+      frame.labels.push("start");
+      encoder.write_timestamp(&self.query_set, 0);
+    }
+  }
+
+  /// 在上一个时间戳之后的`label`阶段结束处写入时间戳
+  pub fn mark(&mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str) {
+    if let Some(index) = self.recording {
+      let frame = &mut self.frames[index];
+      if (frame.labels.len() as u32) < MAX_TIMESTAMPS {
+        encoder.write_timestamp(&self.query_set, frame.labels.len() as u32);
+        frame.labels.push(label);
+      }
+    }
+  }
+
+  /// 将时间戳解析到读回缓冲，在提交前调用
+  pub fn end_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+    if let Some(index) = self.recording {
+      let frame = &mut self.frames[index];
+      let count = frame.labels.len() as u32;
+      encoder.resolve_query_set(&self.query_set, 0..count, &frame.readback_buffer, 0);
+      frame.pending = true;
+    }
+  }
+
+  /// 提交后映射本帧的读回缓冲
+  pub fn after_submit(&mut self) {
+    if let Some(index) = self.recording.take() {
+      let frame = &mut self.frames[index];
+      let size = frame.labels.len() as wgpu::BufferAddress * TIMESTAMP_SIZE;
+      frame.future = Some(Box::pin(frame.readback_buffer.slice(..size).map_async(wgpu::MapMode::Read)));
+    }
+  }
+
+  /// 不阻塞地检查读回是否完成，完成时更新`timings`
+  pub fn poll(&mut self, device: &wgpu::Device) {
+    device.poll(wgpu::Maintain::Poll);
+    let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+    for frame in &mut self.frames {
+      let mapped = match frame.future.as_mut().map(|future| future.as_mut().poll(&mut context)) {
+        Some(std::task::Poll::Ready(result)) => result,
+        _ => continue,
+      };
+      frame.future = None;
+      frame.pending = false;
+      if let Err(error) = mapped {
+        eprintln!("timestamp readback failed: {:?}", error);
+        continue;
+      }
+      let size = frame.labels.len() as wgpu::BufferAddress * TIMESTAMP_SIZE;
+      let timestamps = {
+        let data = frame.readback_buffer.slice(..size).get_mapped_range();
+        data.chunks_exact(TIMESTAMP_SIZE as usize)
+          .map(|bytes| u64::from_ne_bytes(bytes.try_into().unwrap()))
+          .collect::<Vec<_>>()
+      };
+      frame.readback_buffer.unmap();
+      self.timings = timestamps.windows(2).zip(&frame.labels[1..])
+        .map(|(pair, label)| (*label, pair[1].wrapping_sub(pair[0]) as f32 * self.period / 1_000_000.0))
+        .collect();
+    }
+  }
+
+  /// 最近一次读回的各阶段GPU时间（毫秒）
+  pub fn timings(&self) -> &[(&'static str, f32)] {
+    &self.timings
+  }
+
+  /// 整帧的GPU时间（毫秒）
+  pub fn total(&self) -> f32 {
+    self.timings.iter().map(|(_, ms)| ms).sum()
+  }
+}
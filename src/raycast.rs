@@ -0,0 +1,359 @@
+use cgmath::prelude::*;
+use crate::camera::Camera;
+use crate::shape::{
+  Aabb,
+  BoundingSphere,
+  BuferInfo,
+  InstanceData
+};
+
+/// 射线，`direction`不要求归一化，命中距离以`direction`的长度为单位
+#[derive(Debug, Copy, Clone)]
+pub struct Ray {
+  pub origin: cgmath::Point3<f32>,
+  pub direction: cgmath::Vector3<f32>,
+}
+
+impl Ray {
+  /// 由屏幕像素坐标（左上角为原点）经视图投影矩阵的逆反投影得到世界空间射线
+  ///
+  /// 起点在近平面上，方向已归一化，因此命中距离即为到近平面上起点的世界距离
+  pub fn from_screen(camera: &Camera, x: f32, y: f32, width: u32, height: u32) -> Option<Self> {
+    let inverse = camera.get_view_projection_matrix().invert()?;
+    let ndc_x = x / width as f32 * 2.0 - 1.0;
+    let ndc_y = 1.0 - y / height as f32 * 2.0;
+    let near = inverse.transform_point(cgmath::Point3::new(ndc_x, ndc_y, 0.0));
+    let far = inverse.transform_point(cgmath::Point3::new(ndc_x, ndc_y, 1.0));
+    Some(Self {
+      origin: near,
+      direction: (far - near).normalize(),
+    })
+  }
+
+  pub fn at(&self, distance: f32) -> cgmath::Point3<f32> {
+    self.origin + self.direction * distance
+  }
+
+  /// 变换到另一个空间，不归一化方向，因此变换前后的命中距离相同
+  pub fn transform(&self, matrix: &cgmath::Matrix4<f32>) -> Self {
+    Self {
+      origin: matrix.transform_point(self.origin),
+      direction: matrix.transform_vector(self.direction),
+    }
+  }
+
+  /// 与包围盒相交时返回进入距离（起点在盒内时为0），slab方法
+  pub fn intersect_aabb(&self, aabb: &Aabb, max_distance: f32) -> Option<f32> {
+    let mut t_min = 0.0_f32;
+    let mut t_max = max_distance;
+    for axis in 0..3 {
+      let inverse = 1.0 / self.direction[axis]; // 方向分量为0时为无穷大，比较结果仍然正确
+      let t0 = (aabb.min[axis] - self.origin[axis]) * inverse;
+      let t1 = (aabb.max[axis] - self.origin[axis]) * inverse;
+      t_min = t_min.max(t0.min(t1));
+      t_max = t_max.min(t0.max(t1));
+    }
+    if t_min <= t_max { Some(t_min) } else { None }
+  }
+
+  /// 与球相交时返回进入距离（起点在球内时为0）
+  pub fn intersect_sphere(&self, sphere: &BoundingSphere) -> Option<f32> {
+    let offset = self.origin - sphere.center;
+    let a = self.direction.magnitude2();
+    let b = offset.dot(self.direction);
+    let c = offset.magnitude2() - sphere.radius * sphere.radius;
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+      return None;
+    }
+    let far = (-b + discriminant.sqrt()) / a;
+    if far < 0.0 {
+      return None;
+    }
+    Some(((-b - discriminant.sqrt()) / a).max(0.0))
+  }
+
+  /// Möller-Trumbore算法，返回命中距离和`p1`、`p2`的重心坐标
+  fn intersect_triangle(&self, [p0, p1, p2]: [cgmath::Point3<f32>; 3]) -> Option<(f32, f32, f32)> {
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+    let p = self.direction.cross(edge2);
+    let determinant = edge1.dot(p);
+    if determinant.abs() < f32::EPSILON {
+      return None; // 射线与三角形平行
+    }
+    let inverse = 1.0 / determinant;
+    let s = self.origin - p0;
+    let u = s.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+      return None;
+    }
+    let q = s.cross(edge1);
+    let v = self.direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+      return None;
+    }
+    let t = edge2.dot(q) * inverse;
+    if t < 0.0 { None } else { Some((t, u, v)) }
+  }
+}
+
+/// 射线与网格三角形的交点
+#[derive(Debug, Copy, Clone)]
+pub struct TriangleHit {
+  pub distance: f32,
+  /// 三角形序号，即索引数据中的第`triangle * 3`个索引开始的三角形
+  pub triangle: u32,
+  /// 三个顶点的重心坐标
+  pub barycentric: cgmath::Vector3<f32>,
+  /// 由顶点UV插值得到
+  pub uv: cgmath::Vector2<f32>,
+}
+
+/// 射线与场景中某个实例的交点
+#[derive(Debug, Copy, Clone)]
+pub struct RayHit {
+  pub mesh_id: u32,
+  pub instance: u32,
+  /// 世界空间中的命中点
+  pub position: cgmath::Point3<f32>,
+  pub hit: TriangleHit,
+}
+
+/// BVH节点，叶节点的`children`为None
+#[derive(Debug, Clone)]
+struct BvhNode {
+  bounds: Aabb,
+  /// 在`Bvh::triangles`中的范围
+  triangles: std::ops::Range<usize>,
+  children: Option<(usize, usize)>,
+}
+
+/// 叶节点最多包含的三角形数
+const LEAF_SIZE: usize = 4;
+
+/// 模型空间的三角形包围体层次结构，用于CPU射线检测
+pub struct Bvh {
+  positions: Vec<cgmath::Point3<f32>>,
+  uvs: Vec<cgmath::Vector2<f32>>,
+  /// 三角形的顶点序号，构建时按节点重新排列
+  triangles: Vec<([u32; 3], u32)>,
+  nodes: Vec<BvhNode>,
+}
+
+impl Bvh {
+  pub fn new(buffer_info: &BuferInfo) -> Self {
+    let positions = buffer_info.vertices.iter().map(|vertex| cgmath::Point3::from(vertex.position)).collect::<Vec<_>>();
+    let uvs = buffer_info.vertices.iter().map(|vertex| cgmath::Vector2::from(vertex.uv)).collect();
+    let triangles = buffer_info.indices.chunks_exact(3)
+      .enumerate()
+      .map(|(index, triangle)| ([triangle[0] as u32, triangle[1] as u32, triangle[2] as u32], index as u32))
+      .collect();
+    let mut bvh = Self {
+      positions,
+      uvs,
+      triangles,
+      nodes: vec![],
+    };
+    if !bvh.triangles.is_empty() {
+      bvh.build(0..bvh.triangles.len());
+    }
+    bvh
+  }
+
+  fn triangle_points(&self, [a, b, c]: [u32; 3]) -> [cgmath::Point3<f32>; 3] {
+    [self.positions[a as usize], self.positions[b as usize], self.positions[c as usize]]
+  }
+
+  /// 递归构建，按三角形中心包围盒最长轴的中位数划分，返回节点序号
+  fn build(&mut self, range: std::ops::Range<usize>) -> usize {
+    let bounds = Aabb::from_points(self.triangles[range.clone()].iter().flat_map(|(triangle, _)| self.triangle_points(*triangle)));
+    let index = self.nodes.len();
+    self.nodes.push(BvhNode {
+      bounds,
+      triangles: range.clone(),
+      children: None,
+    });
+    if range.len() <= LEAF_SIZE {
+      return index;
+    }
+    let centroid = |bvh: &Self, triangle: [u32; 3]| {
+      let [a, b, c] = bvh.triangle_points(triangle);
+      (a.to_vec() + b.to_vec() + c.to_vec()) / 3.0
+    };
+    let centroids = Aabb::from_points(self.triangles[range.clone()].iter().map(|(triangle, _)| cgmath::Point3::from_vec(centroid(self, *triangle))));
+    let extent = centroids.max - centroids.min;
+    let axis = if extent.x >= extent.y && extent.x >= extent.z { 0 } else if extent.y >= extent.z { 1 } else { 2 };
+    let mut triangles = self.triangles[range.clone()].to_vec();
+    triangles.sort_by(|a, b| centroid(self, a.0)[axis].total_cmp(&centroid(self, b.0)[axis]));
+    self.triangles[range.clone()].copy_from_slice(&triangles);
+    let middle = range.start + range.len() / 2;
+    let left = self.build(range.start..middle);
+    let right = self.build(middle..range.end);
+    self.nodes[index].children = Some((left, right));
+    index
+  }
+
+  /// 模型空间的射线检测，返回`max_distance`以内最近的交点
+  pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<TriangleHit> {
+    let mut closest: Option<TriangleHit> = None;
+    let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+    while let Some(index) = stack.pop() {
+      let node = &self.nodes[index];
+      let limit = closest.map_or(max_distance, |hit| hit.distance);
+      if ray.intersect_aabb(&node.bounds, limit).is_none() {
+        continue;
+      }
+      if let Some((left, right)) = node.children {
+        stack.push(left);
+        stack.push(right);
+        continue;
+      }
+      for (triangle, triangle_index) in &self.triangles[node.triangles.clone()] {
+        if let Some((distance, u, v)) = ray.intersect_triangle(self.triangle_points(*triangle)) {
+          if distance < closest.map_or(max_distance, |hit| hit.distance) {
+            let barycentric = cgmath::Vector3::new(1.0 - u - v, u, v);
+            let [a, b, c] = triangle.map(|vertex| self.uvs[vertex as usize]);
+            closest = Some(TriangleHit {
+              distance,
+              triangle: *triangle_index,
+              barycentric,
+              uv: a * barycentric.x + b * barycentric.y + c * barycentric.z,
+            });
+          }
+        }
+      }
+    }
+    closest
+  }
+}
+
+/// 世界空间射线与网格各实例的检测，先用包围球快速排除，再在模型空间中遍历BVH
+pub fn raycast_instances(
+  ray: &Ray,
+  mesh_id: u32,
+  bvh: &Bvh,
+  bounding_sphere: &BoundingSphere,
+  instances: &[InstanceData],
+  max_distance: f32,
+) -> Option<RayHit> {
+  let mut closest: Option<RayHit> = None;
+  for (instance, data) in instances.iter().enumerate() {
+    let model_matrix = cgmath::Matrix4::from(data.model_matrix);
+    let limit = closest.map_or(max_distance, |hit| hit.hit.distance);
+    match ray.intersect_sphere(&bounding_sphere.transform(&model_matrix)) {
+      Some(distance) if distance <= limit => {},
+      _ => continue,
+    }
+    let inverse = match model_matrix.invert() {
+      Some(inverse) => inverse,
+      None => continue,
+    };
+    if let Some(hit) = bvh.raycast(&ray.transform(&inverse), limit) {
+      closest = Some(RayHit {
+        mesh_id,
+        instance: instance as u32,
+        position: ray.at(hit.distance),
+        hit,
+      });
+    }
+  }
+  closest
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::shape::get_sphere;
+
+  fn ray(origin: [f32; 3], direction: [f32; 3]) -> Ray {
+    Ray { origin: origin.into(), direction: direction.into() }
+  }
+
+  fn unit_box() -> Aabb {
+    Aabb { min: cgmath::Point3::new(-1.0, -1.0, -1.0), max: cgmath::Point3::new(1.0, 1.0, 1.0) }
+  }
+
+  #[test]
+  fn intersect_aabb_hits_and_misses() {
+    let aabb = unit_box();
+    assert_eq!(ray([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]).intersect_aabb(&aabb, f32::MAX), Some(4.0));
+    assert_eq!(ray([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]).intersect_aabb(&aabb, f32::MAX), Some(0.0)); // 起点在盒内
+    assert_eq!(ray([0.0, 2.0, 5.0], [0.0, 0.0, -1.0]).intersect_aabb(&aabb, f32::MAX), None); // 方向分量为0的轴上不重叠
+    assert_eq!(ray([0.0, 0.0, 5.0], [0.0, 0.0, 1.0]).intersect_aabb(&aabb, f32::MAX), None); // 盒在射线后方
+    assert_eq!(ray([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]).intersect_aabb(&aabb, 3.0), None); // 超出最大距离
+    // 斜向穿过角落
+    let distance = ray([-3.0, -3.0, 0.0], [1.0, 1.0, 0.0]).intersect_aabb(&aabb, f32::MAX).unwrap();
+    assert!((distance - 2.0).abs() < 1e-6);
+  }
+
+  #[test]
+  fn intersect_sphere_hits_and_misses() {
+    let sphere = BoundingSphere { center: cgmath::Point3::new(0.0, 0.0, 0.0), radius: 1.0 };
+    assert_eq!(ray([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]).intersect_sphere(&sphere), Some(4.0));
+    assert_eq!(ray([0.0, 0.0, 5.0], [0.0, 0.0, -2.0]).intersect_sphere(&sphere), Some(2.0)); // 以方向长度为单位
+    assert_eq!(ray([0.0, 0.5, 0.0], [0.0, 0.0, -1.0]).intersect_sphere(&sphere), Some(0.0)); // 起点在球内
+    assert_eq!(ray([0.0, 0.0, 5.0], [0.0, 0.0, 1.0]).intersect_sphere(&sphere), None);
+    assert_eq!(ray([0.0, 1.5, 5.0], [0.0, 0.0, -1.0]).intersect_sphere(&sphere), None);
+  }
+
+  #[test]
+  fn intersect_triangle_barycentric() {
+    let triangle = [cgmath::Point3::new(0.0, 0.0, 0.0), cgmath::Point3::new(1.0, 0.0, 0.0), cgmath::Point3::new(0.0, 1.0, 0.0)];
+    let (distance, u, v) = ray([0.25, 0.5, 2.0], [0.0, 0.0, -1.0]).intersect_triangle(triangle).unwrap();
+    assert!((distance - 2.0).abs() < 1e-6);
+    assert!((u - 0.25).abs() < 1e-6 && (v - 0.5).abs() < 1e-6);
+    // 背面同样命中
+    assert!(ray([0.25, 0.25, -2.0], [0.0, 0.0, 1.0]).intersect_triangle(triangle).is_some());
+    assert!(ray([0.75, 0.75, 2.0], [0.0, 0.0, -1.0]).intersect_triangle(triangle).is_none()); // 斜边之外
+    assert!(ray([-0.1, 0.5, 2.0], [0.0, 0.0, -1.0]).intersect_triangle(triangle).is_none());
+    assert!(ray([0.25, 0.25, 2.0], [0.0, 0.0, 1.0]).intersect_triangle(triangle).is_none()); // 三角形在后方
+    assert!(ray([0.25, 0.25, 2.0], [1.0, 0.0, 0.0]).intersect_triangle(triangle).is_none()); // 平行
+  }
+
+  /// 逐个三角形检测，作为BVH的参照
+  fn brute_force(bvh: &Bvh, ray: &Ray, max_distance: f32) -> Option<f32> {
+    bvh.triangles.iter()
+      .filter_map(|(triangle, _)| ray.intersect_triangle(bvh.triangle_points(*triangle)))
+      .map(|(distance, _, _)| distance)
+      .filter(|distance| *distance < max_distance)
+      .min_by(f32::total_cmp)
+  }
+
+  #[test]
+  fn bvh_matches_brute_force() {
+    let bvh = Bvh::new(&get_sphere(24, 16, 1.0));
+    assert!(bvh.nodes.len() > 1);
+    // 确定性的伪随机射线：从半径3的球面上的点射向原点附近
+    let mut seed = 0x12345678u32;
+    let mut random = || {
+      seed ^= seed << 13;
+      seed ^= seed >> 17;
+      seed ^= seed << 5;
+      seed as f32 / u32::MAX as f32 * 2.0 - 1.0
+    };
+    let (mut hits, mut misses) = (0, 0);
+    for _ in 0..500 {
+      let origin = cgmath::Vector3::new(random(), random(), random()).normalize() * 3.0;
+      let target = cgmath::Vector3::new(random(), random(), random()) * 1.5;
+      let ray = Ray { origin: cgmath::Point3::from_vec(origin), direction: (target - origin).normalize() };
+      let expected = brute_force(&bvh, &ray, f32::MAX);
+      let actual = bvh.raycast(&ray, f32::MAX);
+      match (expected, actual) {
+        (Some(expected), Some(actual)) => {
+          assert!((expected - actual.distance).abs() < 1e-5);
+          let sum = actual.barycentric.x + actual.barycentric.y + actual.barycentric.z;
+          assert!((sum - 1.0).abs() < 1e-5);
+          hits += 1;
+        },
+        (None, None) => misses += 1,
+        (expected, actual) => panic!("brute force {:?}, bvh {:?}", expected, actual),
+      }
+    }
+    assert!(hits > 0 && misses > 0);
+    // 最大距离以内没有三角形时不命中
+    let ray = ray([0.05, 0.07, 3.0], [0.0, 0.0, -1.0]);
+    assert!(bvh.raycast(&ray, 1.5).is_none());
+    assert!((bvh.raycast(&ray, 2.5).unwrap().distance - 2.0).abs() < 0.02); // 细分的球面略小于半径
+  }
+}
@@ -1,22 +0,0 @@
-struct VertexInput {
-  [[location(0)]] position: vec3<f32>;
-  [[location(1)]] color: vec3<f32>;
-};
-
-struct VertexOutput {
-  [[builtin(position)]] clip_position: vec4<f32>;
-  [[location(0)]] color: vec3<f32>;
-};
-
-[[stage(vertex)]]
-fn vs_main(data: VertexInput) -> VertexOutput {
-  var out: VertexOutput;
-  out.clip_position = vec4<f32>(data.position, 1.0);
-  out.color = data.color;
-  return out;
-}
-
-[[stage(fragment)]]
-fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-  return vec4<f32>(in.color, 1.0);
-}
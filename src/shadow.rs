@@ -0,0 +1,264 @@
+use crate::light::{
+  Light,
+  PointLight,
+  MAX_LIGHTS,
+  MAX_POINT_SHADOWS,
+  shadow_casters
+};
+use crate::shape::{
+  Vertex,
+  InstanceData
+};
+use crate::texture::Texture;
+use crate::stats;
+
+/// 点光源立方体阴影贴图单面分辨率
+const POINT_SHADOW_SIZE: u32 = 512;
+
+/// 阴影参数
+#[derive(Debug, Copy, Clone)]
+pub struct ShadowSettings {
+  /// 阴影贴图分辨率
+  pub map_size: u32,
+  /// 常量深度偏移（以深度缓冲最小精度为单位），用于消除阴影痤疮
+  pub depth_bias: i32,
+  /// 按多边形斜率缩放的深度偏移
+  pub slope_scale: f32,
+  /// PCF滤波半径（以纹素为单位），采样数为`(2r + 1)^2`
+  pub pcf_radius: u32,
+}
+
+impl Default for ShadowSettings {
+  fn default() -> Self {
+    Self {
+      map_size: 2048,
+      depth_bias: 2,
+      slope_scale: 2.0,
+      pcf_radius: 1,
+    }
+  }
+}
+
+/// 阴影pass中每个光源的uniform数据，与shadow.wgsl中的`ShadowUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowUniform {
+  view_projection: [[f32; 4]; 4],
+}
+
+/// 一组阴影pass所需的资源：每层一个渲染目标视图和一个视图投影矩阵
+struct ShadowLayers {
+  views: Vec<wgpu::TextureView>,
+  buffers: Vec<stats::Buffer>,
+  groups: Vec<wgpu::BindGroup>,
+  layout: wgpu::BindGroupLayout,
+  pipeline: wgpu::RenderPipeline,
+  front_face: wgpu::FrontFace,
+}
+
+/// 方向光、聚光灯和点光源的阴影贴图及其渲染管线
+pub struct ShadowMap {
+  pub settings: ShadowSettings,
+  /// 方向光和聚光灯的深度纹理数组，每个光源占一层
+  pub texture: Texture,
+  /// 点光源的立方体深度纹理数组，每个光源占六层
+  pub point_texture: Texture,
+  layers: ShadowLayers,
+  point_layers: ShadowLayers,
+}
+
+impl ShadowLayers {
+  fn new(texture: &Texture, count: u32, front_face: wgpu::FrontFace, settings: &ShadowSettings, device: &wgpu::Device) -> Self {
+    let views = (0..count).map(|layer| texture.layer_view(layer)).collect::<Vec<_>>();
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("shadow bind group layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::VERTEX,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        }
+      ]
+    });
+    let buffers = (0..count).map(|_| {
+      stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+        label: Some("Shadow light buffer"),
+        contents: bytemuck::cast_slice(&[ShadowUniform { view_projection: [[0.0; 4]; 4] }]),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+      })
+    }).collect::<Vec<_>>();
+    let groups = buffers.iter().map(|buffer| {
+      device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("shadow bind group"),
+        layout: &layout,
+        entries: &[
+          wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding()
+          }
+        ]
+      })
+    }).collect::<Vec<_>>();
+    let pipeline = Self::create_pipeline(settings, &layout, front_face, device);
+    Self {
+      views,
+      buffers,
+      groups,
+      layout,
+      pipeline,
+      front_face,
+    }
+  }
+
+  fn create_pipeline(
+    settings: &ShadowSettings,
+    layout: &wgpu::BindGroupLayout,
+    front_face: wgpu::FrontFace,
+    device: &wgpu::Device,
+  ) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+      label: Some("Shadow Shader"),
+      source: wgpu::ShaderSource::Wgsl(include_str!("shadow.wgsl").into())
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+      label: Some("Shadow Pipeline Layout"),
+      bind_group_layouts: &[layout],
+      push_constant_ranges: &[]
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+      label: Some("Shadow Pipeline"),
+      layout: Some(&pipeline_layout),
+      vertex: wgpu::VertexState {
+        module: &shader,
+        entry_point: "vs_main",
+        buffers: &[
+          Vertex::desc(),
+          InstanceData::desc()
+        ]
+      },
+      fragment: None, // 只写入深度
+      primitive: wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        strip_index_format: None,
+        front_face,
+        cull_mode: Some(wgpu::Face::Back),
+        polygon_mode: wgpu::PolygonMode::Fill,
+        unclipped_depth: false,
+        conservative: false
+      },
+      depth_stencil: Some(wgpu::DepthStencilState {
+        format: Texture::DEPTH_FORMAT,
+        depth_write_enabled: true,
+        depth_compare: wgpu::CompareFunction::LessEqual,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState {
+          constant: settings.depth_bias,
+          slope_scale: settings.slope_scale,
+          clamp: 0.0,
+        }
+      }),
+      multisample: wgpu::MultisampleState {
+        count: 1,
+        mask: !0,
+        alpha_to_coverage_enabled: false,
+      },
+      multiview: None
+    })
+  }
+
+  fn write(&self, layer: usize, view_projection: cgmath::Matrix4<f32>, queue: &wgpu::Queue) {
+    let uniform = ShadowUniform {
+      view_projection: view_projection.into(),
+    };
+    queue.write_buffer(&self.buffers[layer], 0, bytemuck::cast_slice(&[uniform]));
+  }
+
+  fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, layer: usize) -> wgpu::RenderPass<'a> {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+      label: Some("Shadow Pass"),
+      color_attachments: &[],
+      depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+        view: &self.views[layer],
+        depth_ops: Some(wgpu::Operations {
+          load: wgpu::LoadOp::Clear(1.0),
+          store: true
+        }),
+        stencil_ops: None
+      })
+    });
+    render_pass.set_pipeline(&self.pipeline);
+    render_pass.set_bind_group(0, &self.groups[layer], &[]);
+    render_pass
+  }
+}
+
+impl ShadowMap {
+  pub fn new(settings: ShadowSettings, device: &wgpu::Device) -> Self {
+    let texture = Texture::create_shadow_map(
+      device,
+      settings.map_size,
+      MAX_LIGHTS as u32,
+      wgpu::TextureViewDimension::D2Array,
+      "shadow_map"
+    );
+    let point_texture = Texture::create_shadow_map(
+      device,
+      POINT_SHADOW_SIZE,
+      MAX_POINT_SHADOWS as u32 * 6,
+      wgpu::TextureViewDimension::CubeArray,
+      "point_shadow_map"
+    );
+    let layers = ShadowLayers::new(&texture, MAX_LIGHTS as u32, wgpu::FrontFace::Ccw, &settings, device);
+    // 立方体面的投影矩阵翻转了y轴，三角形绕序随之相反
+    let point_layers = ShadowLayers::new(&point_texture, MAX_POINT_SHADOWS as u32 * 6, wgpu::FrontFace::Cw, &settings, device);
+    Self {
+      settings,
+      texture,
+      point_texture,
+      layers,
+      point_layers,
+    }
+  }
+
+  /// 修改阴影参数；深度偏移是管线状态的一部分，需要重建管线（阴影贴图分辨率在创建后不可修改）
+  pub fn set_settings(&mut self, settings: ShadowSettings, device: &wgpu::Device) {
+    self.settings = ShadowSettings {
+      map_size: self.settings.map_size,
+      ..settings
+    };
+    for layers in [&mut self.layers, &mut self.point_layers] {
+      layers.pipeline = ShadowLayers::create_pipeline(&self.settings, &layers.layout, layers.front_face, device);
+    }
+  }
+
+  /// 更新各光源视角的视图投影矩阵
+  pub fn update_lights(&self, lights: &[Light], queue: &wgpu::Queue) {
+    for (layer, light) in lights.iter().take(MAX_LIGHTS).enumerate() {
+      self.layers.write(layer, light.get_view_projection_matrix(), queue);
+    }
+  }
+
+  /// 更新投射阴影的点光源六个面的视图投影矩阵
+  pub fn update_point_lights(&self, point_lights: &[PointLight], queue: &wgpu::Queue) {
+    for (shadow_index, light) in shadow_casters(point_lights).enumerate() {
+      for face in 0..6 {
+        self.point_layers.write(shadow_index * 6 + face, light.get_face_view_projection_matrix(face), queue);
+      }
+    }
+  }
+
+  /// 开始第`layer`个光源的阴影pass，之后由调用方绘制场景几何体
+  pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, layer: usize) -> wgpu::RenderPass<'a> {
+    self.layers.begin_pass(encoder, layer)
+  }
+
+  /// 开始第`shadow_index`个投射阴影的点光源的第`face`个立方体面的阴影pass
+  pub fn begin_point_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, shadow_index: usize, face: usize) -> wgpu::RenderPass<'a> {
+    self.point_layers.begin_pass(encoder, shadow_index * 6 + face)
+  }
+}
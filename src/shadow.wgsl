@@ -0,0 +1,30 @@
+// 阴影贴图：从光源视角只渲染深度
+
+struct VertexInput {
+  [[location(0)]] position: vec3<f32>;
+};
+
+struct InstanceInput {
+  [[location(4)]] model_0: vec4<f32>;
+  [[location(5)]] model_1: vec4<f32>;
+  [[location(6)]] model_2: vec4<f32>;
+  [[location(7)]] model_3: vec4<f32>;
+};
+
+struct ShadowUniform {
+  view_projection: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> light: ShadowUniform;
+
+[[stage(vertex)]]
+fn vs_main(inputData: VertexInput, instanceData: InstanceInput) -> [[builtin(position)]] vec4<f32> {
+  let model_matrix = mat4x4<f32>(
+    instanceData.model_0,
+    instanceData.model_1,
+    instanceData.model_2,
+    instanceData.model_3
+  );
+  return light.view_projection * model_matrix * vec4<f32>(inputData.position, 1.0);
+}
@@ -1,4 +1,6 @@
 use std::mem;
+use crate::raycast::Bvh;
+use crate::stats;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -6,6 +8,7 @@ pub struct Vertex {
   pub position: [f32; 3],
   pub color: [f32; 3],
   pub uv: [f32; 2],
+  pub normal: [f32; 3],
 }
 
 impl Vertex {
@@ -29,6 +32,11 @@ impl Vertex {
           shader_location: 2,
           format: wgpu::VertexFormat::Float32x2,
         }, // uv
+        wgpu::VertexAttribute {
+          offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+          shader_location: 3,
+          format: wgpu::VertexFormat::Float32x3,
+        }, // normal
       ]
     }
   }
@@ -51,22 +59,22 @@ impl InstanceData {
       attributes: &[
         wgpu::VertexAttribute {
           offset: 0,
-          shader_location: 3,
+          shader_location: 4,
           format: wgpu::VertexFormat::Float32x4,
         },
         wgpu::VertexAttribute {
           offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
-          shader_location: 4,
+          shader_location: 5,
           format: wgpu::VertexFormat::Float32x4,
         },
         wgpu::VertexAttribute {
           offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
-          shader_location: 5,
+          shader_location: 6,
           format: wgpu::VertexFormat::Float32x4,
         },
         wgpu::VertexAttribute {
           offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
-          shader_location: 6,
+          shader_location: 7,
           format: wgpu::VertexFormat::Float32x4,
         }
       ]
@@ -75,51 +83,231 @@ impl InstanceData {
 }
 
 /// 物体实例所需信息，实际上就是构成model matrix
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Instance {
   /// 物体中心位置
   pub center: cgmath::Vector3<f32>,
   /// 物体旋转四元量
-  pub rotation: cgmath::Quaternion<f32>
+  pub rotation: cgmath::Quaternion<f32>,
+  /// 沿局部坐标轴的缩放
+  pub scale: cgmath::Vector3<f32>
 }
 
 impl Instance {
   /// 获取实例数据；
   pub fn get_data(&self) -> InstanceData {
-    let model_matrix = cgmath::Matrix4::from_translation(self.center) * cgmath::Matrix4::from(self.rotation);
+    let model_matrix = cgmath::Matrix4::from_translation(self.center)
+      * cgmath::Matrix4::from(self.rotation)
+      * cgmath::Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z);
     InstanceData {
       model_matrix: model_matrix.into()
     }
   }
 }
 
+/// 轴对齐包围盒
+#[derive(Debug, Copy, Clone)]
+pub struct Aabb {
+  pub min: cgmath::Point3<f32>,
+  pub max: cgmath::Point3<f32>,
+}
+
+impl Aabb {
+  pub fn from_points(points: impl IntoIterator<Item = cgmath::Point3<f32>>) -> Self {
+    let mut min = cgmath::Point3::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = cgmath::Point3::new(f32::MIN, f32::MIN, f32::MIN);
+    for point in points {
+      min = cgmath::Point3::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z));
+      max = cgmath::Point3::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z));
+    }
+    Self { min, max }
+  }
+
+  /// 8个顶点，序号的第0/1/2位分别表示取x/y/z的最大值
+  pub fn corners(&self) -> [cgmath::Point3<f32>; 8] {
+    let mut corners = [self.min; 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+      *corner = cgmath::Point3::new(
+        if i & 1 == 0 { self.min.x } else { self.max.x },
+        if i & 2 == 0 { self.min.y } else { self.max.y },
+        if i & 4 == 0 { self.min.z } else { self.max.z },
+      );
+    }
+    corners
+  }
+
+  /// 变换后重新计算包围盒
+  pub fn transform(&self, matrix: &cgmath::Matrix4<f32>) -> Self {
+    Self::from_points(self.corners().iter().map(|corner| cgmath::Transform::transform_point(matrix, *corner)))
+  }
+
+  /// 同时包含两者的包围盒
+  pub fn union(&self, other: &Aabb) -> Self {
+    Self::from_points([self.min, self.max, other.min, other.max])
+  }
+
+  pub fn center(&self) -> cgmath::Point3<f32> {
+    cgmath::EuclideanSpace::midpoint(self.min, self.max)
+  }
+
+  /// 外接球
+  pub fn bounding_sphere(&self) -> BoundingSphere {
+    BoundingSphere {
+      center: self.center(),
+      radius: cgmath::MetricSpace::distance(self.min, self.max) / 2.0,
+    }
+  }
+}
+
+/// 包围球
+#[derive(Debug, Copy, Clone)]
+pub struct BoundingSphere {
+  pub center: cgmath::Point3<f32>,
+  pub radius: f32,
+}
+
+impl BoundingSphere {
+  /// 变换后的包围球，半径按最大的轴向缩放计算
+  pub fn transform(&self, matrix: &cgmath::Matrix4<f32>) -> Self {
+    let scale = [matrix.x, matrix.y, matrix.z].iter()
+      .map(|axis| cgmath::InnerSpace::magnitude(axis.truncate()))
+      .fold(0.0, f32::max);
+    Self {
+      center: cgmath::Transform::transform_point(matrix, self.center),
+      radius: self.radius * scale,
+    }
+  }
+}
+
 pub struct BuferInfo {
   pub vertices: Vec<Vertex>,
   pub indices: Vec<u16>,
 }
 
-/// 获取圆形的顶点数据和相应的顶点索引数据，用于构建顶点缓冲和索引缓冲；
-/// 
-/// `segment`为角度切割份数；`radius`为半径；`aspect`为屏幕宽高比；
-pub fn get_circle(segment: u8, radius: f32, aspect: f32) -> BuferInfo {
+impl BuferInfo {
+  pub fn bounds(&self) -> Aabb {
+    Aabb::from_points(self.vertices.iter().map(|vertex| cgmath::Point3::from(vertex.position)))
+  }
+
+  /// 以包围盒中心为球心，比包围盒的外接球更紧凑
+  pub fn bounding_sphere(&self) -> BoundingSphere {
+    let center = self.bounds().center();
+    let radius = self.vertices.iter()
+      .map(|vertex| cgmath::MetricSpace::distance(center, cgmath::Point3::from(vertex.position)))
+      .fold(0.0, f32::max);
+    BoundingSphere { center, radius }
+  }
+
+  /// 按索引展开为不共享顶点的三角形列表，顶点序号模3即为顶点在三角形中的位置
+  pub fn unindexed(&self) -> Vec<Vertex> {
+    self.indices.iter().map(|index| self.vertices[*index as usize]).collect()
+  }
+}
+
+/// 已上传到GPU的网格（顶点缓冲和索引缓冲）
+pub struct Mesh {
+  pub vertex_buffer: stats::Buffer,
+  pub index_buffer: stats::Buffer,
+  pub index_num: u32,
+  /// 展开后的顶点缓冲，仅在不支持线框多边形模式时用于重心坐标线框
+  pub unindexed_buffer: Option<stats::Buffer>,
+  /// 模型空间包围盒
+  pub bounds: Aabb,
+  /// 模型空间包围球
+  pub bounding_sphere: BoundingSphere,
+  /// 用于CPU射线检测
+  pub bvh: Bvh,
+}
+
+impl Mesh {
+  pub fn new(device: &wgpu::Device, buffer_info: &BuferInfo, label: &str) -> Self {
+    let vertex_buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some(&format!("{} Vertex Buffer", label)),
+      usage: wgpu::BufferUsages::VERTEX,
+      contents: bytemuck::cast_slice(&buffer_info.vertices),
+    });
+    let index_buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some(&format!("{} Index Buffer", label)),
+      usage: wgpu::BufferUsages::INDEX,
+      contents: bytemuck::cast_slice(&buffer_info.indices),
+    });
+    Self {
+      vertex_buffer,
+      index_buffer,
+      index_num: buffer_info.indices.len() as u32,
+      unindexed_buffer: None,
+      bounds: buffer_info.bounds(),
+      bounding_sphere: buffer_info.bounding_sphere(),
+      bvh: Bvh::new(buffer_info),
+    }
+  }
+
+  /// 创建展开后的顶点缓冲，顶点数与`index_num`相同
+  pub fn create_unindexed_buffer(&mut self, device: &wgpu::Device, buffer_info: &BuferInfo, label: &str) {
+    self.unindexed_buffer = Some(stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some(&format!("{} Unindexed Vertex Buffer", label)),
+      usage: wgpu::BufferUsages::VERTEX,
+      contents: bytemuck::cast_slice(&buffer_info.unindexed()),
+    }));
+  }
+}
+
+/// 获取UV球体的顶点数据和相应的顶点索引数据，用于构建顶点缓冲和索引缓冲；
+///
+/// `sectors`为经度切割份数；`stacks`为纬度切割份数；`radius`为半径；
+pub fn get_sphere(sectors: u16, stacks: u16, radius: f32) -> BuferInfo {
   let mut vertices: Vec<Vertex> = vec![];
   let mut indices: Vec<u16> = vec![];
   let color: [f32; 3] = [0.3, 0.5, 0.8];
-  let per_angle: f32 = std::f32::consts::PI * 2.0 / (segment as f32);
-  for idx in 0..=segment-1 {
-    let angle = idx as f32 * per_angle;
-    let x = radius * angle.cos() / aspect; // 除以宽高比，校正比例
-    let y = radius * angle.sin();
-    vertices.push(Vertex {
-      position: [x, y, 1.0],
-      color,
-      uv: [x, y]
-    });
-    indices.push(0);
-    indices.push(idx as u16);
-    indices.push(((idx + 1) % segment) as u16); // 逆时针索引
+  let pi = std::f32::consts::PI;
+  for i in 0..=stacks {
+    let v = i as f32 / stacks as f32;
+    let phi = pi / 2.0 - v * pi; // 从北极（+y）到南极（-y）
+    for j in 0..=sectors {
+      let u = j as f32 / sectors as f32;
+      let theta = u * pi * 2.0;
+      let normal = [phi.cos() * theta.sin(), phi.sin(), phi.cos() * theta.cos()];
+      vertices.push(Vertex {
+        position: [normal[0] * radius, normal[1] * radius, normal[2] * radius],
+        color,
+        uv: [u, v],
+        normal
+      });
+    }
+  }
+  // 接缝处的顶点重复一份，保证uv连续
+  let row = sectors + 1;
+  for i in 0..stacks {
+    for j in 0..sectors {
+      let k1 = i * row + j;
+      let k2 = k1 + row;
+      if i != 0 {
+        indices.extend_from_slice(&[k1, k2, k1 + 1]); // 逆时针索引
+      }
+      if i != stacks - 1 {
+        indices.extend_from_slice(&[k1 + 1, k2, k2 + 1]);
+      }
+    }
   }
   BuferInfo {
     vertices,
     indices
   }
 }
+
+/// 获取xz平面上的正方形平面（法线朝向+y），`size`为边长，`y`为平面高度；
+pub fn get_plane(size: f32, y: f32) -> BuferInfo {
+  let half = size / 2.0;
+  let color: [f32; 3] = [0.6, 0.6, 0.6];
+  let normal: [f32; 3] = [0.0, 1.0, 0.0];
+  let vertices = vec![
+    Vertex { position: [-half, y, -half], color, uv: [0.0, 0.0], normal },
+    Vertex { position: [-half, y, half], color, uv: [0.0, size], normal },
+    Vertex { position: [half, y, half], color, uv: [size, size], normal },
+    Vertex { position: [half, y, -half], color, uv: [size, 0.0], normal },
+  ];
+  BuferInfo {
+    vertices,
+    indices: vec![0, 1, 2, 0, 2, 3] // 从上方看为逆时针
+  }
+}
@@ -0,0 +1,180 @@
+use crate::texture::Texture;
+
+/// 边缘纹理格式：r为左侧边缘，g为上方边缘
+const EDGES_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg8Unorm;
+/// 混合权重纹理格式
+const WEIGHTS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// 形态学抗锯齿（SMAA 1x的简化版，见smaa.wgsl），与FXAA一样可替代MSAA
+///
+/// 三个pass：亮度边缘检测、沿边缘计算混合权重、按权重与相邻像素混合；
+pub struct Smaa {
+  color_layout: wgpu::BindGroupLayout,
+  edges_layout: wgpu::BindGroupLayout,
+  blend_layout: wgpu::BindGroupLayout,
+  edges_pipeline: wgpu::RenderPipeline,
+  weights_pipeline: wgpu::RenderPipeline,
+  blend_pipeline: wgpu::RenderPipeline,
+  resources: SmaaResources,
+}
+
+/// 与窗口尺寸相关的资源
+struct SmaaResources {
+  edges: Texture,
+  weights: Texture,
+  /// 以后处理链的第i张纹理为输入
+  edges_groups: Vec<wgpu::BindGroup>,
+  weights_group: wgpu::BindGroup,
+  blend_groups: Vec<wgpu::BindGroup>,
+}
+
+impl Smaa {
+  /// `inputs`为后处理链中交替读写的纹理
+  pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, inputs: &[Texture]) -> Self {
+    let color_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("smaa color bind group layout"),
+      entries: &[texture_entry(0)],
+    });
+    let edges_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("smaa edges bind group layout"),
+      entries: &[texture_entry(1)],
+    });
+    let blend_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("smaa blend bind group layout"),
+      entries: &[texture_entry(0), texture_entry(2)],
+    });
+    let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+      label: Some("SMAA Shader"),
+      source: wgpu::ShaderSource::Wgsl(include_str!("smaa.wgsl").into())
+    });
+    let edges_pipeline = create_pipeline(device, &color_layout, &shader, "fs_edges", EDGES_FORMAT);
+    let weights_pipeline = create_pipeline(device, &edges_layout, &shader, "fs_weights", WEIGHTS_FORMAT);
+    let blend_pipeline = create_pipeline(device, &blend_layout, &shader, "fs_blend", Texture::HDR_FORMAT);
+    let resources = SmaaResources::new(device, config, inputs, &color_layout, &edges_layout, &blend_layout);
+    Self {
+      color_layout,
+      edges_layout,
+      blend_layout,
+      edges_pipeline,
+      weights_pipeline,
+      blend_pipeline,
+      resources,
+    }
+  }
+
+  /// 窗口尺寸变化后重建边缘和权重纹理，`inputs`为重建后的后处理纹理
+  pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, inputs: &[Texture]) {
+    self.resources = SmaaResources::new(device, config, inputs, &self.color_layout, &self.edges_layout, &self.blend_layout);
+  }
+
+  /// 以后处理链的第`input`张纹理为输入，抗锯齿后写入`output`
+  pub fn run(&self, encoder: &mut wgpu::CommandEncoder, input: usize, output: &wgpu::TextureView) {
+    let resources = &self.resources;
+    draw(encoder, &self.edges_pipeline, &resources.edges_groups[input], &resources.edges.view, "SMAA Edges Pass");
+    draw(encoder, &self.weights_pipeline, &resources.weights_group, &resources.weights.view, "SMAA Weights Pass");
+    draw(encoder, &self.blend_pipeline, &resources.blend_groups[input], output, "SMAA Blend Pass");
+  }
+}
+
+impl SmaaResources {
+  fn new(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    inputs: &[Texture],
+    color_layout: &wgpu::BindGroupLayout,
+    edges_layout: &wgpu::BindGroupLayout,
+    blend_layout: &wgpu::BindGroupLayout,
+  ) -> Self {
+    let edges = Texture::create_render_target(device, config.width, config.height, 1, EDGES_FORMAT, "smaa_edges");
+    let weights = Texture::create_render_target(device, config.width, config.height, 1, WEIGHTS_FORMAT, "smaa_weights");
+    let create_group = |layout: &wgpu::BindGroupLayout, entries: &[(u32, &wgpu::TextureView)]| {
+      device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("smaa bind group"),
+        layout,
+        entries: &entries.iter().map(|(binding, view)| wgpu::BindGroupEntry {
+          binding: *binding,
+          resource: wgpu::BindingResource::TextureView(view)
+        }).collect::<Vec<_>>(),
+      })
+    };
+    let edges_groups = inputs.iter().map(|input| create_group(color_layout, &[(0, &input.view)])).collect();
+    let weights_group = create_group(edges_layout, &[(1, &edges.view)]);
+    let blend_groups = inputs.iter().map(|input| create_group(blend_layout, &[(0, &input.view), (2, &weights.view)])).collect();
+    Self {
+      edges,
+      weights,
+      edges_groups,
+      weights_group,
+      blend_groups,
+    }
+  }
+}
+
+/// 着色器中只用`textureLoad`读取，不需要采样器
+fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+  wgpu::BindGroupLayoutEntry {
+    binding,
+    visibility: wgpu::ShaderStages::FRAGMENT,
+    ty: wgpu::BindingType::Texture {
+      multisampled: false,
+      view_dimension: wgpu::TextureViewDimension::D2,
+      sample_type: wgpu::TextureSampleType::Float { filterable: false },
+    },
+    count: None
+  }
+}
+
+fn create_pipeline(
+  device: &wgpu::Device,
+  layout: &wgpu::BindGroupLayout,
+  shader: &wgpu::ShaderModule,
+  entry_point: &str,
+  format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+  let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    label: Some("SMAA Pipeline Layout"),
+    bind_group_layouts: &[layout],
+    push_constant_ranges: &[]
+  });
+  device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    label: Some("SMAA Pipeline"),
+    layout: Some(&pipeline_layout),
+    vertex: wgpu::VertexState {
+      module: shader,
+      entry_point: "vs_main",
+      buffers: &[]
+    },
+    fragment: Some(wgpu::FragmentState {
+      module: shader,
+      entry_point,
+      targets: &[wgpu::ColorTargetState {
+        format,
+        blend: None,
+        write_mask: wgpu::ColorWrites::ALL,
+      }],
+    }),
+    primitive: wgpu::PrimitiveState::default(),
+    depth_stencil: None,
+    multisample: wgpu::MultisampleState::default(),
+    multiview: None
+  })
+}
+
+/// 绘制一个全屏三角形，目标先清为0（边缘pass中没有边缘的像素直接丢弃）
+fn draw(encoder: &mut wgpu::CommandEncoder, pipeline: &wgpu::RenderPipeline, group: &wgpu::BindGroup, output: &wgpu::TextureView, label: &str) {
+  let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+    label: Some(label),
+    color_attachments: &[wgpu::RenderPassColorAttachment {
+      view: output,
+      resolve_target: None,
+      ops: wgpu::Operations {
+        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+        store: true,
+      }
+    }],
+    depth_stencil_attachment: None
+  });
+  render_pass.set_pipeline(pipeline);
+  render_pass.set_bind_group(0, group, &[]);
+  render_pass.draw(0..3, 0..1);
+}
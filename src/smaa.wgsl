@@ -0,0 +1,154 @@
+// 形态学抗锯齿（SMAA 1x的简化版）：亮度边缘检测 -> 混合权重 -> 邻域混合
+//
+// 混合权重按MLAA/SMAA的思路沿边缘搜索两端并判断端点处的交叉边缘，
+// 但覆盖面积直接由重建的折线解析计算，不使用预计算的面积纹理和搜索纹理，也不处理对角线
+
+struct VertexOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+};
+
+/// 边缘检测的输入颜色（已完成色调映射和gamma校正）
+[[group(0), binding(0)]]
+var color_t: texture_2d<f32>;
+/// r为像素左侧的边缘，g为像素上方的边缘
+[[group(0), binding(1)]]
+var edges_t: texture_2d<f32>;
+/// r/g为与上方像素的混合权重（本像素向上、上方像素向下），b/a为与左侧像素的混合权重
+[[group(0), binding(2)]]
+var weights_t: texture_2d<f32>;
+
+let EDGE_THRESHOLD: f32 = 0.1;
+/// 局部对比度自适应：邻近边缘明显更强时忽略较弱的边缘
+let LOCAL_CONTRAST_FACTOR: f32 = 2.0;
+/// 沿边缘搜索的最大像素数
+let MAX_SEARCH_STEPS: i32 = 16;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] in_vertex_index: u32) -> VertexOutput {
+  var out: VertexOutput;
+  let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+  let y = f32(i32(in_vertex_index >> 1u) * 4 - 1);
+  out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+  return out;
+}
+
+fn load_color(position: vec2<i32>) -> vec4<f32> {
+  let size = textureDimensions(color_t);
+  return textureLoad(color_t, clamp(position, vec2<i32>(0), size - vec2<i32>(1)), 0);
+}
+
+fn luma(position: vec2<i32>) -> f32 {
+  return dot(load_color(position).rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+[[stage(fragment)]]
+fn fs_edges(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let p = vec2<i32>(in.clip_position.xy);
+  let l = luma(p);
+  let delta_left = abs(l - luma(p + vec2<i32>(-1, 0)));
+  let delta_top = abs(l - luma(p + vec2<i32>(0, -1)));
+  var edges = step(vec2<f32>(EDGE_THRESHOLD), vec2<f32>(delta_left, delta_top));
+  if (edges.x + edges.y == 0.0) {
+    discard;
+  }
+  let delta_right = abs(l - luma(p + vec2<i32>(1, 0)));
+  let delta_bottom = abs(l - luma(p + vec2<i32>(0, 1)));
+  let delta_left2 = abs(luma(p + vec2<i32>(-1, 0)) - luma(p + vec2<i32>(-2, 0)));
+  let delta_top2 = abs(luma(p + vec2<i32>(0, -1)) - luma(p + vec2<i32>(0, -2)));
+  let max_delta = max(max(max(delta_left, delta_top), max(delta_right, delta_bottom)), max(delta_left2, delta_top2));
+  edges = edges * step(vec2<f32>(max_delta), LOCAL_CONTRAST_FACTOR * vec2<f32>(delta_left, delta_top));
+  return vec4<f32>(edges, 0.0, 1.0);
+}
+
+fn edge(position: vec2<i32>) -> vec2<f32> {
+  let size = textureDimensions(edges_t);
+  if (any(position < vec2<i32>(0)) || any(position >= size)) {
+    return vec2<f32>(0.0);
+  }
+  return textureLoad(edges_t, position, 0).rg;
+}
+
+/// 线段(x0, y0)-(x1, y1)在[a, b]区间上的积分
+fn segment_area(x0: f32, y0: f32, x1: f32, y1: f32, a: f32, b: f32) -> f32 {
+  let start = max(x0, a);
+  let end = min(x1, b);
+  if (end <= start) {
+    return 0.0;
+  }
+  let slope = (y1 - y0) / (x1 - x0);
+  let y_start = y0 + slope * (start - x0);
+  let y_end = y0 + slope * (end - x0);
+  return (y_start + y_end) * 0.5 * (end - start);
+}
+
+/// 长度为`d1 + d2 + 1`的边缘上，当前像素（位于[0, 1]）被重建折线覆盖的面积
+///
+/// 折线从左端点的`h1`经边缘中点的0到右端点的`h2`；返回（偏向本像素一侧的面积，偏向另一侧的面积）
+fn coverage(d1: f32, d2: f32, h1: f32, h2: f32) -> vec2<f32> {
+  let left = -d1;
+  let right = d2 + 1.0;
+  let middle = (left + right) * 0.5;
+  let a1 = segment_area(left, h1, middle, 0.0, 0.0, 1.0);
+  let a2 = segment_area(middle, 0.0, right, h2, 0.0, 1.0);
+  return vec2<f32>(max(-a1, 0.0) + max(-a2, 0.0), max(a1, 0.0) + max(a2, 0.0));
+}
+
+/// 端点处的交叉边缘：只在本像素一侧时为-0.5，只在另一侧时为0.5，否则为0
+fn end_height(own_side: f32, other_side: f32) -> f32 {
+  return (other_side - own_side) * 0.5;
+}
+
+[[stage(fragment)]]
+fn fs_weights(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let p = vec2<i32>(in.clip_position.xy);
+  let e = edge(p);
+  var weights = vec4<f32>(0.0);
+  if (e.y > 0.0) {
+    // 上方的水平边缘：向左右搜索端点
+    var left = 0;
+    for (; left < MAX_SEARCH_STEPS && edge(p + vec2<i32>(-left - 1, 0)).y > 0.0; left = left + 1) {}
+    var right = 0;
+    for (; right < MAX_SEARCH_STEPS && edge(p + vec2<i32>(right + 1, 0)).y > 0.0; right = right + 1) {}
+    let h1 = end_height(edge(p + vec2<i32>(-left, 0)).x, edge(p + vec2<i32>(-left, -1)).x);
+    let h2 = end_height(edge(p + vec2<i32>(right + 1, 0)).x, edge(p + vec2<i32>(right + 1, -1)).x);
+    weights = vec4<f32>(coverage(f32(left), f32(right), h1, h2), weights.zw);
+  }
+  if (e.x > 0.0) {
+    // 左侧的竖直边缘：向上下搜索端点
+    var up = 0;
+    for (; up < MAX_SEARCH_STEPS && edge(p + vec2<i32>(0, -up - 1)).x > 0.0; up = up + 1) {}
+    var down = 0;
+    for (; down < MAX_SEARCH_STEPS && edge(p + vec2<i32>(0, down + 1)).x > 0.0; down = down + 1) {}
+    let h1 = end_height(edge(p + vec2<i32>(0, -up)).y, edge(p + vec2<i32>(-1, -up)).y);
+    let h2 = end_height(edge(p + vec2<i32>(0, down + 1)).y, edge(p + vec2<i32>(-1, down + 1)).y);
+    weights = vec4<f32>(weights.xy, coverage(f32(up), f32(down), h1, h2));
+  }
+  return weights;
+}
+
+fn weight(position: vec2<i32>) -> vec4<f32> {
+  let size = textureDimensions(weights_t);
+  if (any(position < vec2<i32>(0)) || any(position >= size)) {
+    return vec4<f32>(0.0);
+  }
+  return textureLoad(weights_t, position, 0);
+}
+
+[[stage(fragment)]]
+fn fs_blend(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let p = vec2<i32>(in.clip_position.xy);
+  let own = weight(p);
+  let top = own.r;
+  let bottom = weight(p + vec2<i32>(0, 1)).g;
+  let left = own.b;
+  let right = weight(p + vec2<i32>(1, 0)).a;
+  let color = load_color(p);
+  // 与SMAA相同，只沿权重较大的方向混合
+  if (max(top, bottom) >= max(left, right)) {
+    if (top + bottom == 0.0) {
+      return color;
+    }
+    return color * (1.0 - top - bottom) + load_color(p + vec2<i32>(0, -1)) * top + load_color(p + vec2<i32>(0, 1)) * bottom;
+  }
+  return color * (1.0 - left - right) + load_color(p + vec2<i32>(-1, 0)) * left + load_color(p + vec2<i32>(1, 0)) * right;
+}
@@ -0,0 +1,378 @@
+use cgmath::SquareMatrix;
+use crate::camera::Camera;
+use crate::texture::Texture;
+use crate::stats;
+
+/// 采样核的最大采样数
+pub const MAX_SAMPLES: usize = 64;
+/// 环境光遮蔽纹理格式
+const OCCLUSION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+const NOISE_SIZE: u32 = 4;
+
+/// 屏幕空间环境光遮蔽参数
+#[derive(Debug, Copy, Clone)]
+pub struct SsaoSettings {
+  pub enabled: bool,
+  /// 采样半球半径（视图空间单位）
+  pub radius: f32,
+  /// 深度比较偏移，用于消除自遮挡
+  pub bias: f32,
+  /// 每个像素的采样数，不超过`MAX_SAMPLES`
+  pub sample_count: u32,
+}
+
+impl Default for SsaoSettings {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      radius: 0.5,
+      bias: 0.025,
+      sample_count: 16,
+    }
+  }
+}
+
+/// SSAO相关uniform变量，与ssao.wgsl中的`SsaoUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SsaoUniform {
+  projection: [[f32; 4]; 4],
+  inverse_projection: [[f32; 4]; 4],
+  kernel: [[f32; 4]; MAX_SAMPLES],
+  radius: f32,
+  bias: f32,
+  sample_count: u32,
+  padding: u32,
+}
+
+/// 屏幕空间环境光遮蔽
+///
+/// 需要先通过深度预pass得到当前帧的深度，计算出的遮蔽项在主pass中与环境光相乘；
+/// 主pass通过`occlusion_layout`/`occlusion_group`（pbr.wgsl中的group 4）读取遮蔽项
+pub struct Ssao {
+  pub settings: SsaoSettings,
+  uniform: SsaoUniform,
+  buffer: stats::Buffer,
+  noise: Texture,
+  layout: wgpu::BindGroupLayout,
+  blur_layout: wgpu::BindGroupLayout,
+  pub occlusion_layout: wgpu::BindGroupLayout,
+  /// 单采样深度的着色器，模糊pass也使用它
+  shader: wgpu::ShaderModule,
+  pipeline: wgpu::RenderPipeline,
+  blur_pipeline: wgpu::RenderPipeline,
+  /// 当前管线对应的深度纹理多重采样数
+  sample_count: u32,
+  /// 未模糊的遮蔽项
+  raw: Texture,
+  /// 模糊后的遮蔽项，供主pass使用
+  occlusion: Texture,
+  group: wgpu::BindGroup,
+  blur_group: wgpu::BindGroup,
+  pub occlusion_group: wgpu::BindGroup,
+}
+
+/// 确定性的伪随机数（xorshift），保证每次运行的采样核一致
+struct Random(u32);
+
+impl Random {
+  fn next(&mut self) -> f32 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 17;
+    self.0 ^= self.0 << 5;
+    (self.0 >> 8) as f32 / (1 << 24) as f32
+  }
+}
+
+/// 生成切线空间+z半球内的采样核，采样点向中心聚集
+fn create_kernel(random: &mut Random) -> [[f32; 4]; MAX_SAMPLES] {
+  let mut kernel = [[0.0; 4]; MAX_SAMPLES];
+  for (i, sample) in kernel.iter_mut().enumerate() {
+    let direction = cgmath::Vector3::new(random.next() * 2.0 - 1.0, random.next() * 2.0 - 1.0, random.next().max(0.05));
+    let t = i as f32 / MAX_SAMPLES as f32;
+    let scale = 0.1 + 0.9 * t * t;
+    let v = cgmath::InnerSpace::normalize(direction) * random.next() * scale;
+    *sample = [v.x, v.y, v.z, 0.0];
+  }
+  kernel
+}
+
+impl Ssao {
+  pub fn new(
+    settings: SsaoSettings,
+    camera: &Camera,
+    depth_texture: &Texture,
+    sample_count: u32,
+    config: &wgpu::SurfaceConfiguration,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+  ) -> Self {
+    let mut random = Random(0x2545f491);
+    let mut uniform = SsaoUniform {
+      projection: cgmath::Matrix4::identity().into(),
+      inverse_projection: cgmath::Matrix4::identity().into(),
+      kernel: create_kernel(&mut random),
+      radius: 0.0,
+      bias: 0.0,
+      sample_count: 0,
+      padding: 0,
+    };
+    uniform.update(&settings, camera);
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("SSAO buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    // 噪声纹理：切线平面内的随机方向，编码到[0, 1]
+    let noise_data = (0..NOISE_SIZE * NOISE_SIZE).flat_map(|_| {
+      let x = random.next();
+      let y = random.next();
+      [(x * 255.0) as u8, (y * 255.0) as u8, 128, 255]
+    }).collect::<Vec<_>>();
+    let noise = Texture::from_rgba8(device, queue, &noise_data, (NOISE_SIZE, NOISE_SIZE), wgpu::TextureFormat::Rgba8Unorm, Some("ssao_noise"));
+    let layout = create_layout(device, sample_count);
+    let blur_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("ssao blur bind group layout"),
+      entries: &[texture_entry(3)]
+    });
+    let occlusion_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("ssao occlusion bind group layout"),
+      entries: &[texture_entry(0)]
+    });
+    let shader = create_shader(device, 1);
+    let pipeline = if sample_count > 1 {
+      create_pipeline(device, &layout, &create_shader(device, sample_count), "fs_ssao")
+    } else {
+      create_pipeline(device, &layout, &shader, "fs_ssao")
+    };
+    let blur_pipeline = create_pipeline(device, &blur_layout, &shader, "fs_blur");
+    let raw = Texture::create_render_target(device, config.width, config.height, 1, OCCLUSION_FORMAT, "ssao_raw");
+    let occlusion = Texture::create_render_target(device, config.width, config.height, 1, OCCLUSION_FORMAT, "ssao_occlusion");
+    let group = create_group(device, &layout, depth_texture, &noise, &buffer);
+    let blur_group = create_texture_group(device, &blur_layout, 3, &raw, "ssao blur bind group");
+    let occlusion_group = create_texture_group(device, &occlusion_layout, 0, &occlusion, "ssao occlusion bind group");
+    Self {
+      settings,
+      uniform,
+      buffer,
+      noise,
+      layout,
+      blur_layout,
+      occlusion_layout,
+      shader,
+      pipeline,
+      blur_pipeline,
+      sample_count,
+      raw,
+      occlusion,
+      group,
+      blur_group,
+      occlusion_group,
+    }
+  }
+
+  /// 窗口尺寸或多重采样数变化后重建纹理和`occlusion_group`
+  pub fn resize(
+    &mut self,
+    depth_texture: &Texture,
+    sample_count: u32,
+    config: &wgpu::SurfaceConfiguration,
+    device: &wgpu::Device,
+  ) {
+    if sample_count != self.sample_count {
+      // 深度纹理是否多重采样决定了着色器中的纹理类型
+      self.sample_count = sample_count;
+      self.layout = create_layout(device, sample_count);
+      self.pipeline = if sample_count > 1 {
+        create_pipeline(device, &self.layout, &create_shader(device, sample_count), "fs_ssao")
+      } else {
+        create_pipeline(device, &self.layout, &self.shader, "fs_ssao")
+      };
+    }
+    self.raw = Texture::create_render_target(device, config.width, config.height, 1, OCCLUSION_FORMAT, "ssao_raw");
+    self.occlusion = Texture::create_render_target(device, config.width, config.height, 1, OCCLUSION_FORMAT, "ssao_occlusion");
+    self.group = create_group(device, &self.layout, depth_texture, &self.noise, &self.buffer);
+    self.blur_group = create_texture_group(device, &self.blur_layout, 3, &self.raw, "ssao blur bind group");
+    self.occlusion_group = create_texture_group(device, &self.occlusion_layout, 0, &self.occlusion, "ssao occlusion bind group");
+  }
+
+  /// 相机投影或参数变化后更新uniform
+  pub fn update(&mut self, settings: SsaoSettings, camera: &Camera, queue: &wgpu::Queue) {
+    self.settings = settings;
+    self.uniform.update(&settings, camera);
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+
+  /// 计算遮蔽项；关闭时将遮蔽纹理清为1（无遮蔽）
+  pub fn run(&self, encoder: &mut wgpu::CommandEncoder) {
+    if !self.settings.enabled {
+      draw(encoder, None, &self.occlusion.view);
+      return;
+    }
+    draw(encoder, Some((&self.pipeline, &self.group)), &self.raw.view);
+    draw(encoder, Some((&self.blur_pipeline, &self.blur_group)), &self.occlusion.view);
+  }
+}
+
+impl SsaoUniform {
+  fn update(&mut self, settings: &SsaoSettings, camera: &Camera) {
+    let projection = camera.get_projection_matrix();
+    self.projection = projection.into();
+    self.inverse_projection = projection.invert().unwrap_or_else(cgmath::Matrix4::identity).into();
+    self.radius = settings.radius.max(0.01);
+    self.bias = settings.bias;
+    self.sample_count = settings.sample_count.clamp(1, MAX_SAMPLES as u32);
+  }
+}
+
+fn create_layout(device: &wgpu::Device, sample_count: u32) -> wgpu::BindGroupLayout {
+  device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+    label: Some("ssao bind group layout"),
+    entries: &[
+      wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+          multisampled: sample_count > 1,
+          view_dimension: wgpu::TextureViewDimension::D2,
+          sample_type: wgpu::TextureSampleType::Depth,
+        },
+        count: None
+      },
+      texture_entry(1),
+      wgpu::BindGroupLayoutEntry {
+        binding: 2,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+          ty: wgpu::BufferBindingType::Uniform,
+          has_dynamic_offset: false,
+          min_binding_size: None
+        },
+        count: None
+      }
+    ]
+  })
+}
+
+/// 不可过滤的单采样纹理，着色器中只用`textureLoad`读取
+fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+  wgpu::BindGroupLayoutEntry {
+    binding,
+    visibility: wgpu::ShaderStages::FRAGMENT,
+    ty: wgpu::BindingType::Texture {
+      multisampled: false,
+      view_dimension: wgpu::TextureViewDimension::D2,
+      sample_type: wgpu::TextureSampleType::Float { filterable: false },
+    },
+    count: None
+  }
+}
+
+/// 深度纹理是否多重采样对应不同的深度读取模块，拼接在ssao.wgsl之前
+fn create_shader(device: &wgpu::Device, sample_count: u32) -> wgpu::ShaderModule {
+  let source = if sample_count > 1 {
+    concat!(include_str!("ssao_depth_msaa.wgsl"), include_str!("ssao.wgsl"))
+  } else {
+    concat!(include_str!("ssao_depth.wgsl"), include_str!("ssao.wgsl"))
+  };
+  device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+    label: Some("SSAO Shader"),
+    source: wgpu::ShaderSource::Wgsl(source.into())
+  })
+}
+
+fn create_pipeline(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, shader: &wgpu::ShaderModule, entry_point: &str) -> wgpu::RenderPipeline {
+  let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    label: Some("SSAO Pipeline Layout"),
+    bind_group_layouts: &[layout],
+    push_constant_ranges: &[]
+  });
+  device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    label: Some("SSAO Pipeline"),
+    layout: Some(&pipeline_layout),
+    vertex: wgpu::VertexState {
+      module: shader,
+      entry_point: "vs_main",
+      buffers: &[]
+    },
+    fragment: Some(wgpu::FragmentState {
+      module: shader,
+      entry_point,
+      targets: &[wgpu::ColorTargetState {
+        format: OCCLUSION_FORMAT,
+        blend: None,
+        write_mask: wgpu::ColorWrites::ALL,
+      }],
+    }),
+    primitive: wgpu::PrimitiveState::default(),
+    depth_stencil: None,
+    multisample: wgpu::MultisampleState::default(),
+    multiview: None
+  })
+}
+
+fn create_group(
+  device: &wgpu::Device,
+  layout: &wgpu::BindGroupLayout,
+  depth_texture: &Texture,
+  noise: &Texture,
+  buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+  // 深度纹理可能带模板，采样时只能使用深度部分
+  let depth_view = depth_texture.texture.create_view(&wgpu::TextureViewDescriptor {
+    aspect: wgpu::TextureAspect::DepthOnly,
+    ..Default::default()
+  });
+  device.create_bind_group(&wgpu::BindGroupDescriptor {
+    label: Some("ssao bind group"),
+    layout,
+    entries: &[
+      wgpu::BindGroupEntry {
+        binding: 0,
+        resource: wgpu::BindingResource::TextureView(&depth_view)
+      },
+      wgpu::BindGroupEntry {
+        binding: 1,
+        resource: wgpu::BindingResource::TextureView(&noise.view)
+      },
+      wgpu::BindGroupEntry {
+        binding: 2,
+        resource: buffer.as_entire_binding()
+      }
+    ]
+  })
+}
+
+fn create_texture_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, binding: u32, texture: &Texture, label: &str) -> wgpu::BindGroup {
+  device.create_bind_group(&wgpu::BindGroupDescriptor {
+    label: Some(label),
+    layout,
+    entries: &[
+      wgpu::BindGroupEntry {
+        binding,
+        resource: wgpu::BindingResource::TextureView(&texture.view)
+      }
+    ]
+  })
+}
+
+/// 绘制全屏三角形到`output`；`pipeline`为None时只将其清为1
+fn draw(encoder: &mut wgpu::CommandEncoder, pipeline: Option<(&wgpu::RenderPipeline, &wgpu::BindGroup)>, output: &wgpu::TextureView) {
+  let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+    label: Some("SSAO Pass"),
+    color_attachments: &[wgpu::RenderPassColorAttachment {
+      view: output,
+      resolve_target: None,
+      ops: wgpu::Operations {
+        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+        store: true,
+      }
+    }],
+    depth_stencil_attachment: None
+  });
+  if let Some((pipeline, group)) = pipeline {
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, group, &[]);
+    render_pass.draw(0..3, 0..1);
+  }
+}
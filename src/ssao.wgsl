@@ -0,0 +1,104 @@
+// 屏幕空间环境光遮蔽：由深度重建视图空间位置，在法线半球内采样判断遮挡
+//
+// 深度纹理的声明和读取在ssao_depth.wgsl（单采样）或ssao_depth_msaa.wgsl（多重采样）中，
+// 由程序拼接在本文件之前，两者都提供`depth_size`和`load_depth`
+
+struct VertexOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+};
+
+struct SsaoUniform {
+  projection: mat4x4<f32>;
+  inverse_projection: mat4x4<f32>;
+  /// 切线空间半球内的采样点
+  kernel: array<vec4<f32>, 64>;
+  radius: f32;
+  bias: f32;
+  sample_count: u32;
+  padding: u32;
+};
+
+[[group(0), binding(1)]]
+var noise_t: texture_2d<f32>;
+[[group(0), binding(2)]]
+var<uniform> ssao: SsaoUniform;
+/// 仅在模糊pass中使用
+[[group(0), binding(3)]]
+var occlusion_t: texture_2d<f32>;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] in_vertex_index: u32) -> VertexOutput {
+  var out: VertexOutput;
+  let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+  let y = f32(i32(in_vertex_index >> 1u) * 4 - 1);
+  out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+  return out;
+}
+
+/// 由像素坐标处的深度重建视图空间位置
+fn view_position(coord: vec2<i32>, size: vec2<i32>) -> vec3<f32> {
+  let clamped = clamp(coord, vec2<i32>(0), size - vec2<i32>(1));
+  let depth = load_depth(clamped);
+  let uv = (vec2<f32>(clamped) + vec2<f32>(0.5)) / vec2<f32>(size);
+  let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+  let position = ssao.inverse_projection * ndc;
+  return position.xyz / position.w;
+}
+
+/// 选取深度变化较小的一侧计算差分，避免物体边缘处的法线错误
+fn min_diff(center: vec3<f32>, a: vec3<f32>, b: vec3<f32>) -> vec3<f32> {
+  let da = a - center;
+  let db = center - b;
+  if (abs(da.z) < abs(db.z)) {
+    return da;
+  }
+  return db;
+}
+
+[[stage(fragment)]]
+fn fs_ssao(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let size = depth_size();
+  let coord = vec2<i32>(in.clip_position.xy);
+  if (load_depth(coord) >= 1.0) {
+    return vec4<f32>(1.0); // 背景无遮蔽
+  }
+  let position = view_position(coord, size);
+  let ddx = min_diff(position, view_position(coord + vec2<i32>(1, 0), size), view_position(coord - vec2<i32>(1, 0), size));
+  let ddy = min_diff(position, view_position(coord + vec2<i32>(0, 1), size), view_position(coord - vec2<i32>(0, 1), size));
+  let normal = normalize(cross(ddy, ddx));
+
+  // 用4x4平铺的随机向量旋转采样核，再由模糊pass消除噪声
+  let noise = textureLoad(noise_t, coord % vec2<i32>(4), 0).xyz * 2.0 - vec3<f32>(1.0);
+  let tangent = normalize(noise - normal * dot(noise, normal));
+  let bitangent = cross(normal, tangent);
+  let tbn = mat3x3<f32>(tangent, bitangent, normal);
+
+  var occlusion = 0.0;
+  for (var i = 0u; i < ssao.sample_count; i = i + 1u) {
+    let sample_position = position + tbn * ssao.kernel[i].xyz * ssao.radius;
+    let clip = ssao.projection * vec4<f32>(sample_position, 1.0);
+    let uv = clip.xy / clip.w * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5);
+    let sample_z = view_position(vec2<i32>(uv * vec2<f32>(size)), size).z;
+    // 距离过远的遮挡物不计入，避免物体轮廓处出现暗边
+    let range_check = smoothStep(0.0, 1.0, ssao.radius / abs(position.z - sample_z));
+    if (sample_z >= sample_position.z + ssao.bias) {
+      occlusion = occlusion + range_check;
+    }
+  }
+  return vec4<f32>(1.0 - occlusion / f32(max(ssao.sample_count, 1u)));
+}
+
+/// 与噪声纹理尺寸一致的4x4均值模糊
+[[stage(fragment)]]
+fn fs_blur(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  let size = textureDimensions(occlusion_t, 0);
+  let coord = vec2<i32>(in.clip_position.xy);
+  var result = 0.0;
+  for (var y = -2; y < 2; y = y + 1) {
+    for (var x = -2; x < 2; x = x + 1) {
+      let offset = clamp(coord + vec2<i32>(x, y), vec2<i32>(0), size - vec2<i32>(1));
+      result = result + textureLoad(occlusion_t, offset, 0).r;
+    }
+  }
+  return vec4<f32>(result / 16.0);
+}
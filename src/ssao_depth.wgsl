@@ -0,0 +1,12 @@
+// SSAO的深度输入：单采样深度纹理
+
+[[group(0), binding(0)]]
+var depth_t: texture_depth_2d;
+
+fn depth_size() -> vec2<i32> {
+  return textureDimensions(depth_t);
+}
+
+fn load_depth(coord: vec2<i32>) -> f32 {
+  return textureLoad(depth_t, coord, 0);
+}
@@ -0,0 +1,12 @@
+// SSAO的深度输入：多重采样深度纹理，只读取第0个采样
+
+[[group(0), binding(0)]]
+var depth_t: texture_depth_multisampled_2d;
+
+fn depth_size() -> vec2<i32> {
+  return textureDimensions(depth_t);
+}
+
+fn load_depth(coord: vec2<i32>) -> f32 {
+  return textureLoad(depth_t, coord, 0);
+}
@@ -0,0 +1,342 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{
+  AtomicU64,
+  Ordering
+};
+use std::time::Duration;
+use wgpu::util::DeviceExt;
+
+/// 滚动统计的帧数
+const WINDOW: usize = 240;
+/// 运行以来帧时间直方图的桶宽（毫秒）和桶数，超出范围的帧计入最后一个桶
+const HISTOGRAM_BUCKET_MS: f32 = 0.1;
+const HISTOGRAM_BUCKETS: usize = 1000;
+
+/// 当前存活的缓冲和纹理字节数，资源释放时扣除
+static BUFFER_BYTES: AtomicU64 = AtomicU64::new(0);
+static TEXTURE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// 计入占用量的GPU资源，释放（drop）时从计数中扣除，可像原资源一样使用
+#[derive(Debug)]
+pub struct Tracked<T> {
+  resource: T,
+  bytes: u64,
+  counter: &'static AtomicU64,
+}
+
+impl<T> Tracked<T> {
+  fn new(resource: T, bytes: u64, counter: &'static AtomicU64) -> Self {
+    counter.fetch_add(bytes, Ordering::Relaxed);
+    Self { resource, bytes, counter }
+  }
+}
+
+impl<T> std::ops::Deref for Tracked<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.resource
+  }
+}
+
+impl<T> Drop for Tracked<T> {
+  fn drop(&mut self) {
+    self.counter.fetch_sub(self.bytes, Ordering::Relaxed);
+  }
+}
+
+pub type Buffer = Tracked<wgpu::Buffer>;
+pub type Texture = Tracked<wgpu::Texture>;
+
+/// 创建缓冲并计入占用量
+pub fn create_buffer(device: &wgpu::Device, descriptor: &wgpu::BufferDescriptor) -> Buffer {
+  Tracked::new(device.create_buffer(descriptor), descriptor.size, &BUFFER_BYTES)
+}
+
+pub fn create_buffer_init(device: &wgpu::Device, descriptor: &wgpu::util::BufferInitDescriptor) -> Buffer {
+  Tracked::new(device.create_buffer_init(descriptor), descriptor.contents.len() as u64, &BUFFER_BYTES)
+}
+
+/// 创建纹理并计入占用量，按格式、mip层级和采样数估算
+pub fn create_texture(device: &wgpu::Device, descriptor: &wgpu::TextureDescriptor) -> Texture {
+  let info = descriptor.format.describe();
+  let (block_width, block_height) = (info.block_dimensions.0 as u64, info.block_dimensions.1 as u64);
+  let is_3d = descriptor.dimension == wgpu::TextureDimension::D3;
+  let bytes = (0..descriptor.mip_level_count).map(|level| {
+    let size = descriptor.size.mip_level_size(level, is_3d);
+    let blocks = (size.width as u64).div_ceil(block_width) * (size.height as u64).div_ceil(block_height);
+    blocks * size.depth_or_array_layers as u64 * info.block_size as u64
+  }).sum::<u64>();
+  Tracked::new(device.create_texture(descriptor), bytes * descriptor.sample_count as u64, &TEXTURE_BYTES)
+}
+
+/// 当前存活的（缓冲，纹理）字节数，为按描述估算的值，不含驱动的对齐和额外开销
+pub fn allocated() -> (u64, u64) {
+  (BUFFER_BYTES.load(Ordering::Relaxed), TEXTURE_BYTES.load(Ordering::Relaxed))
+}
+
+/// 一帧中提交的网格绘制
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DrawStats {
+  pub draw_calls: u64,
+  pub triangles: u64,
+  pub instances: u64,
+}
+
+/// 帧时间（毫秒）的分布
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FrameTimes {
+  pub min: f32,
+  pub avg: f32,
+  pub max: f32,
+  pub p50: f32,
+  pub p95: f32,
+  pub p99: f32,
+}
+
+impl FrameTimes {
+  fn new(samples: impl Iterator<Item = f32>) -> Self {
+    let mut sorted = samples.collect::<Vec<_>>();
+    if sorted.is_empty() {
+      return Self::default();
+    }
+    sorted.sort_by(f32::total_cmp);
+    let percentile = |p: f32| sorted[((sorted.len() - 1) as f32 * p).round() as usize];
+    Self {
+      min: sorted[0],
+      avg: sorted.iter().sum::<f32>() / sorted.len() as f32,
+      max: sorted[sorted.len() - 1],
+      p50: percentile(0.5),
+      p95: percentile(0.95),
+      p99: percentile(0.99),
+    }
+  }
+
+  pub fn fps(&self) -> f32 {
+    if self.avg > 0.0 { 1000.0 / self.avg } else { 0.0 }
+  }
+
+  fn to_json(self) -> String {
+    format!(
+      "{{\"min\": {:.3}, \"avg\": {:.3}, \"max\": {:.3}, \"p50\": {:.3}, \"p95\": {:.3}, \"p99\": {:.3}}}",
+      self.min, self.avg, self.max, self.p50, self.p95, self.p99
+    )
+  }
+}
+
+/// 运行以来的帧时间分布，内存占用固定，百分位精确到一个桶宽
+struct FrameHistogram {
+  buckets: Vec<u64>,
+  count: u64,
+  /// 帧时间总和（毫秒）
+  sum: f64,
+  min: f32,
+  max: f32,
+}
+
+impl FrameHistogram {
+  fn new() -> Self {
+    Self {
+      buckets: vec![0; HISTOGRAM_BUCKETS],
+      count: 0,
+      sum: 0.0,
+      min: f32::MAX,
+      max: 0.0,
+    }
+  }
+
+  fn add(&mut self, ms: f32) {
+    let index = ((ms / HISTOGRAM_BUCKET_MS) as usize).min(HISTOGRAM_BUCKETS - 1);
+    self.buckets[index] += 1;
+    self.count += 1;
+    self.sum += ms as f64;
+    self.min = self.min.min(ms);
+    self.max = self.max.max(ms);
+  }
+
+  /// 百分位取所在桶的上界，并限制在实际的最小/最大值之间
+  fn times(&self) -> FrameTimes {
+    if self.count == 0 {
+      return FrameTimes::default();
+    }
+    let percentile = |p: f64| {
+      let rank = ((self.count - 1) as f64 * p).round() as u64;
+      let mut cumulative = 0;
+      let index = self.buckets.iter().position(|&count| {
+        cumulative += count;
+        cumulative > rank
+      }).unwrap_or(HISTOGRAM_BUCKETS - 1);
+      ((index + 1) as f32 * HISTOGRAM_BUCKET_MS).clamp(self.min, self.max)
+    };
+    FrameTimes {
+      min: self.min,
+      avg: (self.sum / self.count as f64) as f32,
+      max: self.max,
+      p50: percentile(0.5),
+      p95: percentile(0.95),
+      p99: percentile(0.99),
+    }
+  }
+}
+
+/// 帧时间和绘制统计
+pub struct FrameStats {
+  /// 最近`WINDOW`帧的帧时间（毫秒）
+  window: VecDeque<f32>,
+  /// 运行以来所有帧的帧时间分布，退出时输出
+  history: FrameHistogram,
+  /// 正在统计的帧
+  current: DrawStats,
+  /// 上一帧提交的绘制
+  pub last: DrawStats,
+  /// 运行以来提交的绘制
+  total: DrawStats,
+}
+
+impl FrameStats {
+  pub fn new() -> Self {
+    Self {
+      window: VecDeque::with_capacity(WINDOW),
+      history: FrameHistogram::new(),
+      current: DrawStats::default(),
+      last: DrawStats::default(),
+      total: DrawStats::default(),
+    }
+  }
+
+  /// 开始新的一帧，`dt`为距上一帧的时间
+  pub fn frame(&mut self, dt: Duration) {
+    let ms = dt.as_secs_f32() * 1000.0;
+    if self.window.len() == WINDOW {
+      self.window.pop_front();
+    }
+    self.window.push_back(ms);
+    self.history.add(ms);
+    self.last = std::mem::take(&mut self.current);
+    self.total.draw_calls += self.last.draw_calls;
+    self.total.triangles += self.last.triangles;
+    self.total.instances += self.last.instances;
+  }
+
+  /// 记录一次绘制`instances`个实例的网格
+  pub fn draw(&mut self, index_num: u32, instances: u32) {
+    self.current.draw_calls += 1;
+    self.current.triangles += index_num as u64 / 3 * instances as u64;
+    self.current.instances += instances as u64;
+  }
+
+  /// 最近`WINDOW`帧的帧时间分布
+  pub fn times(&self) -> FrameTimes {
+    FrameTimes::new(self.window.iter().copied())
+  }
+
+  /// 运行以来的统计，便于比较不同的运行
+  pub fn to_json(&self) -> String {
+    let frames = self.history.count.max(1) as f64;
+    let (buffer_bytes, texture_bytes) = allocated();
+    let times = self.history.times();
+    format!(
+      concat!(
+        "{{\n",
+        "  \"frames\": {},\n",
+        "  \"seconds\": {:.3},\n",
+        "  \"fps\": {:.2},\n",
+        "  \"frame_ms\": {},\n",
+        "  \"per_frame\": {{\"draw_calls\": {:.1}, \"triangles\": {:.1}, \"instances\": {:.1}}},\n",
+        "  \"allocated\": {{\"buffer_bytes\": {}, \"texture_bytes\": {}}}\n",
+        "}}"
+      ),
+      self.history.count,
+      self.history.sum / 1000.0,
+      times.fps(),
+      times.to_json(),
+      self.total.draw_calls as f64 / frames,
+      self.total.triangles as f64 / frames,
+      self.total.instances as f64 / frames,
+      buffer_bytes,
+      texture_bytes,
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn frame_times_percentiles() {
+    let times = FrameTimes::new((1..=100).rev().map(|ms| ms as f32));
+    assert_eq!(times.min, 1.0);
+    assert_eq!(times.max, 100.0);
+    assert_eq!(times.avg, 50.5);
+    assert_eq!(times.p50, 51.0);
+    assert_eq!(times.p95, 95.0);
+    assert_eq!(times.p99, 99.0);
+    assert!((times.fps() - 1000.0 / 50.5).abs() < 1e-3);
+  }
+
+  #[test]
+  fn frame_times_empty() {
+    let times = FrameTimes::new(std::iter::empty());
+    assert_eq!(times.min, 0.0);
+    assert_eq!(times.max, 0.0);
+    assert_eq!(times.p99, 0.0);
+    assert_eq!(times.fps(), 0.0);
+    assert_eq!(FrameStats::new().times().avg, 0.0);
+  }
+
+  #[test]
+  fn frame_times_to_json() {
+    let times = FrameTimes::new([16.0, 17.5, 33.25].into_iter());
+    assert_eq!(
+      times.to_json(),
+      "{\"min\": 16.000, \"avg\": 22.250, \"max\": 33.250, \"p50\": 17.500, \"p95\": 33.250, \"p99\": 33.250}"
+    );
+  }
+
+  #[test]
+  fn histogram_matches_exact_percentiles() {
+    let mut histogram = FrameHistogram::new();
+    let samples = (0..1000).map(|i| 5.0 + (i % 97) as f32 * 0.37).collect::<Vec<_>>();
+    samples.iter().for_each(|&ms| histogram.add(ms));
+    let exact = FrameTimes::new(samples.iter().copied());
+    let times = histogram.times();
+    assert_eq!((times.min, times.max), (exact.min, exact.max));
+    assert!((times.avg - exact.avg).abs() < 1e-3);
+    for (approx, exact) in [(times.p50, exact.p50), (times.p95, exact.p95), (times.p99, exact.p99)] {
+      assert!((approx - exact).abs() <= HISTOGRAM_BUCKET_MS + 1e-4, "{} vs {}", approx, exact);
+    }
+    // 超出范围的帧计入最后一个桶，百分位不超过实际最大值
+    histogram.add(5000.0);
+    assert_eq!(histogram.times().max, 5000.0);
+    assert_eq!(histogram.buckets.len(), HISTOGRAM_BUCKETS);
+  }
+
+  #[test]
+  fn frame_stats_to_json() {
+    let mut stats = FrameStats::new();
+    stats.draw(36, 2);
+    stats.frame(Duration::from_millis(20));
+    stats.frame(Duration::from_millis(30));
+    let json = stats.to_json();
+    assert!(json.contains("\"frames\": 2,"));
+    assert!(json.contains("\"seconds\": 0.050,"));
+    assert!(json.contains("\"per_frame\": {\"draw_calls\": 0.5, \"triangles\": 12.0, \"instances\": 1.0}"));
+    assert!(json.contains("\"allocated\": {\"buffer_bytes\": "));
+  }
+
+  #[test]
+  fn tracked_subtracts_on_drop() {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let first = Tracked::new((), 100, &COUNTER);
+    let mut second = Tracked::new((), 20, &COUNTER);
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 120);
+    assert_eq!(second.bytes, 20);
+    second = Tracked::new((), 30, &COUNTER); // 替换时旧资源被释放
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 130);
+    drop(first);
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 30);
+    drop(second);
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 0);
+  }
+}
@@ -1,14 +1,19 @@
 use anyhow::*;
 use image::GenericImageView;
+use crate::stats;
 
 pub struct Texture {
-  pub texture: wgpu::Texture,
+  pub texture: stats::Texture,
   pub view: wgpu::TextureView,
   pub sampler: wgpu::Sampler,
 }
 
 impl Texture {
   pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+  /// 带模板的深度格式，开启选中轮廓时使用
+  pub const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+  /// 浮点纹理格式，用于存储HDR数据（可同时作为storage texture和可过滤纹理）
+  pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
 
   pub fn default(device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Self> {
     let bytes = include_bytes!("happy-tree.png");
@@ -33,19 +38,42 @@ impl Texture {
   ) -> Result<Self> {
     let rgba = img.as_rgba8().unwrap();
     let dimensions = img.dimensions();
+    Ok(Self::from_rgba8(device, queue, rgba, dimensions, wgpu::TextureFormat::Rgba8UnormSrgb, label))
+  }
+
+  /// 创建1x1的纯色纹理，用作材质缺省贴图
+  ///
+  /// 颜色类贴图使用`Rgba8UnormSrgb`，法线等数据类贴图使用`Rgba8Unorm`；
+  pub fn from_color(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    color: [u8; 4],
+    format: wgpu::TextureFormat,
+    label: &str,
+  ) -> Self {
+    Self::from_rgba8(device, queue, &color, (1, 1), format, Some(label))
+  }
 
+  pub fn from_rgba8(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    rgba: &[u8],
+    dimensions: (u32, u32),
+    format: wgpu::TextureFormat,
+    label: Option<&str>,
+  ) -> Self {
     let size = wgpu::Extent3d {
       width: dimensions.0,
       height: dimensions.1,
       depth_or_array_layers: 1,
     };
-    let texture = device.create_texture(&wgpu::TextureDescriptor {
+    let texture = stats::create_texture(device, &wgpu::TextureDescriptor {
       label,
       size,
       mip_level_count: 1,
       sample_count: 1,
       dimension: wgpu::TextureDimension::D2,
-      format: wgpu::TextureFormat::Rgba8UnormSrgb,
+      format,
       usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
     });
 
@@ -76,17 +104,19 @@ impl Texture {
       ..Default::default()
     });
 
-    Ok(Self {
+    Self {
       texture,
       view,
       sampler,
-    })
+    }
   }
 
-  /// 创建深度纹理
+  /// 创建深度纹理，`sample_count`须与渲染管线的多重采样数一致
   pub fn create_depth_texture(
     device: &wgpu::Device,
     config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+    format: wgpu::TextureFormat,
     label: &str,
   ) -> Self {
     let size = wgpu::Extent3d {
@@ -98,12 +128,12 @@ impl Texture {
       label: Some(label),
       size,
       mip_level_count: 1,
-      sample_count: 1,
+      sample_count,
       dimension: wgpu::TextureDimension::D2,
-      format: Self::DEPTH_FORMAT,
+      format,
       usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
     };
-    let texture = device.create_texture(&desc);
+    let texture = stats::create_texture(device, &desc);
 
     let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
     let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
@@ -125,4 +155,271 @@ impl Texture {
       sampler,
     }
   }
+
+  /// 创建多重采样颜色纹理，渲染完成后resolve到与之同尺寸、同格式的单采样纹理上
+  pub fn create_msaa_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    label: &str,
+  ) -> Self {
+    let texture = stats::create_texture(device, &wgpu::TextureDescriptor {
+      label: Some(label),
+      size: wgpu::Extent3d {
+        width: config.width,
+        height: config.height,
+        depth_or_array_layers: 1,
+      },
+      mip_level_count: 1,
+      sample_count,
+      dimension: wgpu::TextureDimension::D2,
+      format,
+      usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default()); // 多重采样纹理不能直接采样，仅占位
+
+    Self {
+      texture,
+      view,
+      sampler,
+    }
+  }
+
+  /// 创建离屏渲染目标，可作为后续pass的输入纹理；`mip_level_count`大于1时各级mipmap可分别渲染
+  pub fn create_render_target(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    mip_level_count: u32,
+    format: wgpu::TextureFormat,
+    label: &str,
+  ) -> Self {
+    let texture = stats::create_texture(device, &wgpu::TextureDescriptor {
+      label: Some(label),
+      size: wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+      },
+      mip_level_count,
+      sample_count: 1,
+      dimension: wgpu::TextureDimension::D2,
+      format,
+      usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+      address_mode_u: wgpu::AddressMode::ClampToEdge,
+      address_mode_v: wgpu::AddressMode::ClampToEdge,
+      address_mode_w: wgpu::AddressMode::ClampToEdge,
+      mag_filter: wgpu::FilterMode::Linear,
+      min_filter: wgpu::FilterMode::Linear,
+      mipmap_filter: wgpu::FilterMode::Nearest,
+      ..Default::default()
+    });
+
+    Self {
+      texture,
+      view,
+      sampler,
+    }
+  }
+
+  /// 创建可复制到缓冲中读回的渲染目标（如拾取用的ID纹理）
+  pub fn create_readback_target(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    label: &str,
+  ) -> Self {
+    let texture = stats::create_texture(device, &wgpu::TextureDescriptor {
+      label: Some(label),
+      size: wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+      },
+      mip_level_count: 1,
+      sample_count: 1,
+      dimension: wgpu::TextureDimension::D2,
+      format,
+      usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default()); // 只用于读回，不采样，仅占位
+
+    Self {
+      texture,
+      view,
+      sampler,
+    }
+  }
+
+  /// 创建阴影贴图（深度纹理数组，每个光源一层或六层），附带比较采样器用于PCF
+  ///
+  /// `view_dimension`为`D2Array`（方向光/聚光灯）或`CubeArray`（点光源，`layers`须为6的倍数）；
+  pub fn create_shadow_map(
+    device: &wgpu::Device,
+    size: u32,
+    layers: u32,
+    view_dimension: wgpu::TextureViewDimension,
+    label: &str,
+  ) -> Self {
+    let texture = stats::create_texture(device, &wgpu::TextureDescriptor {
+      label: Some(label),
+      size: wgpu::Extent3d {
+        width: size,
+        height: size,
+        depth_or_array_layers: layers,
+      },
+      mip_level_count: 1,
+      sample_count: 1,
+      dimension: wgpu::TextureDimension::D2,
+      format: Self::DEPTH_FORMAT,
+      usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+      label: Some(label),
+      dimension: Some(view_dimension),
+      ..Default::default()
+    });
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+      address_mode_u: wgpu::AddressMode::ClampToEdge,
+      address_mode_v: wgpu::AddressMode::ClampToEdge,
+      address_mode_w: wgpu::AddressMode::ClampToEdge,
+      mag_filter: wgpu::FilterMode::Linear,
+      min_filter: wgpu::FilterMode::Linear,
+      mipmap_filter: wgpu::FilterMode::Nearest,
+      compare: Some(wgpu::CompareFunction::LessEqual),
+      ..Default::default()
+    });
+
+    Self {
+      texture,
+      view,
+      sampler,
+    }
+  }
+
+  /// 获取纹理数组中某一层的视图，用作渲染目标
+  pub fn layer_view(&self, layer: u32) -> wgpu::TextureView {
+    self.texture.create_view(&wgpu::TextureViewDescriptor {
+      label: None,
+      dimension: Some(wgpu::TextureViewDimension::D2),
+      base_array_layer: layer,
+      array_layer_count: std::num::NonZeroU32::new(1),
+      ..Default::default()
+    })
+  }
+
+  /// 创建HDR立方体贴图，可由计算着色器写入（storage），也可直接上传数据
+  pub fn create_cube(
+    device: &wgpu::Device,
+    size: u32,
+    mip_level_count: u32,
+    label: &str,
+  ) -> Self {
+    let texture = stats::create_texture(device, &wgpu::TextureDescriptor {
+      label: Some(label),
+      size: wgpu::Extent3d {
+        width: size,
+        height: size,
+        depth_or_array_layers: 6,
+      },
+      mip_level_count,
+      sample_count: 1,
+      dimension: wgpu::TextureDimension::D2,
+      format: Self::HDR_FORMAT,
+      usage: wgpu::TextureUsages::TEXTURE_BINDING
+        | wgpu::TextureUsages::STORAGE_BINDING
+        | wgpu::TextureUsages::COPY_SRC
+        | wgpu::TextureUsages::COPY_DST,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+      label: Some(label),
+      dimension: Some(wgpu::TextureViewDimension::Cube),
+      ..Default::default()
+    });
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+      address_mode_u: wgpu::AddressMode::ClampToEdge,
+      address_mode_v: wgpu::AddressMode::ClampToEdge,
+      address_mode_w: wgpu::AddressMode::ClampToEdge,
+      mag_filter: wgpu::FilterMode::Linear,
+      min_filter: wgpu::FilterMode::Linear,
+      mipmap_filter: wgpu::FilterMode::Linear,
+      ..Default::default()
+    });
+
+    Self {
+      texture,
+      view,
+      sampler,
+    }
+  }
+
+  /// 创建二维HDR纹理，可由计算着色器写入（storage）
+  pub fn create_storage_2d(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    label: &str,
+  ) -> Self {
+    let texture = stats::create_texture(device, &wgpu::TextureDescriptor {
+      label: Some(label),
+      size: wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+      },
+      mip_level_count: 1,
+      sample_count: 1,
+      dimension: wgpu::TextureDimension::D2,
+      format: Self::HDR_FORMAT,
+      usage: wgpu::TextureUsages::TEXTURE_BINDING
+        | wgpu::TextureUsages::STORAGE_BINDING
+        | wgpu::TextureUsages::COPY_SRC
+        | wgpu::TextureUsages::COPY_DST,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+      address_mode_u: wgpu::AddressMode::ClampToEdge,
+      address_mode_v: wgpu::AddressMode::ClampToEdge,
+      address_mode_w: wgpu::AddressMode::ClampToEdge,
+      mag_filter: wgpu::FilterMode::Linear,
+      min_filter: wgpu::FilterMode::Linear,
+      mipmap_filter: wgpu::FilterMode::Nearest,
+      ..Default::default()
+    });
+
+    Self {
+      texture,
+      view,
+      sampler,
+    }
+  }
+
+  /// 获取某一级mipmap的二维视图，用于渲染到该级或从该级采样
+  pub fn mip_view(&self, mip_level: u32) -> wgpu::TextureView {
+    self.texture.create_view(&wgpu::TextureViewDescriptor {
+      label: None,
+      dimension: Some(wgpu::TextureViewDimension::D2),
+      base_mip_level: mip_level,
+      mip_level_count: std::num::NonZeroU32::new(1),
+      ..Default::default()
+    })
+  }
+
+  /// 获取某一级mipmap的所有层（立方体的6个面）视图，用于计算着色器写入
+  pub fn mip_layers_view(&self, mip_level: u32) -> wgpu::TextureView {
+    self.texture.create_view(&wgpu::TextureViewDescriptor {
+      label: None,
+      dimension: Some(wgpu::TextureViewDimension::D2Array),
+      base_mip_level: mip_level,
+      mip_level_count: std::num::NonZeroU32::new(1),
+      ..Default::default()
+    })
+  }
 }
@@ -0,0 +1,90 @@
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+/// egui界面：`take_input`和`finish`之间用`context`构建界面，之后在`render`中绘制到交换链
+pub struct Ui {
+  pub visible: bool,
+  context: egui::Context,
+  state: egui_winit::State,
+  render_pass: egui_wgpu_backend::RenderPass,
+  paint_jobs: Vec<egui::ClippedMesh>,
+  /// 累积到下一次绘制时上传或释放的纹理
+  textures_delta: egui::TexturesDelta,
+  scale_factor: f32,
+}
+
+impl Ui {
+  pub fn new(window: &Window, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device) -> Self {
+    let max_texture_side = device.limits().max_texture_dimension_2d as usize;
+    Self {
+      visible: true,
+      context: egui::Context::default(),
+      state: egui_winit::State::new(max_texture_side, window),
+      render_pass: egui_wgpu_backend::RenderPass::new(device, config.format, 1),
+      paint_jobs: vec![],
+      textures_delta: egui::TexturesDelta::default(),
+      scale_factor: window.scale_factor() as f32,
+    }
+  }
+
+  /// 将事件交给egui，返回true表示事件已被界面使用（如点击窗口、在输入框中输入），场景不应再处理
+  pub fn on_event(&mut self, event: &WindowEvent) -> bool {
+    if !self.visible {
+      return false;
+    }
+    if self.state.on_event(&self.context, event) {
+      return true;
+    }
+    match event {
+      WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. } => self.context.wants_pointer_input(),
+      WindowEvent::KeyboardInput { .. } | WindowEvent::ReceivedCharacter(_) => self.context.wants_keyboard_input(),
+      _ => false,
+    }
+  }
+
+  /// egui上下文（内部为引用计数，克隆开销很小）
+  pub fn context(&self) -> egui::Context {
+    self.context.clone()
+  }
+
+  pub fn take_input(&mut self, window: &Window) -> egui::RawInput {
+    self.scale_factor = window.scale_factor() as f32;
+    self.state.take_egui_input(window)
+  }
+
+  /// 处理一帧界面的输出，生成绘制数据
+  pub fn finish(&mut self, window: &Window, output: egui::FullOutput) {
+    self.state.handle_platform_output(window, &self.context, output.platform_output);
+    self.paint_jobs = self.context.tessellate(output.shapes);
+    self.textures_delta.append(output.textures_delta);
+  }
+
+  /// 在后处理之后绘制到`view`上
+  pub fn render(
+    &mut self,
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    config: &wgpu::SurfaceConfiguration,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+  ) {
+    let textures_delta = std::mem::take(&mut self.textures_delta);
+    if let Err(error) = self.render_pass.add_textures(device, queue, &textures_delta) {
+      eprintln!("egui textures: {:?}", error);
+    }
+    if self.visible {
+      let screen = egui_wgpu_backend::ScreenDescriptor {
+        physical_width: config.width,
+        physical_height: config.height,
+        scale_factor: self.scale_factor,
+      };
+      self.render_pass.update_buffers(device, queue, &self.paint_jobs, &screen);
+      if let Err(error) = self.render_pass.execute(encoder, view, &self.paint_jobs, &screen, None) {
+        eprintln!("egui render: {:?}", error);
+      }
+    }
+    if let Err(error) = self.render_pass.remove_textures(textures_delta) {
+      eprintln!("egui textures: {:?}", error);
+    }
+  }
+}
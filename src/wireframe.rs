@@ -0,0 +1,228 @@
+use crate::shape::{
+  Vertex,
+  InstanceData,
+  Mesh
+};
+use crate::texture::Texture;
+use crate::stats;
+
+/// 线框显示方式
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WireframeMode {
+  Off,
+  /// 只显示线框（被遮挡的边会隐藏）
+  Wireframe,
+  /// 在着色结果上叠加线框
+  Overlay,
+}
+
+/// 线框uniform变量，与wireframe.wgsl中的`WireframeUniform`对应
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct WireframeUniform {
+  color: [f32; 4],
+  width: f32,
+  padding: [f32; 3],
+}
+
+pub struct Wireframe {
+  pub mode: WireframeMode,
+  /// 是否使用`PolygonMode::Line`，否则使用重心坐标着色器
+  line_mode: bool,
+  uniform: WireframeUniform,
+  buffer: stats::Buffer,
+  group: wgpu::BindGroup,
+  layout: wgpu::BindGroupLayout,
+  pipeline: wgpu::RenderPipeline,
+}
+
+impl WireframeMode {
+  pub const ALL: [WireframeMode; 3] = [
+    WireframeMode::Off,
+    WireframeMode::Wireframe,
+    WireframeMode::Overlay,
+  ];
+
+  /// 命令行中使用的名称
+  pub fn name(&self) -> &'static str {
+    match self {
+      WireframeMode::Off => "off",
+      WireframeMode::Wireframe => "wire",
+      WireframeMode::Overlay => "overlay",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL.iter().copied().find(|mode| mode.name() == name)
+  }
+
+  pub fn next(&self) -> Self {
+    let index = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+    Self::ALL[(index + 1) % Self::ALL.len()]
+  }
+}
+
+impl Wireframe {
+  /// 设备开启了`POLYGON_MODE_LINE`时直接绘制线框
+  pub fn new(mode: WireframeMode, camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+    let line_mode = device.features().contains(wgpu::Features::POLYGON_MODE_LINE);
+    let uniform = WireframeUniform {
+      color: mode_color(mode),
+      width: 1.5,
+      padding: [0.0; 3],
+    };
+    let buffer = stats::create_buffer_init(device, &wgpu::util::BufferInitDescriptor {
+      label: Some("Wireframe buffer"),
+      contents: bytemuck::cast_slice(&[uniform]),
+      usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    });
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+      label: Some("wireframe bind group layout"),
+      entries: &[
+        wgpu::BindGroupLayoutEntry {
+          binding: 0,
+          visibility: wgpu::ShaderStages::FRAGMENT,
+          ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None
+          },
+          count: None
+        }
+      ]
+    });
+    let group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+      label: Some("wireframe bind group"),
+      layout: &layout,
+      entries: &[
+        wgpu::BindGroupEntry {
+          binding: 0,
+          resource: buffer.as_entire_binding()
+        }
+      ]
+    });
+    let pipeline = create_pipeline(line_mode, &layout, camera_layout, sample_count, depth_format, device);
+    Self {
+      mode,
+      line_mode,
+      uniform,
+      buffer,
+      group,
+      layout,
+      pipeline,
+    }
+  }
+
+  /// 是否以`PolygonMode::Line`绘制，否则使用重心坐标着色器
+  pub fn line_mode(&self) -> bool {
+    self.line_mode
+  }
+
+  /// 重心坐标线框需要展开后的顶点缓冲
+  pub fn needs_unindexed(&self) -> bool {
+    !self.line_mode
+  }
+
+  /// 多重采样数变化后重建管线
+  pub fn set_sample_count(&mut self, camera_layout: &wgpu::BindGroupLayout, sample_count: u32, depth_format: wgpu::TextureFormat, device: &wgpu::Device) {
+    self.pipeline = create_pipeline(self.line_mode, &self.layout, camera_layout, sample_count, depth_format, device);
+  }
+
+  pub fn set_mode(&mut self, mode: WireframeMode, queue: &wgpu::Queue) {
+    self.mode = mode;
+    self.uniform.color = mode_color(mode);
+    queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]));
+  }
+
+  /// 设置管线和绑定组，之后可多次调用`draw`
+  pub fn begin<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera_group: &'a wgpu::BindGroup) {
+    render_pass.set_pipeline(&self.pipeline);
+    render_pass.set_bind_group(0, &self.group, &[]);
+    render_pass.set_bind_group(1, camera_group, &[]);
+  }
+
+  pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, mesh: &'a Mesh, instance_buffer: &'a wgpu::Buffer, instances: std::ops::Range<u32>) {
+    render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+    if self.line_mode {
+      render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+      render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+      render_pass.draw_indexed(0..mesh.index_num, 0, instances);
+    } else if let Some(buffer) = &mesh.unindexed_buffer {
+      render_pass.set_vertex_buffer(0, buffer.slice(..));
+      render_pass.draw(0..mesh.index_num, instances);
+    }
+  }
+}
+
+/// 只显示线框时使用更亮的颜色，叠加时使用与表面对比明显的绿色
+fn mode_color(mode: WireframeMode) -> [f32; 4] {
+  if mode == WireframeMode::Wireframe { [0.8, 0.9, 1.0, 1.0] } else { [0.0, 1.0, 0.4, 1.0] }
+}
+
+fn create_pipeline(
+  line_mode: bool,
+  layout: &wgpu::BindGroupLayout,
+  camera_layout: &wgpu::BindGroupLayout,
+  sample_count: u32,
+  depth_format: wgpu::TextureFormat,
+  device: &wgpu::Device,
+) -> wgpu::RenderPipeline {
+  let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+    label: Some("Wireframe Shader"),
+    source: wgpu::ShaderSource::Wgsl(include_str!("wireframe.wgsl").into())
+  });
+  let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+    label: Some("Wireframe Pipeline Layout"),
+    bind_group_layouts: &[layout, camera_layout],
+    push_constant_ranges: &[]
+  });
+  let (vs_entry, fs_entry) = if line_mode { ("vs_line", "fs_line") } else { ("vs_barycentric", "fs_barycentric") };
+  device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    label: Some("Wireframe Pipeline"),
+    layout: Some(&pipeline_layout),
+    vertex: wgpu::VertexState {
+      module: &shader,
+      entry_point: vs_entry,
+      buffers: &[
+        Vertex::desc(),
+        InstanceData::desc()
+      ]
+    },
+    fragment: Some(wgpu::FragmentState {
+      module: &shader,
+      entry_point: fs_entry,
+      targets: &[wgpu::ColorTargetState {
+        format: Texture::HDR_FORMAT,
+        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+        write_mask: wgpu::ColorWrites::ALL,
+      }],
+    }),
+    primitive: wgpu::PrimitiveState {
+      topology: wgpu::PrimitiveTopology::TriangleList,
+      strip_index_format: None,
+      front_face: wgpu::FrontFace::Ccw,
+      cull_mode: Some(wgpu::Face::Back),
+      polygon_mode: if line_mode { wgpu::PolygonMode::Line } else { wgpu::PolygonMode::Fill },
+      unclipped_depth: false,
+      conservative: false
+    },
+    // 与已绘制的表面深度比较，不写入深度；向相机方向偏移避免线条被表面遮住
+    depth_stencil: Some(wgpu::DepthStencilState {
+      format: depth_format,
+      depth_write_enabled: false,
+      depth_compare: wgpu::CompareFunction::LessEqual,
+      stencil: wgpu::StencilState::default(),
+      bias: wgpu::DepthBiasState {
+        constant: -2,
+        slope_scale: -1.0,
+        clamp: 0.0
+      }
+    }),
+    multisample: wgpu::MultisampleState {
+      count: sample_count,
+      mask: !0,
+      alpha_to_coverage_enabled: false,
+    },
+    multiview: None
+  })
+}
@@ -0,0 +1,85 @@
+// 线框：支持线框多边形模式时直接光栅化三角形的边，否则由重心坐标计算到边的距离
+
+struct VertexInput {
+  [[location(0)]] position: vec3<f32>;
+  [[location(1)]] color: vec3<f32>;
+  [[location(2)]] uv: vec2<f32>;
+  [[location(3)]] normal: vec3<f32>;
+};
+
+struct InstanceInput {
+  [[location(4)]] model_0: vec4<f32>;
+  [[location(5)]] model_1: vec4<f32>;
+  [[location(6)]] model_2: vec4<f32>;
+  [[location(7)]] model_3: vec4<f32>;
+};
+
+struct VertexOutput {
+  [[builtin(position)]] clip_position: vec4<f32>;
+  [[location(0)]] barycentric: vec3<f32>;
+};
+
+struct CameraUnifrom {
+  view_projection: mat4x4<f32>;
+  eye_position: vec4<f32>;
+};
+
+struct WireframeUniform {
+  color: vec4<f32>;
+  /// 重心坐标线框的线宽（像素）
+  width: f32;
+  padding_0: f32;
+  padding_1: f32;
+  padding_2: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> wireframe: WireframeUniform;
+
+[[group(1), binding(0)]]
+var<uniform> camera: CameraUnifrom;
+
+fn clip_position(inputData: VertexInput, instanceData: InstanceInput) -> vec4<f32> {
+  let model_matrix = mat4x4<f32>(
+    instanceData.model_0,
+    instanceData.model_1,
+    instanceData.model_2,
+    instanceData.model_3
+  );
+  return camera.view_projection * (model_matrix * vec4<f32>(inputData.position, 1.0));
+}
+
+[[stage(vertex)]]
+fn vs_line(inputData: VertexInput, instanceData: InstanceInput) -> VertexOutput {
+  var outputData: VertexOutput;
+  outputData.clip_position = clip_position(inputData, instanceData);
+  outputData.barycentric = vec3<f32>(0.0);
+  return outputData;
+}
+
+[[stage(fragment)]]
+fn fs_line(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  return wireframe.color;
+}
+
+/// 顶点缓冲已按索引展开，顶点序号模3即为顶点在三角形中的位置
+[[stage(vertex)]]
+fn vs_barycentric(inputData: VertexInput, instanceData: InstanceInput, [[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+  var outputData: VertexOutput;
+  outputData.clip_position = clip_position(inputData, instanceData);
+  let corner = vertex_index % 3u;
+  outputData.barycentric = vec3<f32>(f32(corner == 0u), f32(corner == 1u), f32(corner == 2u));
+  return outputData;
+}
+
+[[stage(fragment)]]
+fn fs_barycentric(inputData: VertexOutput) -> [[location(0)]] vec4<f32> {
+  // 用屏幕空间导数将重心坐标换算为到最近边的像素距离
+  let distance = inputData.barycentric / fwidth(inputData.barycentric);
+  let edge = min(distance.x, min(distance.y, distance.z));
+  let alpha = 1.0 - smoothStep(wireframe.width - 1.0, wireframe.width, edge);
+  if (alpha <= 0.0) {
+    discard;
+  }
+  return vec4<f32>(wireframe.color.rgb, wireframe.color.a * alpha);
+}